@@ -0,0 +1,86 @@
+//! Asynchronous generation for single threaded executors like the ones used in the browser.
+//!
+//! The work is done by the synchronous core, the future only hands control back to the executor
+//! between the decoding of the image and every [`YIELD_INTERVAL`] packed pixels. No runtime is
+//! required so it works with any executor.
+
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use crate::{imagedecode, pack::BitPacker, BitFlow, FontMode};
+
+/// Number of pixels packed before control is handed back to the executor
+pub const YIELD_INTERVAL: usize = 4096;
+
+/// Generate a single monochromatic font into a new buffer without blocking the executor for the
+/// whole generation, the bytes are the same as with [`crate::generate_monochromatic_to_vec`].
+///
+/// # Errors
+/// An error is returned when the given image data can not be decoded as png.
+pub async fn generate_monochromatic_async(
+    data: &[u8],
+    font_mode: FontMode,
+    bit_flow: BitFlow,
+) -> crate::Result<Vec<u8>> {
+    let decoded = imagedecode::MonochromaticColorIter::new(data, font_mode)?;
+    YieldNow::default().await;
+
+    let mut out = Vec::new();
+    let mut packer = BitPacker::new(&mut out, bit_flow);
+    for (i, pix) in decoded.enumerate() {
+        packer.push(pix)?;
+        if i % YIELD_INTERVAL == YIELD_INTERVAL - 1 {
+            YieldNow::default().await;
+        }
+    }
+    packer.finish()?;
+    Ok(out)
+}
+
+/// A future that is pending once and wakes itself, so the executor can run other tasks first
+#[derive(Default)]
+struct YieldNow {
+    /// Wether the future was already polled once
+    yielded: bool,
+}
+
+impl Future for YieldNow {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if self.yielded {
+            return Poll::Ready(());
+        }
+        self.yielded = true;
+        cx.waker().wake_by_ref();
+        Poll::Pending
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{generate_monochromatic_to_vec, testutil::gray};
+
+    /// The async path produces exactly the bytes of the sync path
+    #[tokio::test]
+    async fn async_matches_sync() {
+        let pixels: Vec<u8> = (0..80u32 * 70)
+            .map(|i| if i % 3 == 0 { 0 } else { 255 })
+            .collect();
+        let png = gray(80, 70, &pixels);
+        for (font_mode, bit_flow) in [
+            (FontMode::Row, BitFlow::Mtl),
+            (FontMode::Column, BitFlow::Ltm),
+        ] {
+            let sync = generate_monochromatic_to_vec(&png, font_mode, bit_flow).unwrap();
+            let asynchronous = generate_monochromatic_async(&png, font_mode, bit_flow)
+                .await
+                .unwrap();
+            assert_eq!(sync, asynchronous);
+        }
+    }
+}
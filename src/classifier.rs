@@ -0,0 +1,209 @@
+//! Pluggable decision wether a pixel of a monochromatic font is on.
+
+use std::io::Write;
+
+use crate::{
+    imagedecode::{self, scan_index, Rgba, RgbaColorIter},
+    pack::BitPacker,
+    BitFlow, FontMode,
+};
+
+/// Decides wether a decoded pixel is on
+pub trait PixelClassifier {
+    /// Wether the pixel is on, `has_alpha` tells wether the image it is from has an alpha channel
+    fn is_on(&self, px: Rgba, has_alpha: bool) -> bool;
+}
+
+impl<F: Fn(Rgba) -> bool> PixelClassifier for F {
+    fn is_on(&self, px: Rgba, _has_alpha: bool) -> bool {
+        self(px)
+    }
+}
+
+/// The classification of [`crate::generate_monochromatic`]: a pixel is on if it is at least as
+/// opaque as the threshold. In images without alpha channel any of its color components also has
+/// to be below the threshold.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DefaultClassifier {
+    /// The cutoff for the alpha and the color components
+    pub threshold: u8,
+}
+
+impl Default for DefaultClassifier {
+    fn default() -> Self {
+        Self {
+            threshold: imagedecode::DEFAULT_THRESHOLD,
+        }
+    }
+}
+
+impl PixelClassifier for DefaultClassifier {
+    fn is_on(&self, px: Rgba, has_alpha: bool) -> bool {
+        px.a >= self.threshold
+            && (has_alpha
+                || px.r < self.threshold
+                || px.g < self.threshold
+                || px.b < self.threshold)
+    }
+}
+
+/// Classification by an exact color, for sheets drawing the glyphs in a color like magenta on a
+/// background of another color: a pixel is on if none of its red, green and blue components
+/// differs from the target by more than the tolerance. Fully transparent pixels are off.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OnColor {
+    /// The color of on pixels, its alpha is ignored
+    pub target: Rgba,
+    /// The largest difference of a single component to the target that is still on
+    pub tolerance: u8,
+}
+
+impl PixelClassifier for OnColor {
+    fn is_on(&self, px: Rgba, _has_alpha: bool) -> bool {
+        px.a != 0
+            && px
+                .rgb()
+                .into_iter()
+                .zip(self.target.rgb())
+                .all(|(c, t)| c.abs_diff(t) <= self.tolerance)
+    }
+}
+
+/// Generate a single monochromatic font with the classifier deciding which pixels are on,
+/// returns the number of bytes written. The alignment padding of the font mode is always off.
+///
+/// # Errors
+/// An error is returned when the given image data can not be decoded as png or writing to the
+/// `out` writer fails.
+pub fn generate_monochromatic_with<C: PixelClassifier>(
+    data: &[u8],
+    font_mode: FontMode,
+    bit_flow: BitFlow,
+    classifier: C,
+    out: &mut impl Write,
+) -> crate::Result<usize> {
+    let decoded = RgbaColorIter::new(data, font_mode)?;
+    let (width, height) = (decoded.width(), decoded.height());
+    let has_alpha = decoded.has_alpha();
+    let pixel_count = width.saturating_mul(height);
+
+    let mut packer = BitPacker::new(out, bit_flow);
+    for (idx, px) in decoded.enumerate() {
+        let in_image = scan_index(font_mode, width, height, idx).is_some_and(|n| n < pixel_count);
+        packer.push(in_image && classifier.is_on(px, has_alpha))?;
+    }
+    packer.finish()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{generate_monochromatic, testutil, FontGenerator};
+
+    /// A classifier keyed on the red channel only ignores the other components, the default
+    /// classifier matches the generator for images without alpha
+    #[test]
+    fn red_channel_classifier() {
+        /// On if the pixel is mostly red
+        struct Red;
+
+        impl PixelClassifier for Red {
+            fn is_on(&self, px: Rgba, _has_alpha: bool) -> bool {
+                px.r >= 128
+            }
+        }
+
+        let png = testutil::rgb(
+            4,
+            3,
+            &[[255, 0, 0], [0, 255, 0], [200, 200, 200], [0, 0, 0]].repeat(3),
+        );
+        let mut out = Vec::new();
+        let written =
+            generate_monochromatic_with(&png, FontMode::ByteColumn, BitFlow::Mtl, Red, &mut out)
+                .unwrap();
+        assert_eq!(written, 4);
+        assert_eq!(out, [0xE0, 0x00, 0xE0, 0x00]);
+
+        let mut default = Vec::new();
+        generate_monochromatic_with(
+            &png,
+            FontMode::Row,
+            BitFlow::Mtl,
+            DefaultClassifier::default(),
+            &mut default,
+        )
+        .unwrap();
+        let mut expected = Vec::new();
+        generate_monochromatic(&png, FontMode::Row, BitFlow::Mtl, &mut expected).unwrap();
+        assert_eq!(default, expected);
+    }
+
+    /// The default classifier counts light opaque pixels of images with alpha channel as on, like
+    /// the generator
+    #[test]
+    fn default_classifier_alpha() {
+        let png = testutil::rgba(
+            4,
+            2,
+            &[
+                [255, 255, 255, 255],
+                [0, 0, 0, 255],
+                [255, 255, 255, 0],
+                [0, 0, 0, 100],
+                [200, 200, 200, 128],
+                [0, 0, 0, 127],
+                [255, 0, 0, 255],
+                [0, 0, 0, 0],
+            ],
+        );
+        let mut classified = Vec::new();
+        generate_monochromatic_with(
+            &png,
+            FontMode::Row,
+            BitFlow::Mtl,
+            DefaultClassifier::default(),
+            &mut classified,
+        )
+        .unwrap();
+        let mut expected = Vec::new();
+        generate_monochromatic(&png, FontMode::Row, BitFlow::Mtl, &mut expected).unwrap();
+        assert_eq!(classified, expected);
+        assert_eq!(classified, [0xCA]);
+    }
+
+    /// Only pixels of the target color within the tolerance are on, whatever their luminance
+    #[test]
+    fn on_color() {
+        let magenta: Rgba = "#FF00FF".parse().unwrap();
+        assert_eq!(magenta, Rgba::new(255, 0, 255, 255));
+        assert!("FF00F".parse::<Rgba>().is_err());
+
+        let (m, g, near) = ([255, 0, 255], [0, 128, 0], [250, 6, 250]);
+        let png = testutil::rgb(4, 2, &[m, g, m, near, g, m, g, g]);
+        let generate = |tolerance| {
+            let on_color = OnColor {
+                target: magenta,
+                tolerance,
+            };
+            let mut out = Vec::new();
+            FontGenerator::new()
+                .with_on_color(Some(on_color))
+                .generate(&png, &mut out)
+                .unwrap();
+            let mut classified = Vec::new();
+            generate_monochromatic_with(
+                &png,
+                FontMode::Row,
+                BitFlow::Mtl,
+                on_color,
+                &mut classified,
+            )
+            .unwrap();
+            assert_eq!(out, classified);
+            out
+        };
+        assert_eq!(generate(0), [0xA4]);
+        assert_eq!(generate(8), [0xB4]);
+    }
+}
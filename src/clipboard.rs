@@ -0,0 +1,90 @@
+//! Copying rendered dumps to the clipboard.
+
+use crate::{dump_with_options, DumpFormat, DumpOptions, GenerationError};
+
+/// A clipboard the rendered text can be copied to
+pub trait ClipboardBackend {
+    /// Replace the content of the clipboard with `text`
+    ///
+    /// # Errors
+    /// An error is returned when the clipboard can not be accessed.
+    fn set_text(&mut self, text: String) -> crate::Result<()>;
+}
+
+/// Render the bytes in the given format and copy the text to the clipboard.
+///
+/// # Errors
+/// An error is returned when the clipboard can not be accessed.
+pub fn dump_to_clipboard(
+    bytes: &[u8],
+    format: DumpFormat,
+    options: &DumpOptions,
+    clipboard: &mut impl ClipboardBackend,
+) -> crate::Result<()> {
+    let mut rendered = Vec::new();
+    dump_with_options(bytes, format, options, &mut rendered)?;
+    let text =
+        String::from_utf8(rendered).map_err(|e| GenerationError::ClipboardError(e.to_string()))?;
+    clipboard.set_text(text)
+}
+
+/// The clipboard of the system
+#[cfg(feature = "clipboard")]
+pub struct SystemClipboard(arboard::Clipboard);
+
+#[cfg(feature = "clipboard")]
+impl SystemClipboard {
+    /// Connect to the clipboard of the system
+    ///
+    /// # Errors
+    /// An error is returned when no clipboard is available.
+    pub fn new() -> crate::Result<Self> {
+        arboard::Clipboard::new()
+            .map(Self)
+            .map_err(|e| GenerationError::ClipboardError(e.to_string()))
+    }
+}
+
+#[cfg(feature = "clipboard")]
+impl ClipboardBackend for SystemClipboard {
+    fn set_text(&mut self, text: String) -> crate::Result<()> {
+        self.0
+            .set_text(text)
+            .map_err(|e| GenerationError::ClipboardError(e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::dump;
+
+    /// Clipboard that keeps the copied text in memory
+    #[derive(Default)]
+    struct MockClipboard(Option<String>);
+
+    impl ClipboardBackend for MockClipboard {
+        fn set_text(&mut self, text: String) -> crate::Result<()> {
+            self.0 = Some(text);
+            Ok(())
+        }
+    }
+
+    /// The rendered dump ends up in the clipboard unchanged
+    #[test]
+    fn dump_delivered_intact() {
+        let bytes: Vec<u8> = (0..20).collect();
+        let mut rendered = Vec::new();
+        dump(&bytes, DumpFormat::Hex, &mut rendered).unwrap();
+
+        let mut clipboard = MockClipboard::default();
+        dump_to_clipboard(
+            &bytes,
+            DumpFormat::Hex,
+            &DumpOptions::default(),
+            &mut clipboard,
+        )
+        .unwrap();
+        assert_eq!(clipboard.0.map(String::into_bytes), Some(rendered));
+    }
+}
@@ -0,0 +1,151 @@
+//! Derivation of the codepoints glyphs represent.
+
+use std::{ops::RangeInclusive, path::Path, str::FromStr};
+
+/// Derive the codepoint a glyph file represents from its file name.
+///
+/// The file stem is either a single literal character (`A.png`), a hexadecimal number (`0041.png`)
+/// or a hexadecimal number in the `U+XXXX` notation (`U+0041.png`).
+pub fn codepoint_from_filename(path: &Path) -> Option<u32> {
+    let stem = path.file_stem()?.to_str()?;
+    let mut chars = stem.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => Some(u32::from(c)),
+        _ => parse_hex(stem.strip_prefix("U+").unwrap_or(stem)),
+    }
+}
+
+/// Derive the codepoints of a glyph sheet from its file name.
+///
+/// Besides the single codepoints of [`codepoint_from_filename`] the file stem can be a range of
+/// hexadecimal numbers like `U+0041-U+005A.png` or `0041-005A.png`, the glyphs of the sheet
+/// represent the codepoints of the range in ascending order.
+pub fn codepoint_range_from_filename(path: &Path) -> Option<RangeInclusive<u32>> {
+    if let Some(codepoint) = codepoint_from_filename(path) {
+        return Some(codepoint..=codepoint);
+    }
+
+    let stem = path.file_stem()?.to_str()?;
+    let (start, end) = stem.split_once('-')?;
+    let start = parse_hex(start.strip_prefix("U+").unwrap_or(start))?;
+    let end = parse_hex(end.strip_prefix("U+").unwrap_or(end))?;
+    (start <= end).then_some(start..=end)
+}
+
+/// Parse a plain hexadecimal number
+fn parse_hex(s: &str) -> Option<u32> {
+    if s.is_empty() || !s.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    u32::from_str_radix(s, 16).ok()
+}
+
+/// Parse a single codepoint given as `0x` or `U+` prefixed hexadecimal or decimal number
+fn parse_codepoint(s: &str) -> Option<u32> {
+    let s = s.trim();
+    match s.strip_prefix("0x").or_else(|| s.strip_prefix("U+")) {
+        Some(hex) => parse_hex(hex),
+        None => s.parse().ok(),
+    }
+}
+
+/// A set of codepoint ranges, parsed from a list like `0x30-0x39,0x41-0x5A,0x20`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CodepointRanges(Vec<RangeInclusive<u32>>);
+
+impl CodepointRanges {
+    /// Wether the codepoint is part of any range
+    pub fn contains(&self, codepoint: u32) -> bool {
+        self.0.iter().any(|range| range.contains(&codepoint))
+    }
+
+    /// Iterate over every codepoint of all ranges
+    pub fn codepoints(&self) -> impl Iterator<Item = u32> + '_ {
+        self.0.iter().flat_map(Clone::clone)
+    }
+}
+
+impl FromStr for CodepointRanges {
+    type Err = CodepointRangesParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.split(',')
+            .map(|part| {
+                let range = match part.split_once('-') {
+                    Some((start, end)) => parse_codepoint(start).zip(parse_codepoint(end)),
+                    None => parse_codepoint(part).map(|cp| (cp, cp)),
+                };
+                range
+                    .filter(|(start, end)| start <= end)
+                    .map(|(start, end)| start..=end)
+                    .ok_or_else(|| CodepointRangesParseError(part.to_owned()))
+            })
+            .collect::<Result<_, _>>()
+            .map(Self)
+    }
+}
+
+/// A codepoint range list contained an invalid range
+#[derive(Debug, Clone, thiserror::Error)]
+#[error("Invalid codepoint range: {0}")]
+pub struct CodepointRangesParseError(String);
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Ranges and single values are parsed
+    #[test]
+    fn parse_ranges() {
+        let ranges: CodepointRanges = "0x30-0x39,U+0041-U+005A,32".parse().unwrap();
+        assert!(ranges.contains(0x30));
+        assert!(ranges.contains(0x39));
+        assert!(!ranges.contains(0x3A));
+        assert!(ranges.contains(u32::from('Q')));
+        assert!(ranges.contains(u32::from(' ')));
+        assert!(!ranges.contains(u32::from('q')));
+        assert!("0x39-0x30".parse::<CodepointRanges>().is_err());
+        assert!("0x30-".parse::<CodepointRanges>().is_err());
+    }
+
+    /// Literal characters, hex numbers and the U+ notation name codepoints, other names don't
+    #[test]
+    fn filename_patterns() {
+        let codepoint = |name| codepoint_from_filename(Path::new(name));
+        assert_eq!(codepoint("0041.png"), Some(0x41));
+        assert_eq!(codepoint("U+00E9.png"), Some(0xE9));
+        assert_eq!(codepoint("A.png"), Some(u32::from('A')));
+        assert_eq!(codepoint("7.png"), Some(u32::from('7')));
+        assert_eq!(codepoint("ä.png"), Some(0xE4));
+        assert_eq!(codepoint("README.png"), None);
+        assert_eq!(codepoint("U+.png"), None);
+        assert_eq!(codepoint("u+0041.png"), None);
+    }
+
+    /// Single codepoints and ranges are derived from file names
+    #[test]
+    fn filename_ranges() {
+        assert_eq!(codepoint_from_filename(Path::new("U+0041.png")), Some(0x41));
+        assert_eq!(
+            codepoint_range_from_filename(Path::new("glyphs/U+0041.png")),
+            Some(0x41..=0x41)
+        );
+
+        let sheet = codepoint_range_from_filename(Path::new("U+0041-U+005A.png")).unwrap();
+        let chars: String = sheet.filter_map(char::from_u32).collect();
+        assert_eq!(chars, "ABCDEFGHIJKLMNOPQRSTUVWXYZ");
+        assert_eq!(
+            codepoint_range_from_filename(Path::new("0030-0039.png")),
+            Some(0x30..=0x39)
+        );
+        assert_eq!(
+            codepoint_from_filename(Path::new("U+0041-U+005A.png")),
+            None
+        );
+        assert_eq!(
+            codepoint_range_from_filename(Path::new("U+005A-U+0041.png")),
+            None
+        );
+        assert_eq!(codepoint_range_from_filename(Path::new("a-b-c.png")), None);
+    }
+}
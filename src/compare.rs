@@ -0,0 +1,102 @@
+//! Comparison of generated fonts for regression testing.
+
+/// The position of a single bit inside a font
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BitPosition {
+    /// The offset of the byte inside the font
+    pub byte: usize,
+    /// The bit inside the byte, 0 is the least significant bit
+    pub bit: u8,
+}
+
+/// The differences between two fonts, see [`compare_fonts`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DiffReport {
+    /// The positions of all differing bits in ascending order
+    pub positions: Vec<BitPosition>,
+    /// The length of the first font in bytes
+    pub len_a: usize,
+    /// The length of the second font in bytes
+    pub len_b: usize,
+}
+
+impl DiffReport {
+    /// The number of differing bits
+    pub fn differing_bits(&self) -> usize {
+        self.positions.len()
+    }
+
+    /// Wether both fonts are identical
+    pub fn is_identical(&self) -> bool {
+        self.positions.is_empty() && self.len_a == self.len_b
+    }
+
+    /// Wether the fonts have the same length and differ in at most `tolerance` bits
+    pub fn within_tolerance(&self, tolerance: usize) -> bool {
+        self.len_a == self.len_b && self.differing_bits() <= tolerance
+    }
+}
+
+/// Compare two generated fonts bit by bit.
+///
+/// If the fonts differ in length the missing bytes of the shorter font count as 0.
+pub fn compare_fonts(a: &[u8], b: &[u8]) -> DiffReport {
+    let len = a.len().max(b.len());
+    let positions = (0..len)
+        .flat_map(|byte| {
+            let a = a.get(byte).copied().unwrap_or(0);
+            let b = b.get(byte).copied().unwrap_or(0);
+            let diff = a ^ b;
+            (0..8u8)
+                .rev()
+                .filter(move |&bit| diff & (1 << bit) != 0)
+                .map(move |bit| BitPosition { byte, bit })
+        })
+        .collect();
+
+    DiffReport {
+        positions,
+        len_a: a.len(),
+        len_b: b.len(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{generate_monochromatic, testutil, BitFlow, FontMode};
+
+    /// Generate the glyph given as picture
+    fn generate(rows: &[&str]) -> Vec<u8> {
+        let mut out = Vec::new();
+        generate_monochromatic(
+            &testutil::picture(rows),
+            FontMode::Row,
+            BitFlow::Mtl,
+            &mut out,
+        )
+        .unwrap();
+        out
+    }
+
+    /// Two glyphs differing in three pixels differ in exactly three bits
+    #[test]
+    fn differing_bits() {
+        let a = generate(&["..####..", ".#....#.", ".######."]);
+        let b = generate(&["..####..", "##....#.", "#####.#."]);
+
+        let diff = compare_fonts(&a, &b);
+        assert_eq!(diff.differing_bits(), 3);
+        assert_eq!(
+            diff.positions,
+            [
+                BitPosition { byte: 1, bit: 7 },
+                BitPosition { byte: 2, bit: 7 },
+                BitPosition { byte: 2, bit: 2 }
+            ]
+        );
+        assert!(diff.within_tolerance(3));
+        assert!(!diff.within_tolerance(2));
+        assert!(compare_fonts(&a, &a).is_identical());
+    }
+}
@@ -0,0 +1,102 @@
+//! Run length encoding of packed glyphs.
+
+use crate::GenerationError;
+
+/// How the bytes of a glyph are stored in the font
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Compression {
+    /// The packed bytes are stored as they are
+    #[default]
+    None,
+    /// The packed bytes are run length encoded, see [`rle_encode`]
+    Rle,
+}
+
+impl Compression {
+    /// The flag stored for this compression in the glyph index
+    pub fn flag(self) -> u8 {
+        match self {
+            Self::None => 0,
+            Self::Rle => 1,
+        }
+    }
+
+    /// The compression stored as `flag` in the glyph index
+    pub fn from_flag(flag: u8) -> Option<Self> {
+        match flag {
+            0 => Some(Self::None),
+            1 => Some(Self::Rle),
+            _ => None,
+        }
+    }
+
+    /// Encode the packed bytes with every compression and return the smallest result, the
+    /// uncompressed bytes are kept on ties.
+    pub fn smallest(bytes: Vec<u8>) -> (Self, Vec<u8>) {
+        let encoded = rle_encode(&bytes);
+        if encoded.len() < bytes.len() {
+            (Self::Rle, encoded)
+        } else {
+            (Self::None, bytes)
+        }
+    }
+
+    /// Restore the packed bytes of a glyph stored with this compression
+    ///
+    /// # Errors
+    /// An error is returned when the bytes are not valid for the compression.
+    pub fn decompress(self, bytes: &[u8]) -> crate::Result<Vec<u8>> {
+        match self {
+            Self::None => Ok(bytes.to_vec()),
+            Self::Rle => rle_decode(bytes),
+        }
+    }
+}
+
+/// Run length encode the bytes as pairs of a count from 1 to 255 and the repeated byte
+pub fn rle_encode(bytes: &[u8]) -> Vec<u8> {
+    let mut encoded = Vec::new();
+    let mut iter = bytes.iter().peekable();
+    while let Some(&byte) = iter.next() {
+        let mut count = 1u8;
+        while count < u8::MAX && iter.next_if_eq(&&byte).is_some() {
+            count = count.saturating_add(1);
+        }
+        encoded.extend_from_slice(&[count, byte]);
+    }
+    encoded
+}
+
+/// Decode bytes encoded by [`rle_encode`]
+///
+/// # Errors
+/// An error is returned when the data ends in the middle of a pair or contains an empty run.
+pub fn rle_decode(encoded: &[u8]) -> crate::Result<Vec<u8>> {
+    if !encoded.len().is_multiple_of(2) {
+        return Err(GenerationError::MalformedRle(encoded.len()));
+    }
+    let mut bytes = Vec::new();
+    for (n, pair) in encoded.chunks_exact(2).enumerate() {
+        if pair[0] == 0 {
+            return Err(GenerationError::MalformedRle(n.saturating_mul(2)));
+        }
+        bytes.extend(std::iter::repeat_n(pair[1], usize::from(pair[0])));
+    }
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Long runs are split at 255 bytes and decode to the input
+    #[test]
+    fn rle_round_trip() {
+        let mut bytes = vec![0; 300];
+        bytes.extend_from_slice(&[1, 2, 2]);
+        let encoded = rle_encode(&bytes);
+        assert_eq!(encoded, [255, 0, 45, 0, 1, 1, 2, 2]);
+        assert_eq!(rle_decode(&encoded).unwrap(), bytes);
+        assert!(rle_decode(&[2, 0, 1]).is_err());
+    }
+}
@@ -0,0 +1,200 @@
+//! Binary patches between two versions of a font for over the air updates.
+
+use std::io::{self, Write};
+
+use crate::GenerationError;
+
+/// A run of bytes replacing the bytes of the old font starting at `offset`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PatchRecord {
+    /// Offset of the first replaced byte
+    pub offset: usize,
+    /// The bytes of the new font
+    pub bytes: Vec<u8>,
+}
+
+impl PatchRecord {
+    /// Write the record as little endian `u32` offset and `u32` length followed by the bytes
+    ///
+    /// # Errors
+    /// An error is returned when the offset or length doesn't fit into a `u32` or writing to
+    /// `out` fails.
+    pub fn write(&self, out: &mut impl Write) -> io::Result<()> {
+        let to_u32 = |val: usize| {
+            u32::try_from(val).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))
+        };
+        out.write_all(&to_u32(self.offset)?.to_le_bytes())?;
+        out.write_all(&to_u32(self.bytes.len())?.to_le_bytes())?;
+        out.write_all(&self.bytes)
+    }
+
+    /// Read a record written by [`PatchRecord::write`] from the start of `bytes`, returns the
+    /// record and the number of bytes it takes up
+    pub fn read(bytes: &[u8]) -> Option<(Self, usize)> {
+        let (offset, rest) = read_u32(bytes)?;
+        let (len, rest) = read_u32(rest)?;
+        let data = rest.get(..len)?;
+        let record = Self {
+            offset,
+            bytes: data.to_vec(),
+        };
+        Some((record, len.saturating_add(8)))
+    }
+}
+
+/// Write a patch as little endian `u32` length of the new font followed by the records
+///
+/// # Errors
+/// An error is returned when the length or a record doesn't fit into a `u32` or writing to `out`
+/// fails.
+pub fn write_patch(new_len: usize, patch: &[PatchRecord], out: &mut impl Write) -> io::Result<()> {
+    let new_len =
+        u32::try_from(new_len).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    out.write_all(&new_len.to_le_bytes())?;
+    for record in patch {
+        record.write(out)?;
+    }
+    Ok(())
+}
+
+/// Parse a patch written by [`write_patch`], returns the length of the new font and the records
+///
+/// # Errors
+/// An error is returned when the patch ends in the middle of its header or a record.
+pub fn parse_patch(bytes: &[u8]) -> crate::Result<(usize, Vec<PatchRecord>)> {
+    let (new_len, mut rest) = read_u32(bytes).ok_or(GenerationError::MalformedPatch(0))?;
+    let mut records = Vec::new();
+    while !rest.is_empty() {
+        let (record, len) = PatchRecord::read(rest).ok_or_else(|| {
+            GenerationError::MalformedPatch(bytes.len().saturating_sub(rest.len()))
+        })?;
+        rest = rest.get(len..).unwrap_or_default();
+        records.push(record);
+    }
+    Ok((new_len, records))
+}
+
+/// Split a little endian `u32` off the start of `bytes`
+fn read_u32(bytes: &[u8]) -> Option<(usize, &[u8])> {
+    match bytes {
+        [a, b, c, d, rest @ ..] => {
+            let val = usize::try_from(u32::from_le_bytes([*a, *b, *c, *d])).ok()?;
+            Some((val, rest))
+        }
+        _ => None,
+    }
+}
+
+/// Compute the records turning `old` into `new`, one record for every run of changed bytes.
+/// Bytes appended to a longer new font are part of the last record.
+pub fn make_patch(old: &[u8], new: &[u8]) -> Vec<PatchRecord> {
+    let mut records: Vec<PatchRecord> = Vec::new();
+    for (offset, &byte) in new.iter().enumerate() {
+        if old.get(offset) == Some(&byte) {
+            continue;
+        }
+        match records.last_mut() {
+            Some(last) if last.offset.saturating_add(last.bytes.len()) == offset => {
+                last.bytes.push(byte);
+            }
+            _ => records.push(PatchRecord {
+                offset,
+                bytes: vec![byte],
+            }),
+        }
+    }
+    records
+}
+
+/// Apply the records to `old`, producing the new font of `new_len` bytes. The old font is
+/// truncated or extended with zeros to `new_len` before the records are applied.
+///
+/// # Errors
+/// An error is returned when a record reaches past `new_len`.
+pub fn apply_patch(old: &[u8], patch: &[PatchRecord], new_len: usize) -> crate::Result<Vec<u8>> {
+    let mut font = old.to_vec();
+    font.resize(new_len, 0);
+    for record in patch {
+        let region = record
+            .offset
+            .checked_add(record.bytes.len())
+            .and_then(|end| font.get_mut(record.offset..end))
+            .ok_or(GenerationError::PatchOutOfRange {
+                offset: record.offset,
+                len: record.bytes.len(),
+            })?;
+        region.copy_from_slice(&record.bytes);
+    }
+    Ok(font)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Applying the patch to the old font reproduces the new font exactly
+    #[test]
+    fn patch_round_trip() {
+        let old: Vec<u8> = (0..64).collect();
+        let mut new = old.clone();
+        new[3] = 0xFF;
+        new[4] = 0xFE;
+        new[40] = 0;
+        new.extend_from_slice(&[1, 2, 3]);
+
+        let patch = make_patch(&old, &new);
+        assert_eq!(
+            patch,
+            [
+                PatchRecord {
+                    offset: 3,
+                    bytes: vec![0xFF, 0xFE]
+                },
+                PatchRecord {
+                    offset: 40,
+                    bytes: vec![0]
+                },
+                PatchRecord {
+                    offset: 64,
+                    bytes: vec![1, 2, 3]
+                },
+            ]
+        );
+        assert_eq!(apply_patch(&old, &patch, new.len()).unwrap(), new);
+
+        let shorter = &old[..32];
+        let patch = make_patch(&old, shorter);
+        assert!(patch.is_empty());
+        assert_eq!(apply_patch(&old, &patch, shorter.len()).unwrap(), shorter);
+        assert!(apply_patch(&old, &make_patch(&old, &new), 32).is_err());
+    }
+
+    /// A written patch starts with the new length and parses back to the same records
+    #[test]
+    fn patch_serialization() {
+        let old = [0u8; 8];
+        let new = [0, 7, 7, 0, 0, 0, 0, 0, 9];
+        let patch = make_patch(&old, &new);
+
+        let mut serialized = Vec::new();
+        write_patch(new.len(), &patch, &mut serialized).unwrap();
+        assert_eq!(
+            serialized,
+            [9, 0, 0, 0, 1, 0, 0, 0, 2, 0, 0, 0, 7, 7, 8, 0, 0, 0, 1, 0, 0, 0, 9]
+        );
+
+        let (new_len, parsed) = parse_patch(&serialized).unwrap();
+        assert_eq!(new_len, new.len());
+        assert_eq!(parsed, patch);
+        assert_eq!(apply_patch(&old, &parsed, new_len).unwrap(), new);
+
+        assert!(matches!(
+            parse_patch(&serialized[..serialized.len() - 1]),
+            Err(GenerationError::MalformedPatch(14))
+        ));
+        assert!(matches!(
+            parse_patch(&serialized[..2]),
+            Err(GenerationError::MalformedPatch(0))
+        ));
+    }
+}
@@ -0,0 +1,1441 @@
+//! Generation of complete fonts from glyph image files.
+
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    io::{self, Read, Write},
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    codepoint_from_filename,
+    flush::FlushWriter,
+    imagedecode::inspect_png,
+    index::crc32,
+    settings::{DirSettings, SETTINGS_FILE_NAME},
+    Compression, FontGenerator, FontReport, GenerationError, Glyph, GlyphMetrics, GlyphRecord,
+};
+
+/// Generate a single glyph from the png file at `file_path`.
+///
+/// # Errors
+/// An error is returned when the file can not be read, decoded, the glyph exceeds the byte budget
+/// of the generator or writing to `out` fails.
+pub fn generate_file(
+    file_path: &Path,
+    generator: &FontGenerator,
+    out: &mut impl Write,
+) -> crate::Result<GlyphRecord> {
+    let file = fs::File::open(file_path).map_err(GenerationError::IoError)?;
+    generate_reader(file_path, file, generator, out)
+}
+
+/// Generate a single glyph from png `data` which was already read, e.g. from stdin.
+///
+/// The `file_path` only names the glyph in the returned record and its codepoint is derived from
+/// it like in [`generate_file`].
+///
+/// # Errors
+/// An error is returned when the data can not be decoded, the glyph exceeds the byte budget of
+/// the generator or writing to `out` fails.
+pub fn generate_data(
+    file_path: &Path,
+    data: &[u8],
+    generator: &FontGenerator,
+    out: &mut impl Write,
+) -> crate::Result<GlyphRecord> {
+    let (record, glyph) = decode_glyph(file_path, data, generator)?;
+    write_decoded(file_path, record, &glyph, generator, out)
+}
+
+/// Generate a single glyph from png data read from `reader`, e.g. stdin or an open file.
+///
+/// The png decoder works on a byte slice, so the reader is read to its end into a single buffer.
+/// The buffer is dropped once the image is decoded, before the glyph is packed. The `file_path`
+/// names the glyph like in [`generate_data`].
+///
+/// # Errors
+/// An error is returned when reading fails, the data can not be decoded, the glyph exceeds the
+/// byte budget of the generator or writing to `out` fails.
+pub fn generate_reader(
+    file_path: &Path,
+    mut reader: impl Read,
+    generator: &FontGenerator,
+    out: &mut impl Write,
+) -> crate::Result<GlyphRecord> {
+    let (record, glyph) = {
+        let mut data = Vec::new();
+        reader
+            .read_to_end(&mut data)
+            .map_err(GenerationError::IoError)?;
+        decode_glyph(file_path, &data, generator)?
+    };
+    write_decoded(file_path, record, &glyph, generator, out)
+}
+
+/// Pack a decoded glyph, complete its record and write it to `out`
+fn write_decoded(
+    file_path: &Path,
+    mut record: GlyphRecord,
+    glyph: &Glyph,
+    generator: &FontGenerator,
+    out: &mut impl Write,
+) -> crate::Result<GlyphRecord> {
+    let (compression, bytes) = pack_glyph(file_path, glyph, generator)?;
+    record.compression = compression;
+    record.byte_len = bytes.len();
+    record.crc = crc32(&bytes);
+    out.write_all(&bytes)
+        .map_err(GenerationError::OutputWriterError)?;
+    Ok(record)
+}
+
+/// Generate all images in a directory like [`generate_dir`], but write every glyph to its own
+/// file in `out_dir` instead of concatenating them. The file is named after the stem of the
+/// image with the extension `.bin`, e.g. `A.png` becomes `A.bin`, and holds exactly the bytes
+/// of the glyph.
+///
+/// The glyphs are generated in memory first, no file is written if two glyphs would share a
+/// file name, like the mirrored copy of a glyph or images with the same stem in different
+/// subdirectories. The end sentinel is not written. The offsets of the report are those of the
+/// concatenated font.
+///
+/// # Errors
+/// An error is returned when the directory can not be read, generating any glyph fails and the
+/// generator doesn't continue on errors, two glyphs share a file name or writing a file fails.
+pub fn generate_dir_split(
+    dir_path: &Path,
+    generator: &FontGenerator,
+    out_dir: &Path,
+) -> crate::Result<FontReport> {
+    let mut font = Vec::new();
+    let report = generate_dir(dir_path, generator, &mut font)?;
+
+    let mut sources: HashMap<PathBuf, &Path> = HashMap::new();
+    let mut files = Vec::new();
+    for record in &report.glyphs {
+        let mut name = PathBuf::from(record.path.file_stem().unwrap_or_default());
+        name.set_extension("bin");
+        let file = out_dir.join(name);
+        if let Some(first) = sources.insert(file.clone(), &record.path) {
+            return Err(GenerationError::SplitOutputCollision {
+                file,
+                first: first.to_owned(),
+                second: record.path.clone(),
+            });
+        }
+        files.push((file, record));
+    }
+
+    fs::create_dir_all(out_dir).map_err(GenerationError::IoError)?;
+    for (file, record) in files {
+        let end = record.offset.saturating_add(record.byte_len);
+        let bytes = font.get(record.offset..end).unwrap_or_default();
+        fs::write(file, bytes).map_err(GenerationError::OutputWriterError)?;
+    }
+    Ok(report)
+}
+
+/// Concatenate the already packed glyphs of the `.bin` files in a directory into a font, the
+/// files are processed sorted by name and their bytes are written unchanged.
+///
+/// Every glyph is recorded with the given cell size and the codepoint derived from its file name,
+/// so the [`GlyphIndex`](crate::GlyphIndex) can be built from the returned report. The set bits
+/// of every file are counted as its on pixels. Files without the `.bin` extension are skipped
+/// with a warning.
+///
+/// # Errors
+/// An error is returned when the directory or a file can not be read or writing to `out` fails.
+pub fn pack_bins(
+    dir_path: &Path,
+    cell_size: (usize, usize),
+    out: &mut impl Write,
+) -> crate::Result<FontReport> {
+    let mut report = FontReport::new(crate::FontMode::default(), crate::BitFlow::default());
+    let mut paths = Vec::new();
+    for res in fs::read_dir(dir_path)? {
+        let path = res?.path();
+        if path.extension().is_some_and(|ext| ext == "bin") {
+            paths.push(path);
+        } else {
+            report
+                .warnings
+                .push(format!("Skipped {}, not a .bin glyph", path.display()));
+        }
+    }
+    paths.sort_unstable();
+
+    let mut offset = 0usize;
+    for path in paths {
+        let bytes = fs::read(&path)?;
+        out.write_all(&bytes)
+            .map_err(GenerationError::OutputWriterError)?;
+        report.glyphs.push(GlyphRecord {
+            codepoint: codepoint_from_filename(&path),
+            path,
+            width: cell_size.0,
+            height: cell_size.1,
+            offset,
+            byte_len: bytes.len(),
+            crc: crc32(&bytes),
+            compression: Compression::None,
+            metrics: GlyphMetrics::default(),
+            dropped_rows: 0,
+            on_count: usize::try_from(bytes.iter().map(|b| b.count_ones()).sum::<u32>())
+                .unwrap_or(usize::MAX),
+            crop: None,
+        });
+        offset = offset.saturating_add(bytes.len());
+    }
+
+    Ok(report)
+}
+
+/// Decode the glyph read from `file_path`, the returned record has no size and offset yet.
+fn decode_glyph(
+    file_path: &Path,
+    data: &[u8],
+    generator: &FontGenerator,
+) -> crate::Result<(GlyphRecord, Glyph)> {
+    let (glyph, crop) = generator.cropped_glyph(data)?;
+    if let Some(min) = generator.min_coverage(&glyph) {
+        let coverage = glyph.coverage();
+        if coverage < min {
+            return Err(GenerationError::InsufficientCoverage {
+                file: file_path.to_owned(),
+                coverage,
+                min,
+            });
+        }
+    }
+
+    let codepoint = codepoint_from_filename(file_path);
+    let mut metrics = glyph.metrics();
+    if let Some(advance) = codepoint.and_then(|cp| generator.forced_width(cp)) {
+        metrics.advance = advance;
+    }
+
+    let dropped_rows = generator.dropped_rows(&glyph);
+    let record = GlyphRecord {
+        path: file_path.to_owned(),
+        codepoint,
+        width: glyph.width(),
+        height: glyph.height().saturating_sub(dropped_rows),
+        offset: 0,
+        byte_len: 0,
+        crc: 0,
+        compression: Compression::None,
+        metrics,
+        dropped_rows,
+        on_count: glyph.on_count(),
+        crop,
+    };
+    Ok((record, glyph))
+}
+
+/// Pack the glyph loaded from `file_path` and check it against the byte budget of the generator
+fn pack_glyph(
+    file_path: &Path,
+    glyph: &Glyph,
+    generator: &FontGenerator,
+) -> crate::Result<(Compression, Vec<u8>)> {
+    let (compression, bytes) = encode_glyph(glyph, generator)?;
+    let size = bytes.len();
+    match generator.max_glyph_bytes() {
+        Some(max) if size > max => Err(GenerationError::GlyphTooLarge {
+            file: file_path.to_owned(),
+            size,
+            max,
+        }),
+        _ => Ok((compression, bytes)),
+    }
+}
+
+/// Pack the glyph and compress it if the generator compresses glyphs
+fn encode_glyph(glyph: &Glyph, generator: &FontGenerator) -> crate::Result<(Compression, Vec<u8>)> {
+    let mut bytes = Vec::new();
+    generator.write_glyph(glyph, &mut bytes)?;
+    if generator.compress() {
+        Ok(Compression::smallest(bytes))
+    } else {
+        Ok((Compression::None, bytes))
+    }
+}
+
+/// Prefix the packed bytes of a glyph with its width, height and advance if the generator
+/// writes glyph headers
+fn with_glyph_header(record: &GlyphRecord, bytes: Vec<u8>, generator: &FontGenerator) -> Vec<u8> {
+    if !generator.glyph_header() {
+        return bytes;
+    }
+    let mut prefixed = vec![
+        u8::try_from(record.width).unwrap_or(u8::MAX),
+        u8::try_from(record.height).unwrap_or(u8::MAX),
+        record.metrics.scaled_advance(generator.advance_scale()),
+    ];
+    prefixed.extend(bytes);
+    prefixed
+}
+
+/// The progress of a directory generation run
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Progress<'a> {
+    /// The glyph of this file is generated next
+    Glyph(&'a Path),
+    /// All glyphs are written, contains the summary line of the run
+    Summary(&'a str),
+}
+
+/// Generate all images in a directory as font, the files are processed sorted by name.
+///
+/// See [`generate_dir_with_progress`] to follow the progress of the run.
+///
+/// # Errors
+/// An error is returned when the directory can not be read, writing to `out` fails or generating
+/// any glyph fails and the generator doesn't continue on errors.
+pub fn generate_dir(
+    dir_path: &Path,
+    generator: &FontGenerator,
+    out: &mut impl Write,
+) -> crate::Result<FontReport> {
+    generate_dir_with_progress(dir_path, generator, out, |_| {})
+}
+
+/// Generate all images in a directory as font, the files are processed sorted by name.
+///
+/// Every glyph is packed on its own and starts on a fresh byte, a glyph whose pixel count is not
+/// a multiple of 8 ends with a byte padded with off pixels. In
+/// [`FontMode::ByteColumn`](crate::FontMode::ByteColumn) and
+/// [`FontMode::AlignedColumn`](crate::FontMode::AlignedColumn) every glyph already ends on a byte
+/// boundary since its columns are aligned to multiples of 8 pixels.
+///
+/// If the generator has an end sentinel it is written after the last glyph, including the
+/// mirrored glyphs. `out` is flushed as described by the flush policy of the generator.
+///
+/// Files whose image header can't be decoded, like a stray `README.txt`, are skipped with a
+/// warning in the report unless the generator is strict. A uniform generator checks the sizes in
+/// the image headers before generating any glyph and fails on the first image whose size differs
+/// from the first one.
+///
+/// If the directory contains a `.efg` settings file, see [`DirSettings`], its settings override
+/// the options of the generator for this directory.
+///
+/// `progress` is called before every glyph is generated and with the summary line once the run
+/// is finished. With a parallel generator, see `FontGenerator::with_parallel`, all glyphs are
+/// decoded up front and `progress` is called as their results are collected in order.
+///
+/// All glyphs are decoded before the first byte is written. The glyph records of the report are
+/// in processing order even if the glyphs were reordered in the output, the records of a mirrored
+/// copy of the font follow the records of the normal glyphs.
+///
+/// # Errors
+/// An error is returned when the directory can not be read, writing to `out` fails or generating
+/// any glyph fails and the generator doesn't continue on errors.
+pub fn generate_dir_with_progress(
+    dir_path: &Path,
+    generator: &FontGenerator,
+    out: &mut impl Write,
+    mut progress: impl FnMut(Progress<'_>),
+) -> crate::Result<FontReport> {
+    let generator = &dir_generator(dir_path, generator)?;
+    let out = &mut FlushWriter::new(out, generator.flush_policy());
+    let mut report = FontReport::for_generator(generator);
+    let mut files = Vec::new();
+    collect_files(
+        dir_path,
+        generator.recursive(),
+        &mut HashSet::new(),
+        &mut files,
+        &mut report.warnings,
+    )?;
+
+    let mut paths = Vec::new();
+    let mut uniform_size = None;
+    for path in files {
+        let include = generator.include_codepoints();
+        if include.is_some() || generator.codepoint_from_filename() {
+            match codepoint_from_filename(&path) {
+                Some(codepoint) if include.is_none_or(|inc| inc.contains(codepoint)) => {}
+                Some(_) => continue,
+                None => {
+                    report.warnings.push(format!(
+                        "Skipped {}, no codepoint could be derived from its name",
+                        path.display()
+                    ));
+                    continue;
+                }
+            }
+        }
+        if !generator.strict() || generator.uniform() {
+            let header = fs::read(&path)
+                .map_err(GenerationError::IoError)
+                .and_then(|data| inspect_png(&data, generator.font_mode()));
+            match header {
+                Ok(info) if generator.uniform() => {
+                    let found = (info.width, info.height);
+                    match uniform_size {
+                        None => uniform_size = Some(found),
+                        Some(expected) if expected != found => {
+                            return Err(GenerationError::NonUniformGlyphSize {
+                                expected,
+                                found,
+                                file: path,
+                            })
+                        }
+                        Some(_) => {}
+                    }
+                }
+                Ok(_) => {}
+                // A strict generator fails on the file when generating its glyph
+                Err(_) if generator.strict() => {}
+                Err(e) => {
+                    report.warnings.push(format!(
+                        "Skipped {}, it is no image: {}",
+                        path.display(),
+                        e
+                    ));
+                    continue;
+                }
+            }
+        }
+        paths.push(path);
+    }
+
+    let mut decoded = load_parallel(&paths, generator).map(Vec::into_iter);
+    let mut glyphs = Vec::new();
+    let mut packed = Vec::new();
+    for path in paths {
+        progress(Progress::Glyph(&path));
+        let loaded = match decoded.as_mut().and_then(Iterator::next) {
+            Some(loaded) => loaded,
+            None => load_packed(&path, generator),
+        };
+        match loaded {
+            Ok((record, glyph, bytes)) => {
+                report.glyphs.push(record);
+                glyphs.push(glyph);
+                packed.push(bytes);
+            }
+            Err(e) if generator.continue_on_error() => report.errors.push((path, e)),
+            Err(e) => return Err(e.in_file(path)),
+        }
+    }
+
+    let order: Vec<usize> = if generator.optimize_order() {
+        similarity_order(&packed)
+    } else {
+        (0..packed.len()).collect()
+    };
+
+    let mut offset = 0usize;
+    let mut last_len = 0;
+    for &n in &order {
+        if let (Some(record), Some(bytes)) = (report.glyphs.get_mut(n), packed.get(n)) {
+            let padding = write_page_padding(out, offset, generator.glyph_page_align())?;
+            report.padding_len = report.padding_len.saturating_add(padding);
+            offset = offset.saturating_add(padding);
+            out.write_all(bytes)
+                .and_then(|()| out.glyph_done())
+                .map_err(GenerationError::OutputWriterError)?;
+            record.offset = offset;
+            record.byte_len = bytes.len();
+            record.crc = crc32(bytes);
+            offset = offset.saturating_add(bytes.len());
+            last_len = bytes.len();
+        }
+    }
+
+    if generator.mirror() {
+        let mut mirrored_records = report.glyphs.clone();
+        for &n in &order {
+            if let (Some(record), Some(glyph)) = (mirrored_records.get_mut(n), glyphs.get(n)) {
+                let mirrored = glyph.mirrored();
+                let (compression, bytes) = encode_glyph(&mirrored, generator)?;
+                record.compression = compression;
+                record.metrics = mirrored.metrics();
+                let bytes = with_glyph_header(record, bytes, generator);
+                let padding = write_page_padding(out, offset, generator.glyph_page_align())?;
+                report.padding_len = report.padding_len.saturating_add(padding);
+                offset = offset.saturating_add(padding);
+                out.write_all(&bytes)
+                    .and_then(|()| out.glyph_done())
+                    .map_err(GenerationError::OutputWriterError)?;
+                record.offset = offset;
+                record.byte_len = bytes.len();
+                record.crc = crc32(&bytes);
+                offset = offset.saturating_add(record.byte_len);
+                last_len = record.byte_len;
+            }
+        }
+        report.glyphs.append(&mut mirrored_records);
+    }
+
+    if let Some(sentinel) = generator.end_sentinel() {
+        let bytes = sentinel.bytes(last_len);
+        out.write_all(&bytes)
+            .map_err(GenerationError::OutputWriterError)?;
+        report.sentinel_len = bytes.len();
+    }
+    out.flush_pending()
+        .map_err(GenerationError::OutputWriterError)?;
+
+    progress(Progress::Summary(&report.summary()));
+    Ok(report)
+}
+
+/// Collect the files of a directory sorted by name, except for the settings file.
+///
+/// If `recursive` the files of a subdirectory are collected in its place, depth-first. Every
+/// directory is only visited once, so symlink loops are skipped with a warning, like
+/// subdirectories that can't be read.
+fn collect_files(
+    dir_path: &Path,
+    recursive: bool,
+    visited: &mut HashSet<PathBuf>,
+    files: &mut Vec<PathBuf>,
+    warnings: &mut Vec<String>,
+) -> io::Result<()> {
+    if recursive && !visited.insert(fs::canonicalize(dir_path)?) {
+        warnings.push(format!(
+            "Skipped {}, the directory was already visited",
+            dir_path.display()
+        ));
+        return Ok(());
+    }
+
+    let mut entries = Vec::new();
+    for res in fs::read_dir(dir_path)? {
+        match res {
+            Ok(ent) if ent.file_name() == SETTINGS_FILE_NAME => {}
+            Ok(ent) => entries.push(ent),
+            Err(e) => warnings.push(format!(
+                "Skipped unreadable entry in {}: {}",
+                dir_path.display(),
+                e
+            )),
+        }
+    }
+    entries.sort_unstable_by_key(|ent| ent.file_name());
+
+    for path in entries.iter().map(fs::DirEntry::path) {
+        if recursive && path.is_dir() {
+            if let Err(e) = collect_files(&path, recursive, visited, files, warnings) {
+                warnings.push(format!(
+                    "Skipped unreadable directory {}: {}",
+                    path.display(),
+                    e
+                ));
+            }
+        } else {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// A loaded glyph with its record and packed bytes, including the glyph header
+type PackedGlyph = (GlyphRecord, Glyph, Vec<u8>);
+
+/// Load and pack the glyph at `file_path`, the record has no size and offset yet
+fn load_packed(file_path: &Path, generator: &FontGenerator) -> crate::Result<PackedGlyph> {
+    let data = fs::read(file_path).map_err(GenerationError::IoError)?;
+    decode_packed(file_path, &data, generator)
+}
+
+/// Decode and pack the glyph read from `file_path`, the record has no size and offset yet
+pub(crate) fn decode_packed(
+    file_path: &Path,
+    data: &[u8],
+    generator: &FontGenerator,
+) -> crate::Result<PackedGlyph> {
+    let (record, glyph) = decode_glyph(file_path, data, generator)?;
+    let (compression, bytes) = pack_glyph(file_path, &glyph, generator)?;
+    let record = GlyphRecord {
+        compression,
+        ..record
+    };
+    let bytes = with_glyph_header(&record, bytes, generator);
+    Ok((record, glyph, bytes))
+}
+
+/// Load and pack all glyphs on the rayon thread pool if the generator is parallel, the results
+/// are in the order of `paths`
+#[cfg(feature = "rayon")]
+fn load_parallel(
+    paths: &[PathBuf],
+    generator: &FontGenerator,
+) -> Option<Vec<crate::Result<PackedGlyph>>> {
+    use rayon::prelude::*;
+
+    generator.parallel().then(|| {
+        paths
+            .par_iter()
+            .map(|path| load_packed(path, generator))
+            .collect()
+    })
+}
+
+/// Without the rayon feature glyphs are always loaded sequentially
+#[cfg(not(feature = "rayon"))]
+fn load_parallel(
+    _paths: &[PathBuf],
+    _generator: &FontGenerator,
+) -> Option<Vec<crate::Result<PackedGlyph>>> {
+    None
+}
+
+/// Fill the gap from `offset` to the next multiple of the page size with `0xFF`, returns the
+/// number of bytes written
+fn write_page_padding(
+    out: &mut impl Write,
+    offset: usize,
+    page_size: Option<usize>,
+) -> crate::Result<usize> {
+    let Some(page_size) = page_size else {
+        return Ok(0);
+    };
+    let len = offset.next_multiple_of(page_size).wrapping_sub(offset);
+    out.write_all(&vec![0xFF; len])
+        .map_err(GenerationError::OutputWriterError)?;
+    Ok(len)
+}
+
+/// The generator for a directory, with the settings of its settings file applied if present
+fn dir_generator(dir_path: &Path, generator: &FontGenerator) -> crate::Result<FontGenerator> {
+    let settings_path = dir_path.join(SETTINGS_FILE_NAME);
+    let settings = match fs::read_to_string(&settings_path) {
+        Ok(settings) => settings,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(generator.clone()),
+        Err(e) => return Err(GenerationError::IoError(e)),
+    };
+    let settings: DirSettings = settings
+        .parse()
+        .map_err(|e| GenerationError::InvalidSettings {
+            file: settings_path,
+            source: e,
+        })?;
+    Ok(settings.apply(generator))
+}
+
+/// Order the glyphs so that each glyph is followed by the most similar remaining glyph,
+/// starting with the first glyph. The similarity is the hamming distance of the packed bytes.
+fn similarity_order(glyphs: &[Vec<u8>]) -> Vec<usize> {
+    let mut remaining: Vec<usize> = (0..glyphs.len()).collect();
+    let mut order = Vec::with_capacity(glyphs.len());
+    let mut cur = remaining.first().copied();
+    while let Some(n) = cur {
+        remaining.retain(|&r| r != n);
+        order.push(n);
+        cur = remaining
+            .iter()
+            .copied()
+            .min_by_key(|&r| hamming_distance(&glyphs[n], &glyphs[r]));
+    }
+    order
+}
+
+/// Number of differing bits, missing bytes of the shorter slice count as 0
+fn hamming_distance(a: &[u8], b: &[u8]) -> u32 {
+    let len = a.len().max(b.len());
+    (0..len)
+        .map(|i| {
+            let a = a.get(i).copied().unwrap_or(0);
+            let b = b.get(i).copied().unwrap_or(0);
+            (a ^ b).count_ones()
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{testutil, CodepointRanges, FlushPolicy, FontMode, GlyphIndex};
+
+    /// The JSON report matches the generated output
+    #[test]
+    fn report_totals() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(
+            dir.path().join("a.png"),
+            testutil::picture(&["#.#.", "...."]),
+        )
+        .unwrap();
+        fs::write(
+            dir.path().join("b.png"),
+            testutil::picture(&["########", "........", "#......."]),
+        )
+        .unwrap();
+
+        let mut out = Vec::new();
+        let report = generate_dir(dir.path(), &FontGenerator::new(), &mut out).unwrap();
+        let mut json = Vec::new();
+        report.write_json(&mut json).unwrap();
+
+        let json: serde_json::Value = serde_json::from_slice(&json).unwrap();
+        assert_eq!(json["total_glyphs"], 2);
+        assert_eq!(json["total_bytes"], out.len());
+        assert_eq!(json["font_mode"], "row");
+        assert_eq!(json["bit_flow"], "mtl");
+        assert_eq!(json["glyphs"][0]["bytes"], 1);
+        assert_eq!(json["glyphs"][1]["bytes"], 3);
+        assert_eq!(json["glyphs"][1]["width"], 8);
+        assert_eq!(json["warnings"].as_array().map(Vec::len), Some(0));
+        assert_eq!(json["errors"].as_array().map(Vec::len), Some(0));
+    }
+
+    /// Writer counting how often it is flushed
+    #[derive(Default)]
+    struct FlushCounter {
+        /// The written bytes
+        bytes: Vec<u8>,
+        /// The number of flushes
+        flushes: usize,
+    }
+
+    impl Write for FlushCounter {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.bytes.write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            self.flushes += 1;
+            Ok(())
+        }
+    }
+
+    /// The output is flushed once at the end, after every glyph or after enough bytes
+    #[test]
+    fn flush_policy() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["a", "b", "c"] {
+            fs::write(
+                dir.path().join(format!("{name}.png")),
+                testutil::picture(&["#.......", ".#......", "..#....."]),
+            )
+            .unwrap();
+        }
+
+        let flushes = |policy| {
+            let generator = FontGenerator::new().with_flush_policy(policy);
+            let mut out = FlushCounter::default();
+            generate_dir(dir.path(), &generator, &mut out).unwrap();
+            assert_eq!(out.bytes.len(), 9);
+            out.flushes
+        };
+        assert_eq!(flushes(FlushPolicy::End), 1);
+        assert_eq!(flushes(FlushPolicy::EveryGlyph), 3);
+        assert_eq!(flushes(FlushPolicy::EveryBytes(4)), 2);
+        assert_eq!(flushes(FlushPolicy::EveryBytes(3)), 3);
+    }
+
+    /// The manifest has a header and one line with all fields per glyph
+    #[test]
+    fn manifest_lines() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(
+            dir.path().join("0041.png"),
+            testutil::picture(&["#.#.", "...."]),
+        )
+        .unwrap();
+        fs::write(
+            dir.path().join("b.png"),
+            testutil::picture(&["########", "........", "#......."]),
+        )
+        .unwrap();
+        fs::write(dir.path().join("tall.png"), testutil::picture(&["#", "#"])).unwrap();
+
+        let report = generate_dir(dir.path(), &FontGenerator::new(), &mut Vec::new()).unwrap();
+        let mut manifest = Vec::new();
+        report.write_manifest(&mut manifest).unwrap();
+        let manifest = String::from_utf8(manifest).unwrap();
+
+        let mut lines = manifest.lines();
+        assert!(lines.next().unwrap().starts_with('#'));
+        let lines: Vec<Vec<&str>> = lines.map(|line| line.split('\t').collect()).collect();
+        assert_eq!(
+            lines,
+            [
+                ["0041.png", "U+0041", "0", "1", "4", "2", "25.00%"],
+                ["b.png", "U+0062", "1", "3", "8", "3", "37.50%"],
+                ["tall.png", "-", "4", "1", "1", "2", "100.00%"],
+            ]
+        );
+    }
+
+    /// Every glyph is announced before the summary line of the run
+    #[test]
+    fn progress_summary() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(
+            dir.path().join("a.png"),
+            testutil::picture(&["#.#.", "...."]),
+        )
+        .unwrap();
+        fs::write(
+            dir.path().join("b.png"),
+            testutil::picture(&["########", "........"]),
+        )
+        .unwrap();
+
+        let mut lines = Vec::new();
+        generate_dir_with_progress(dir.path(), &FontGenerator::new(), &mut Vec::new(), |p| {
+            lines.push(match p {
+                Progress::Glyph(path) => path.file_name().unwrap().to_string_lossy().into_owned(),
+                Progress::Summary(summary) => summary.to_owned(),
+            });
+        })
+        .unwrap();
+
+        assert_eq!(
+            lines,
+            ["a.png", "b.png", "Generated 2 glyphs with 3 bytes in total"]
+        );
+    }
+
+    /// The settings file of a directory overrides the mode of the generator
+    #[test]
+    fn dir_settings() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(
+            dir.path().join("a.png"),
+            testutil::picture(&["#.......", "#.......", "#......."]),
+        )
+        .unwrap();
+
+        let mut row = Vec::new();
+        generate_dir(dir.path(), &FontGenerator::new(), &mut row).unwrap();
+        assert_eq!(row, [0x80, 0x80, 0x80]);
+
+        fs::write(
+            dir.path().join(".efg"),
+            "# vertical display
+mode = byte-column
+",
+        )
+        .unwrap();
+        let mut column = Vec::new();
+        let report = generate_dir(dir.path(), &FontGenerator::new(), &mut column).unwrap();
+        assert_eq!(column, [0xE0, 0, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(report.font_mode, FontMode::ByteColumn);
+        assert_eq!(report.glyphs.len(), 1);
+
+        fs::write(
+            dir.path().join(".efg"),
+            "mode = diagonal
+",
+        )
+        .unwrap();
+        assert!(matches!(
+            generate_dir(dir.path(), &FontGenerator::new(), &mut Vec::new()),
+            Err(GenerationError::InvalidSettings { .. })
+        ));
+    }
+
+    /// The width table has one byte per glyph holding its content width
+    #[test]
+    fn widths_table() {
+        let dir = tempfile::tempdir().unwrap();
+        let glyphs = [
+            [".#......", "..#....."],
+            ["#......#", "........"],
+            ["........", "........"],
+        ];
+        for (i, rows) in glyphs.iter().enumerate() {
+            fs::write(dir.path().join(format!("{i}.png")), testutil::picture(rows)).unwrap();
+        }
+
+        let report = generate_dir(dir.path(), &FontGenerator::new(), &mut Vec::new()).unwrap();
+        let mut widths = Vec::new();
+        report.write_widths(&mut widths).unwrap();
+        assert_eq!(widths, [2, 8, 0]);
+    }
+
+    /// Files without codepoint are skipped with a warning, the map is sorted by codepoint and
+    /// points to the glyph numbers in name order
+    #[test]
+    fn codepoint_map() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["0042.png", "A.png", "U+0020.png", "logo.png"] {
+            fs::write(dir.path().join(name), testutil::picture(&["#."])).unwrap();
+        }
+
+        let generator = FontGenerator::new().with_codepoint_from_filename(true);
+        let report = generate_dir(dir.path(), &generator, &mut Vec::new()).unwrap();
+        assert_eq!(report.glyphs.len(), 3);
+        assert!(report.warnings[0].contains("logo.png"));
+
+        let mut map = Vec::new();
+        report.write_codepoint_map(&mut map).unwrap();
+        let entries: Vec<u32> = map
+            .chunks(4)
+            .map(|c| u32::from_le_bytes([c[0], c[1], c[2], c[3]]))
+            .collect();
+        assert_eq!(entries, [0x20, 2, 0x41, 1, 0x42, 0]);
+    }
+
+    /// Every glyph is written to a file named after its image, shared names are an error
+    #[test]
+    fn split_output() {
+        let input = tempfile::tempdir().unwrap();
+        let output = tempfile::tempdir().unwrap();
+        fs::write(
+            input.path().join("A.png"),
+            testutil::picture(&["#.......", "#......."]),
+        )
+        .unwrap();
+        fs::write(
+            input.path().join("B.png"),
+            testutil::picture(&["........", ".#......"]),
+        )
+        .unwrap();
+
+        let report =
+            generate_dir_split(input.path(), &FontGenerator::new(), output.path()).unwrap();
+        assert_eq!(report.glyphs.len(), 2);
+        assert_eq!(fs::read(output.path().join("A.bin")).unwrap(), [0x80, 0x80]);
+        assert_eq!(fs::read(output.path().join("B.bin")).unwrap(), [0x00, 0x40]);
+
+        let mirrored = FontGenerator::new().with_mirror(true);
+        let res = generate_dir_split(input.path(), &mirrored, output.path());
+        assert!(matches!(
+            res,
+            Err(GenerationError::SplitOutputCollision { file, .. }) if file == output.path().join("A.bin")
+        ));
+    }
+
+    /// The checksum table holds the CRC-32 of every glyph, flipping a single pixel changes only
+    /// the checksum of its glyph
+    #[test]
+    fn checksum_table() {
+        let dir = tempfile::tempdir().unwrap();
+        let write = |name: &str, rows: &[&str]| {
+            fs::write(dir.path().join(name), testutil::picture(rows)).unwrap();
+        };
+        write("a.png", &["#..#....", "..##...."]);
+        write("b.png", &["........", ".####..."]);
+
+        let checksums = || {
+            let mut font = Vec::new();
+            let report = generate_dir(dir.path(), &FontGenerator::new(), &mut font).unwrap();
+            let mut table = Vec::new();
+            report.write_checksums(&mut table).unwrap();
+            assert_eq!(table[..4], crc32(&font[..2]).to_le_bytes());
+            table
+        };
+        let before = checksums();
+        assert_eq!(before.len(), 8);
+
+        write("b.png", &["........", ".###...."]);
+        let after = checksums();
+        assert_eq!(after[..4], before[..4]);
+        assert_ne!(after[4..], before[4..]);
+    }
+
+    /// Digits share the forced advance while letters keep their content width
+    #[test]
+    fn force_width() {
+        let dir = tempfile::tempdir().unwrap();
+        let glyphs = [
+            ("0", [".##.....", "#..#...."]),
+            ("1", ["..#.....", "..#....."]),
+            ("A", [".###....", "#...#..."]),
+            ("i", ["#.......", "#......."]),
+        ];
+        for (name, rows) in glyphs {
+            fs::write(
+                dir.path().join(format!("{name}.png")),
+                testutil::picture(&rows),
+            )
+            .unwrap();
+        }
+
+        let digits = "0x30-0x39".parse::<CodepointRanges>().unwrap();
+        let generator =
+            FontGenerator::new().with_force_width(digits.codepoints().map(|cp| (cp, 4)).collect());
+        let report = generate_dir(dir.path(), &generator, &mut Vec::new()).unwrap();
+        let mut widths = Vec::new();
+        report.write_widths(&mut widths).unwrap();
+        assert_eq!(widths, [4, 4, 5, 1]);
+    }
+
+    /// Every glyph starts at a multiple of the page size, the gaps are erased flash
+    #[test]
+    fn glyph_page_align() {
+        let dir = tempfile::tempdir().unwrap();
+        let glyphs = [["#.......", "........"], ["..#.....", "......#."]];
+        for (i, rows) in glyphs.iter().enumerate() {
+            fs::write(dir.path().join(format!("{i}.png")), testutil::picture(rows)).unwrap();
+        }
+
+        let generator = FontGenerator::new()
+            .with_glyph_page_align(256)
+            .with_mirror(true);
+        let mut font = Vec::new();
+        let report = generate_dir(dir.path(), &generator, &mut font).unwrap();
+        let offsets: Vec<_> = report.glyphs.iter().map(|g| g.offset).collect();
+        assert_eq!(offsets, [0, 256, 512, 768]);
+        assert!(offsets.iter().all(|offset| offset % 256 == 0));
+        assert_eq!(font.len(), 768 + 2);
+        assert_eq!(report.total_bytes(), font.len());
+        assert_eq!(font[256..258], [0x20, 0x02]);
+        assert!(font[2..256].iter().all(|&b| b == 0xFF));
+    }
+
+    /// Only glyphs with whitelisted codepoints are generated
+    #[test]
+    fn include_codepoints() {
+        let dir = tempfile::tempdir().unwrap();
+        let glyph = testutil::picture(&["#.......", "........"]);
+        for name in ["0030.png", "0039.png", "0041.png", "0061.png", "space.png"] {
+            fs::write(dir.path().join(name), &glyph).unwrap();
+        }
+
+        let generator =
+            FontGenerator::new().with_include_codepoints("0x30-0x39,0x41-0x5A".parse().unwrap());
+        let mut out = Vec::new();
+        let report = generate_dir(dir.path(), &generator, &mut out).unwrap();
+
+        let codepoints: Vec<_> = report.glyphs.iter().map(|g| g.codepoint).collect();
+        assert_eq!(codepoints, [Some(0x30), Some(0x39), Some(0x41)]);
+        assert_eq!(out.len(), 6);
+        assert_eq!(report.warnings.len(), 1);
+
+        let index = GlyphIndex::from_report(&report).unwrap();
+        let offsets: Vec<_> = index.entries.iter().map(|e| e.offset).collect();
+        assert_eq!(offsets, [0, 2, 4]);
+    }
+
+    /// A nearly blank glyph doesn't reach the minimum coverage
+    #[test]
+    fn min_coverage() {
+        let dir = tempfile::tempdir().unwrap();
+        let inked = dir.path().join("inked.png");
+        let faint = dir.path().join("faint.png");
+        fs::write(&inked, testutil::picture(&["####", "#..#", "####"])).unwrap();
+        fs::write(&faint, testutil::picture(&["....", ".#..", "...."])).unwrap();
+
+        let generator = FontGenerator::new().with_min_coverage(20.0);
+        generate_file(&inked, &generator, &mut Vec::new()).unwrap();
+        let err = generate_dir(dir.path(), &generator, &mut Vec::new()).unwrap_err();
+        assert!(matches!(
+            err,
+            GenerationError::InsufficientCoverage { file, .. } if file == faint
+        ));
+    }
+
+    /// The sentinel is written once after the last glyph
+    #[test]
+    fn end_sentinel() {
+        let dir = tempfile::tempdir().unwrap();
+        for (name, rows) in [
+            ("a.png", ["#.......", "#......."]),
+            ("b.png", ["........", "........"]),
+        ] {
+            fs::write(dir.path().join(name), testutil::picture(&rows)).unwrap();
+        }
+
+        let generator = FontGenerator::new().with_end_sentinel("0xff".parse().unwrap());
+        let mut out = Vec::new();
+        let report = generate_dir(dir.path(), &generator, &mut out).unwrap();
+        assert_eq!(out, [0x80, 0x80, 0x00, 0x00, 0xFF, 0xFF]);
+        assert_eq!(report.total_bytes(), out.len());
+
+        let generator = FontGenerator::new().with_end_sentinel("0xA5:3".parse().unwrap());
+        let mut out = Vec::new();
+        generate_dir(dir.path(), &generator, &mut out).unwrap();
+        assert_eq!(out[4..], [0xA5; 3]);
+        assert_eq!(out.iter().filter(|&&b| b == 0xA5).count(), 3);
+    }
+
+    /// A glyph packed to more bytes than the budget is rejected with its file
+    #[test]
+    fn max_glyph_bytes() {
+        let dir = tempfile::tempdir().unwrap();
+        let small = dir.path().join("a.png");
+        let large = dir.path().join("b.png");
+        fs::write(&small, testutil::picture(&["#.......", "#......."])).unwrap();
+        fs::write(
+            &large,
+            testutil::picture(&["#.......", "#.......", "#......."]),
+        )
+        .unwrap();
+
+        let generator = FontGenerator::new().with_max_glyph_bytes(2);
+        assert_eq!(
+            generate_file(&small, &generator, &mut Vec::new())
+                .unwrap()
+                .byte_len,
+            2
+        );
+        let mut out = Vec::new();
+        let err = generate_dir(dir.path(), &generator, &mut out).unwrap_err();
+        assert!(matches!(
+            err,
+            GenerationError::GlyphTooLarge { file, size: 3, max: 2 } if file == large
+        ));
+        assert!(out.is_empty());
+    }
+
+    /// Bad files are skipped and their errors collected
+    #[test]
+    fn continue_on_error() {
+        let dir = tempfile::tempdir().unwrap();
+        let glyph = testutil::picture(&["#.......", "........"]);
+        fs::write(dir.path().join("a.png"), &glyph).unwrap();
+        fs::write(dir.path().join("b.png"), b"not a png").unwrap();
+        fs::write(dir.path().join("c.png"), &glyph).unwrap();
+        fs::write(dir.path().join("d.png"), &glyph[..glyph.len() / 2]).unwrap();
+
+        let generator = FontGenerator::new().with_strict(true);
+        assert!(generate_dir(dir.path(), &generator, &mut Vec::new()).is_err());
+
+        let mut out = Vec::new();
+        let report = generate_dir(
+            dir.path(),
+            &generator.with_continue_on_error(true),
+            &mut out,
+        )
+        .unwrap();
+        let failed: Vec<_> = report.errors.iter().map(|(path, _)| path.clone()).collect();
+        assert_eq!(failed, [dir.path().join("b.png"), dir.path().join("d.png")]);
+        assert_eq!(report.glyphs.len(), 2);
+        assert_eq!(out, [0x80, 0x00, 0x80, 0x00]);
+    }
+
+    /// The mirrored copy of the font has the columns of every glyph reversed
+    #[test]
+    fn with_mirror() {
+        let dir = tempfile::tempdir().unwrap();
+        let glyphs = [["#..", "#..", "##."], [".##", "..#", "..."]];
+        for (i, rows) in glyphs.iter().enumerate() {
+            fs::write(dir.path().join(format!("{i}.png")), testutil::picture(rows)).unwrap();
+        }
+
+        let generator = FontGenerator::new()
+            .with_mode(FontMode::ByteColumn)
+            .with_mirror(true);
+        let mut out = Vec::new();
+        let report = generate_dir(dir.path(), &generator, &mut out).unwrap();
+
+        assert_eq!(report.glyphs.len(), 4);
+        let (normal, mirrored) = out.split_at(out.len() / 2);
+        assert_eq!(normal, [0xE0, 0x20, 0x00, 0x00, 0x80, 0xC0]);
+        for (normal, mirrored) in normal.chunks(3).zip(mirrored.chunks(3)) {
+            let reversed: Vec<_> = normal.iter().rev().copied().collect();
+            assert_eq!(mirrored, reversed);
+        }
+        assert_eq!(report.glyphs[2].offset, 6);
+        assert_eq!(report.glyphs[2].path, report.glyphs[0].path);
+    }
+
+    /// Trimmed glyphs keep only their content columns
+    #[test]
+    fn trimmed_columns() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut narrow = vec!["..#.#..."; 7];
+        narrow.push("..##....");
+        let mut wide = vec!["...#...#"; 7];
+        wide.push("...#####");
+        fs::write(dir.path().join("a.png"), testutil::picture(&narrow)).unwrap();
+        fs::write(dir.path().join("b.png"), testutil::picture(&wide)).unwrap();
+
+        let generator = FontGenerator::new()
+            .with_mode(FontMode::ByteColumn)
+            .with_trim(true);
+        let mut out = Vec::new();
+        let report = generate_dir(dir.path(), &generator, &mut out).unwrap();
+        let index = GlyphIndex::from_report(&report).unwrap();
+
+        let widths: Vec<_> = index.entries.iter().map(|e| e.width).collect();
+        assert_eq!(widths, [3, 5]);
+        let offsets: Vec<_> = index.entries.iter().map(|e| e.offset).collect();
+        assert_eq!(offsets, [0, 3]);
+        assert_eq!(out, [0xFF, 0x01, 0xFE, 0xFF, 0x01, 0x01, 0x01, 0xFF]);
+    }
+
+    /// Reordered glyphs can be found through the index and form the original glyph set
+    #[test]
+    fn optimize_order_permutation() {
+        let dir = tempfile::tempdir().unwrap();
+        let glyphs = [
+            ["########", "########"],
+            ["........", "#......."],
+            ["#######.", "########"],
+            ["........", "##......"],
+        ];
+        for (i, rows) in glyphs.iter().enumerate() {
+            fs::write(dir.path().join(format!("{i}.png")), testutil::picture(rows)).unwrap();
+        }
+
+        let mut plain = Vec::new();
+        generate_dir(dir.path(), &FontGenerator::new(), &mut plain).unwrap();
+        let mut font = Vec::new();
+        let report = generate_dir(
+            dir.path(),
+            &FontGenerator::new().with_optimize_order(true),
+            &mut font,
+        )
+        .unwrap();
+        let index = GlyphIndex::from_report(&report).unwrap();
+
+        let order = index.storage_order();
+        assert_eq!(order, [0, 2, 3, 1]);
+        let mut sorted = order.clone();
+        sorted.sort_unstable();
+        assert_eq!(sorted, [0, 1, 2, 3]);
+
+        for n in 0..glyphs.len() {
+            let reordered = index.glyph_range(n, font.len()).unwrap();
+            assert_eq!(font[reordered], plain[n * 2..n * 2 + 2]);
+        }
+    }
+
+    /// Two 5x7 glyphs don't share a byte, the second starts on a fresh byte
+    #[test]
+    fn glyph_byte_alignment() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("a.png"), testutil::picture(&["#####"; 7])).unwrap();
+        fs::write(dir.path().join("b.png"), testutil::picture(&["#...."; 7])).unwrap();
+
+        let mut out = Vec::new();
+        let report = generate_dir(dir.path(), &FontGenerator::new(), &mut out).unwrap();
+        assert_eq!(report.glyphs[1].offset, 5);
+        assert_eq!(
+            out,
+            [0xFF, 0xFF, 0xFF, 0xFF, 0xE0, 0x84, 0x21, 0x08, 0x42, 0x00]
+        );
+    }
+
+    /// Subdirectories are generated depth-first in name order, other files and symlink loops are
+    /// skipped with a warning
+    #[test]
+    fn recursive() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("b").join("d")).unwrap();
+        for (name, rows) in [
+            ("c.png", ["#......."]),
+            ("a.png", [".#......"]),
+            ("b/e.png", ["..#....."]),
+            ("b/d/f.png", ["...#...."]),
+        ] {
+            fs::write(root.join(name), testutil::picture(&rows)).unwrap();
+        }
+        fs::write(root.join("b").join("notes.txt"), b"no glyph").unwrap();
+        #[cfg(unix)]
+        std::os::unix::fs::symlink(root, root.join("b").join("d").join("loop")).unwrap();
+
+        let generator = FontGenerator::new();
+        let flat = generate_dir(root, &generator, &mut Vec::new()).unwrap();
+        assert_eq!(flat.glyphs.len(), 2);
+
+        let mut out = Vec::new();
+        let report = generate_dir(root, &generator.with_recursive(true), &mut out).unwrap();
+        let paths: Vec<_> = report.glyphs.iter().map(|g| g.path.clone()).collect();
+        assert_eq!(
+            paths,
+            ["a.png", "b/d/f.png", "b/e.png", "c.png"].map(|name| root.join(name))
+        );
+        assert_eq!(out, [0x40, 0x10, 0x20, 0x80]);
+        assert!(report.warnings.iter().any(|w| w.contains("notes.txt")));
+        #[cfg(unix)]
+        assert!(report
+            .warnings
+            .iter()
+            .any(|w| w.contains("already visited")));
+    }
+
+    /// Files that are no images are skipped with a warning, a strict generator fails on them
+    #[test]
+    fn skips_non_images() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("a.png"), testutil::picture(&["#......."])).unwrap();
+        fs::write(dir.path().join("README.txt"), b"glyphs of the font").unwrap();
+
+        let mut out = Vec::new();
+        let report = generate_dir(dir.path(), &FontGenerator::new(), &mut out).unwrap();
+        assert_eq!(report.glyphs.len(), 1);
+        assert_eq!(out, [0x80]);
+        assert!(report.warnings[0].contains("README.txt"));
+
+        let strict = FontGenerator::new().with_strict(true);
+        assert!(generate_dir(dir.path(), &strict, &mut Vec::new()).is_err());
+    }
+
+    /// A uniform generator fails on the first image with a different size
+    #[test]
+    fn uniform_sizes() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("a.png"), testutil::picture(&["#.", ".#"])).unwrap();
+        fs::write(dir.path().join("b.png"), testutil::picture(&["##", "#."])).unwrap();
+        fs::write(dir.path().join("c.png"), testutil::picture(&["#.#", ".#."])).unwrap();
+
+        let generator = FontGenerator::new().with_uniform(true);
+        let res = generate_dir(dir.path(), &generator, &mut Vec::new());
+        let Err(GenerationError::NonUniformGlyphSize {
+            expected,
+            found,
+            file,
+        }) = res
+        else {
+            panic!("expected a size mismatch, got {:?}", res);
+        };
+        assert_eq!((expected, found), ((2, 2), (3, 2)));
+        assert_eq!(file, dir.path().join("c.png"));
+
+        let report = generate_dir(dir.path(), &FontGenerator::new(), &mut Vec::new()).unwrap();
+        assert_eq!(report.glyphs.len(), 3);
+    }
+
+    /// A file of a directory that fails to decode is named in the error
+    #[test]
+    fn error_names_file() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("a.png"), testutil::picture(&["#."])).unwrap();
+        let mut broken = testutil::picture(&["#.", ".#"]);
+        broken.truncate(broken.len() / 2);
+        fs::write(dir.path().join("b.png"), broken).unwrap();
+
+        let strict = FontGenerator::new().with_strict(true);
+        let e = generate_dir(dir.path(), &strict, &mut Vec::new()).unwrap_err();
+        let GenerationError::InFile { path, .. } = &e else {
+            panic!("expected the failing file, got {:?}", e);
+        };
+        assert_eq!(path, &dir.path().join("b.png"));
+        assert!(e.to_string().contains("b.png"));
+    }
+
+    /// An empty image in a directory is reported with its file name
+    #[test]
+    fn empty_image_in_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("a.png"), testutil::picture(&["#."])).unwrap();
+        fs::write(dir.path().join("b.png"), testutil::gray(0, 0, &[])).unwrap();
+
+        let generator = FontGenerator::new()
+            .with_strict(true)
+            .with_continue_on_error(true);
+        let report = generate_dir(dir.path(), &generator, &mut Vec::new()).unwrap();
+        assert_eq!(report.glyphs.len(), 1);
+        let (path, e) = &report.errors[0];
+        assert_eq!(path, &dir.path().join("b.png"));
+        assert!(matches!(
+            e,
+            GenerationError::EmptyImage {
+                width: 0,
+                height: 0
+            }
+        ));
+    }
+
+    /// The parallel generation writes exactly the bytes of the sequential one
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn parallel_matches_sequential() {
+        let dir = tempfile::tempdir().unwrap();
+        let rows = ["#.#.#..#", ".##..#.#", "###....#", "...##..#"];
+        for (i, name) in ["d", "a", "f", "c", "b", "e"].into_iter().enumerate() {
+            let picture: Vec<&str> = rows.iter().cycle().skip(i).take(6).copied().collect();
+            fs::write(
+                dir.path().join(format!("{name}.png")),
+                testutil::picture(&picture),
+            )
+            .unwrap();
+        }
+
+        let generator = FontGenerator::new().with_trim(true);
+        let mut sequential = Vec::new();
+        let sequential_report = generate_dir(dir.path(), &generator, &mut sequential).unwrap();
+        let mut parallel = Vec::new();
+        let parallel_report =
+            generate_dir(dir.path(), &generator.with_parallel(true), &mut parallel).unwrap();
+        assert_eq!(parallel, sequential);
+        let offsets = |report: &FontReport| -> Vec<(PathBuf, usize)> {
+            report
+                .glyphs
+                .iter()
+                .map(|glyph| (glyph.path.clone(), glyph.offset))
+                .collect()
+        };
+        assert_eq!(offsets(&parallel_report), offsets(&sequential_report));
+    }
+
+    /// Every glyph starts with a header holding its measured width, height and advance
+    #[test]
+    fn self_describing() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(
+            dir.path().join("a.png"),
+            testutil::picture(&[".##.....", "#..#...."]),
+        )
+        .unwrap();
+        fs::write(
+            dir.path().join("b.png"),
+            testutil::picture(&["..#.....", "..#....."]),
+        )
+        .unwrap();
+
+        let generator = FontGenerator::new().with_self_describing();
+        let mut out = Vec::new();
+        let report = generate_dir(dir.path(), &generator, &mut out).unwrap();
+
+        let mut offset = 0;
+        for (record, bitmap) in report.glyphs.iter().zip([[0x69], [0xC0]]) {
+            let glyph = &out[offset..offset + record.byte_len];
+            let (header, bytes) = glyph.split_at(3);
+            assert_eq!(
+                header,
+                [record.width, record.height, record.metrics.advance].map(|v| v as u8)
+            );
+            assert_eq!(bytes, bitmap);
+            offset += record.byte_len;
+        }
+        assert_eq!(out, [4, 2, 4, 0x69, 1, 2, 1, 0xC0]);
+    }
+
+    /// Packed glyphs are concatenated unchanged and indexed with their codepoints
+    #[test]
+    fn pack_bins_index() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("0041.bin"), [0x11, 0x12]).unwrap();
+        fs::write(dir.path().join("0042.bin"), [0x21]).unwrap();
+        fs::write(dir.path().join("U+0043.bin"), [0x31, 0x32, 0x33]).unwrap();
+        fs::write(dir.path().join("notes.txt"), "not a glyph").unwrap();
+
+        let mut font = Vec::new();
+        let report = pack_bins(dir.path(), (8, 2), &mut font).unwrap();
+        assert_eq!(font, [0x11, 0x12, 0x21, 0x31, 0x32, 0x33]);
+        assert_eq!(report.warnings.len(), 1);
+
+        let codepoints: Vec<_> = report.glyphs.iter().map(|g| g.codepoint).collect();
+        assert_eq!(codepoints, [Some(0x41), Some(0x42), Some(0x43)]);
+
+        let index = GlyphIndex::from_report(&report).unwrap();
+        let offsets: Vec<_> = index.entries.iter().map(|e| e.offset).collect();
+        assert_eq!(offsets, [0, 2, 3]);
+        assert!(index.entries.iter().all(|e| (e.width, e.height) == (8, 2)));
+        assert_eq!(index.glyph_range(2, font.len()), Some(3..6));
+    }
+
+    /// Blank rows are removed from the packed glyph and their count is recorded
+    #[test]
+    fn collapse_blank_rows() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(
+            dir.path().join("a.png"),
+            testutil::picture(&["........", "#..#....", "........", "........", ".##....."]),
+        )
+        .unwrap();
+
+        let generator = FontGenerator::new().with_collapse_blank_rows(true);
+        let mut out = Vec::new();
+        let report = generate_dir(dir.path(), &generator, &mut out).unwrap();
+        assert_eq!(out, [0x90, 0x60]);
+        assert_eq!(report.glyphs[0].dropped_rows, 3);
+        assert_eq!(report.glyphs[0].height, 2);
+
+        let mut metrics = Vec::new();
+        report.write_metrics(&mut metrics).unwrap();
+        assert_eq!(metrics, [4, 1, 3]);
+    }
+}
@@ -0,0 +1,204 @@
+//! Dithering of midtones into reproducible patterns of on and off pixels.
+
+use alloc::{borrow::ToOwned, string::String};
+use core::str::FromStr;
+
+/// How pixels between on and off are distributed in a monochromatic font
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Dither {
+    /// Every pixel is compared against the same threshold
+    #[default]
+    None,
+    /// Every pixel is compared against the entry of a Bayer threshold map at its coordinates,
+    /// so a uniform midtone becomes a regular pattern. Sizes that aren't a power of two are
+    /// rounded up to the next power of two, the smallest matrix is 2x2.
+    Ordered {
+        /// The number of rows and columns of the threshold map
+        matrix_size: usize,
+    },
+    /// The difference between the ink of every pixel and its on or off value is diffused to the
+    /// pixels right and below it with the Floyd–Steinberg weights, so gradients keep their
+    /// perceived brightness. The diffusion needs the whole image, it is applied when a glyph is
+    /// decoded and the threshold of single pixels is unchanged.
+    FloydSteinberg,
+}
+
+impl Dither {
+    /// The threshold for the pixel at `x`, `y`. Without dithering this is `threshold`, the
+    /// ordered dither ignores it and spreads the thresholds evenly over the range of u8. The
+    /// Floyd–Steinberg dither doesn't vary the threshold, see [`Dither::FloydSteinberg`].
+    pub fn threshold_at(self, x: usize, y: usize, threshold: u8) -> u8 {
+        match self {
+            Self::None | Self::FloydSteinberg => threshold,
+            Self::Ordered { matrix_size } => {
+                let size = matrix_size.max(2).next_power_of_two();
+                let cells = size.saturating_mul(size);
+                // The center of the cell of the entry in the range of 0 to 256
+                let center = bayer(x, y, size)
+                    .saturating_mul(2)
+                    .saturating_add(1)
+                    .saturating_mul(256)
+                    .checked_div(cells.saturating_mul(2))
+                    .unwrap_or(0);
+                u8::try_from(center).unwrap_or(u8::MAX)
+            }
+        }
+    }
+}
+
+/// Quantize the ink levels of an image stored row by row, from 0 for background to `u8::MAX` for
+/// full ink, with Floyd–Steinberg error diffusion.
+///
+/// A pixel is on if its ink plus the diffused error is above `u8::MAX - threshold`, matching the
+/// plain threshold of the color of a pixel. The error of every pixel is distributed 7/16 to the
+/// right, 3/16 to the bottom left, 5/16 to the bottom and 1/16 to the bottom right neighbor.
+#[cfg(feature = "std")]
+pub(crate) fn floyd_steinberg(levels: &[u8], width: usize, threshold: u8) -> Vec<bool> {
+    let cutoff = i32::from(u8::MAX.saturating_sub(threshold));
+    let mut ink: Vec<i32> = levels.iter().copied().map(i32::from).collect();
+    let mut pixels = Vec::with_capacity(ink.len());
+    for idx in 0..ink.len() {
+        let level = ink[idx];
+        let on = level > cutoff;
+        let error = level.saturating_sub(if on { i32::from(u8::MAX) } else { 0 });
+        pixels.push(on);
+
+        let x = idx % width.max(1);
+        let mut diffuse = |target: Option<usize>, weight: i32| {
+            if let Some(cell) = target.and_then(|target| ink.get_mut(target)) {
+                *cell = cell.saturating_add(error.saturating_mul(weight) / 16);
+            }
+        };
+        let below = idx.checked_add(width);
+        let has_right = x.saturating_add(1) < width;
+        diffuse(has_right.then(|| idx.saturating_add(1)), 7);
+        diffuse(below.filter(|_| x > 0).map(|b| b.saturating_sub(1)), 3);
+        diffuse(below, 5);
+        diffuse(below.filter(|_| has_right).map(|b| b.saturating_add(1)), 1);
+    }
+    pixels
+}
+
+/// The entry of the Bayer matrix of the power of two `size` at `x`, `y`.
+///
+/// The matrix of size 2n is built from four copies of the matrix of size n, so the lowest bits of
+/// the coordinates select the entry of the 2x2 matrix that is most significant.
+fn bayer(x: usize, y: usize, size: usize) -> usize {
+    let mut value = 0usize;
+    let mut bit = 1usize;
+    while bit < size {
+        let bx = usize::from(x & bit != 0);
+        let by = usize::from(y & bit != 0);
+        // The 2x2 matrix [[0, 2], [3, 1]]
+        value = value
+            .saturating_mul(4)
+            .saturating_add(((bx ^ by) << 1) | by);
+        bit <<= 1;
+    }
+    value
+}
+
+impl FromStr for Dither {
+    type Err = DitherParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once(':') {
+            None if s == "none" => Ok(Self::None),
+            None if s == "ordered" => Ok(Self::Ordered { matrix_size: 4 }),
+            None if s == "floyd-steinberg" || s == "fs" => Ok(Self::FloydSteinberg),
+            Some(("ordered", size)) => match size.parse::<usize>() {
+                Ok(matrix_size) if matrix_size >= 2 && matrix_size.is_power_of_two() => {
+                    Ok(Self::Ordered { matrix_size })
+                }
+                _ => Err(DitherParseError(s.to_owned())),
+            },
+            _ => Err(DitherParseError(s.to_owned())),
+        }
+    }
+}
+
+/// A dither mode was tried to be parsed that doesn't exist
+#[derive(Clone, Debug)]
+#[cfg_attr(
+    feature = "std",
+    derive(thiserror::Error),
+    error("Unsupported dither mode: {0}")
+)]
+pub struct DitherParseError(String);
+
+display_without_std!(DitherParseError);
+
+#[cfg(test)]
+#[cfg(feature = "std")]
+mod test {
+    use super::*;
+    use crate::{testutil, FontGenerator};
+
+    /// The recursive construction yields the well known 4x4 Bayer matrix
+    #[cfg(feature = "std")]
+    #[test]
+    fn bayer_matrix() {
+        let matrix: Vec<Vec<usize>> = (0..4)
+            .map(|y| (0..4).map(|x| bayer(x, y, 4)).collect())
+            .collect();
+        assert_eq!(
+            matrix,
+            [[0, 8, 2, 10], [12, 4, 14, 6], [3, 11, 1, 9], [15, 7, 13, 5]]
+        );
+    }
+
+    /// A uniform 50% gray becomes a checkerboard
+    #[cfg(feature = "std")]
+    #[test]
+    fn ordered_checkerboard() {
+        let png = testutil::gray(8, 4, &[128; 8 * 4]);
+        for matrix_size in [2, 4, 8] {
+            let generator = FontGenerator::new().with_dither(Dither::Ordered { matrix_size });
+            let mut out = Vec::new();
+            generator.generate(&png, &mut out).unwrap();
+            assert_eq!(out, [0x55, 0xAA, 0x55, 0xAA]);
+        }
+
+        let mut out = Vec::new();
+        FontGenerator::new().generate(&png, &mut out).unwrap();
+        assert_eq!(out, [0x00; 4]);
+    }
+
+    /// A horizontal gradient becomes a mix of on and off pixels getting sparser to the right
+    /// instead of a hard split at the threshold
+    #[cfg(feature = "std")]
+    #[test]
+    fn floyd_steinberg_gradient() {
+        let (width, height) = (32usize, 4usize);
+        let levels: Vec<u8> = (0..height)
+            .flat_map(|_| (0..width).map(|x| u8::try_from(x * 255 / (width - 1)).unwrap()))
+            .collect();
+        let png = testutil::gray(32, 4, &levels);
+        let decode = |dither| {
+            FontGenerator::new()
+                .with_dither(dither)
+                .glyph(&png)
+                .unwrap()
+        };
+        let transitions = |glyph: &crate::Glyph, y| {
+            (1..width)
+                .filter(|&x| glyph.get(x, y) != glyph.get(x - 1, y))
+                .count()
+        };
+
+        let plain = decode(Dither::None);
+        assert!((0..height).all(|y| transitions(&plain, y) == 1));
+
+        let dithered = decode(Dither::FloydSteinberg);
+        for y in 0..height {
+            assert!(transitions(&dithered, y) > 4);
+            assert!(dithered.get(0, y));
+            assert!(!dithered.get(width - 1, y));
+        }
+        let left = (0..8).filter(|&x| dithered.get(x, 0)).count();
+        let right = (24..32).filter(|&x| dithered.get(x, 0)).count();
+        assert!(left > right);
+        let half = width * height / 2;
+        assert!(dithered.on_count().abs_diff(half) <= width / 4);
+    }
+}
@@ -0,0 +1,410 @@
+//! Rendering of generated fonts in human readable formats.
+
+use std::{io::Write, str::FromStr};
+
+use crate::{
+    pack::{packed_len, unpack_monochromatic},
+    BitFlow, FontMode, GenerationError,
+};
+
+/// Render the bytes in the given format and write them to `out`.
+///
+/// # Errors
+/// An error is returned when writing to `out` fails, for example because of a broken pipe.
+pub fn dump(bytes: &[u8], format: DumpFormat, out: &mut impl Write) -> crate::Result<()> {
+    dump_with_options(bytes, format, &DumpOptions::default(), out)
+}
+
+/// Render the bytes in the given format with additional options and write them to `out`.
+///
+/// # Errors
+/// An error is returned when writing to `out` fails, for example because of a broken pipe.
+pub fn dump_with_options(
+    bytes: &[u8],
+    format: DumpFormat,
+    options: &DumpOptions,
+    out: &mut impl Write,
+) -> crate::Result<()> {
+    match format {
+        DumpFormat::Binary => write_numbers(bytes, |byte| format!("{:08b}", byte), out),
+        DumpFormat::Hex => write_numbers(bytes, |byte| format!("{:#04x}", byte), out),
+        DumpFormat::Decimal => write_numbers(bytes, |byte| format!("{:03}", byte), out),
+        DumpFormat::CHeader => write_c_array(bytes, options, out),
+        DumpFormat::Ascii => {
+            let (width, height) = options
+                .glyph_size
+                .ok_or(GenerationError::MissingGlyphSize)?;
+            let glyph_len = packed_len(options.font_mode, width, height)?;
+            write_ascii(bytes, width, height, glyph_len, options, out)
+        }
+    }
+    .map_err(GenerationError::OutputWriterError)
+}
+
+/// Parse the bytes back from a rendered dump
+///
+/// # Errors
+/// An error is returned when the text contains a value that is not a byte of the format or the
+/// format is [`DumpFormat::Ascii`], which can not be parsed back.
+pub fn parse_dump(text: &str, format: DumpFormat) -> crate::Result<Vec<u8>> {
+    let (values, radix) = match format {
+        DumpFormat::Binary => (text, 2),
+        DumpFormat::Hex => (text, 16),
+        DumpFormat::Decimal => (text, 10),
+        DumpFormat::CHeader => {
+            let start = text.find('{').map_or(0, |i| i.saturating_add(1));
+            let end = text.rfind('}').unwrap_or(text.len());
+            (text.get(start..end).unwrap_or(""), 16)
+        }
+        DumpFormat::Ascii => return Err(GenerationError::UnparsableDumpFormat(format)),
+    };
+
+    values
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|value| !value.is_empty())
+        .map(|value| {
+            let digits = match radix {
+                16 => value.strip_prefix("0x").unwrap_or(value),
+                _ => value,
+            };
+            u8::from_str_radix(digits, radix)
+                .map_err(|_| GenerationError::InvalidDumpValue(value.to_owned()))
+        })
+        .collect()
+}
+
+/// Render the bytes in the given format, parse them back and check that the result is identical
+/// to the input. Returns the bytes parsed from the rendered dump.
+///
+/// # Errors
+/// An error is returned when the parsed bytes differ from the input.
+pub fn verify_dump(
+    bytes: &[u8],
+    format: DumpFormat,
+    options: &DumpOptions,
+) -> crate::Result<Vec<u8>> {
+    let mut rendered = Vec::new();
+    dump_with_options(bytes, format, options, &mut rendered)?;
+    let parsed = parse_dump(&String::from_utf8_lossy(&rendered), format)?;
+    if parsed != bytes {
+        let offset = bytes
+            .iter()
+            .zip(&parsed)
+            .position(|(a, b)| a != b)
+            .unwrap_or_else(|| bytes.len().min(parsed.len()));
+        return Err(GenerationError::DumpMismatch(offset));
+    }
+    Ok(parsed)
+}
+
+/// Write every byte rendered as number followed by a space, with 8 bytes per line
+fn write_numbers(
+    bytes: &[u8],
+    render: impl Fn(u8) -> String,
+    out: &mut impl Write,
+) -> std::io::Result<()> {
+    for (&byte, i) in bytes.iter().zip(1..) {
+        write!(out, "{} ", render(byte))?;
+        if i % 8 == 0 {
+            writeln!(out)?;
+        }
+    }
+    writeln!(out)
+}
+
+/// Write the bytes as C array definition with 16 bytes per line
+fn write_c_array(bytes: &[u8], options: &DumpOptions, out: &mut impl Write) -> std::io::Result<()> {
+    writeln!(out, "#include <stdint.h>")?;
+    if options.progmem {
+        writeln!(out, "#include <avr/pgmspace.h>")?;
+    }
+    writeln!(out)?;
+
+    let identifier = options.identifier();
+    if options.define_len {
+        writeln!(
+            out,
+            "#define {}_LEN {}",
+            identifier.to_uppercase(),
+            bytes.len()
+        )?;
+        writeln!(out)?;
+    }
+
+    let qualifier = if options.progmem { " PROGMEM" } else { "" };
+    writeln!(out, "// {} bytes", bytes.len())?;
+    writeln!(out, "const uint8_t {}[]{} = {{", identifier, qualifier)?;
+    for (i, byte) in bytes.iter().enumerate() {
+        if i == 0 {
+            write!(out, "    ")?;
+        } else if i % 16 == 0 {
+            write!(out, ",\n    ")?;
+        } else {
+            write!(out, ", ")?;
+        }
+        write!(out, "{:#04x}", byte)?;
+    }
+    if !bytes.is_empty() {
+        writeln!(out)?;
+    }
+    writeln!(out, "}};")
+}
+
+/// Write the pixels of every glyph as lines of `#` for on and space for off pixels, the glyphs
+/// are separated by an empty line. The packing of the font mode and bit flow of the options is
+/// reversed, a trailing incomplete glyph is rendered with its missing pixels off.
+fn write_ascii(
+    bytes: &[u8],
+    width: usize,
+    height: usize,
+    glyph_len: usize,
+    options: &DumpOptions,
+    out: &mut impl Write,
+) -> std::io::Result<()> {
+    for (n, glyph) in bytes.chunks(glyph_len.max(1)).enumerate() {
+        if n != 0 {
+            writeln!(out)?;
+        }
+        let pixels =
+            unpack_monochromatic(glyph, width, height, options.font_mode, options.bit_flow);
+        for row in pixels.chunks(width.max(1)) {
+            let line: String = row.iter().map(|&on| if on { '#' } else { ' ' }).collect();
+            writeln!(out, "{}", line)?;
+        }
+    }
+    Ok(())
+}
+
+/// Additional options of the rendered dump
+#[derive(Debug, Clone, Default)]
+pub struct DumpOptions {
+    /// Place the C array in the flash memory of AVR microcontrollers using the `PROGMEM`
+    /// qualifier of `avr/pgmspace.h`
+    pub progmem: bool,
+    /// The name of the C array, `font` if not set
+    pub identifier: Option<String>,
+    /// Define the length of the C array as `<IDENTIFIER>_LEN`, e.g. `FONT_LEN`
+    pub define_len: bool,
+    /// The width and height of every glyph, required by the ascii format
+    pub glyph_size: Option<(usize, usize)>,
+    /// The mode the font was generated in, used by the ascii format
+    pub font_mode: FontMode,
+    /// The flow of the bits inside each byte, used by the ascii format
+    pub bit_flow: BitFlow,
+}
+
+impl DumpOptions {
+    /// The name of the C array
+    pub fn identifier(&self) -> &str {
+        self.identifier.as_deref().unwrap_or("font")
+    }
+}
+
+/// The format to show the dump in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DumpFormat {
+    /// Binary number
+    Binary,
+    /// Hexadecimal numbers
+    Hex,
+    /// Decimal numbers padded to three digits
+    Decimal,
+    /// Definition of a C array holding the bytes
+    CHeader,
+    /// Preview of the pixels of every glyph, `#` for on and space for off pixels
+    Ascii,
+}
+
+impl FromStr for DumpFormat {
+    type Err = DumpFormatParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "binary" => Ok(Self::Binary),
+            "hex" => Ok(Self::Hex),
+            "dec" | "decimal" => Ok(Self::Decimal),
+            "c" | "c-header" => Ok(Self::CHeader),
+            "ascii" | "preview" => Ok(Self::Ascii),
+            s => Err(DumpFormatParseError(s.to_owned())),
+        }
+    }
+}
+
+/// An error that occurs when trying to parse a dump format that doesn't exist
+#[derive(Debug, Clone, thiserror::Error)]
+#[error("Unsupported dump format: {0}")]
+pub struct DumpFormatParseError(String);
+
+#[cfg(test)]
+mod test {
+    use std::io;
+
+    use super::*;
+
+    /// Writer that fails like a closed pipe
+    struct BrokenPipe;
+
+    impl Write for BrokenPipe {
+        fn write(&mut self, _buf: &[u8]) -> io::Result<usize> {
+            Err(io::ErrorKind::BrokenPipe.into())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    /// Write errors are returned instead of panicking
+    #[test]
+    fn dump_write_error() {
+        let res = dump(&[1, 2, 3], DumpFormat::Hex, &mut BrokenPipe);
+        assert!(matches!(
+            res,
+            Err(GenerationError::OutputWriterError(e)) if e.kind() == io::ErrorKind::BrokenPipe
+        ));
+    }
+
+    /// The formats wrap after 8 bytes
+    #[test]
+    fn dump_formats() {
+        let mut out = Vec::new();
+        dump(&[0x0f; 9], DumpFormat::Hex, &mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "0x0f 0x0f 0x0f 0x0f 0x0f 0x0f 0x0f 0x0f \n0x0f \n"
+        );
+
+        let mut out = Vec::new();
+        dump(&[5, 255], DumpFormat::Binary, &mut out).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "00000101 11111111 \n");
+    }
+
+    /// Decimal bytes are padded to three digits
+    #[test]
+    fn decimal_format() {
+        let mut out = Vec::new();
+        dump(&[7, 42, 255], DumpFormat::Decimal, &mut out).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "007 042 255 \n");
+    }
+
+    /// The PROGMEM array includes the AVR header and keeps all bytes
+    #[test]
+    fn c_array_progmem() {
+        let bytes: Vec<u8> = (0..18).collect();
+        let mut out = Vec::new();
+        let options = DumpOptions {
+            progmem: true,
+            ..DumpOptions::default()
+        };
+        dump_with_options(&bytes, DumpFormat::CHeader, &options, &mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "#include <stdint.h>\n\
+             #include <avr/pgmspace.h>\n\
+             \n\
+             // 18 bytes\n\
+             const uint8_t font[] PROGMEM = {\n    \
+             0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, \
+             0x08, 0x09, 0x0a, 0x0b, 0x0c, 0x0d, 0x0e, 0x0f,\n    \
+             0x10, 0x11\n\
+             };\n"
+        );
+
+        let mut out = Vec::new();
+        dump(&[0xff], DumpFormat::CHeader, &mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(!out.contains("PROGMEM") && !out.contains("pgmspace"));
+        assert!(out.contains("const uint8_t font[] = {\n    0xff\n};"));
+    }
+
+    /// The identifier names the array and its length define, commas only separate bytes
+    #[test]
+    fn c_array_identifier() {
+        let options = DumpOptions {
+            identifier: Some("small_font".to_owned()),
+            define_len: true,
+            ..DumpOptions::default()
+        };
+        let mut out = Vec::new();
+        dump_with_options(&[0x00, 0x1f, 0xff], DumpFormat::CHeader, &options, &mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "#include <stdint.h>\n\
+             \n\
+             #define SMALL_FONT_LEN 3\n\
+             \n\
+             // 3 bytes\n\
+             const uint8_t small_font[] = {\n    \
+             0x00, 0x1f, 0xff\n\
+             };\n"
+        );
+    }
+
+    /// The ascii format reverses the packing and draws every glyph on its own block
+    #[test]
+    fn ascii_preview() {
+        let options = DumpOptions {
+            glyph_size: Some((3, 4)),
+            ..DumpOptions::default()
+        };
+        let mut out = Vec::new();
+        dump_with_options(
+            &[0x57, 0xA0, 0xAA, 0x00],
+            DumpFormat::Ascii,
+            &options,
+            &mut out,
+        )
+        .unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            " # \n# #\n###\n # \n\n# #\n # \n#  \n   \n"
+        );
+
+        let options = DumpOptions {
+            glyph_size: Some((3, 4)),
+            font_mode: FontMode::Column,
+            bit_flow: BitFlow::Ltm,
+            ..DumpOptions::default()
+        };
+        let mut out = Vec::new();
+        dump_with_options(&[0x37, 0x0F], DumpFormat::Ascii, &options, &mut out).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "###\n###\n# #\n  #\n");
+
+        assert!(matches!(
+            dump(&[0xFF], DumpFormat::Ascii, &mut Vec::new()),
+            Err(GenerationError::MissingGlyphSize)
+        ));
+    }
+
+    /// Random bytes pass through every format unchanged
+    #[test]
+    fn verify_passthrough() {
+        let mut state = 0x2545_F491_u32;
+        let bytes: Vec<u8> = (0..1000)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                state.to_le_bytes()[0]
+            })
+            .collect();
+
+        for format in [
+            DumpFormat::Binary,
+            DumpFormat::Hex,
+            DumpFormat::Decimal,
+            DumpFormat::CHeader,
+        ] {
+            let options = DumpOptions {
+                progmem: true,
+                define_len: true,
+                ..DumpOptions::default()
+            };
+            assert_eq!(verify_dump(&bytes, format, &options).unwrap(), bytes);
+        }
+        assert!(matches!(
+            parse_dump("0x01 0x1g", DumpFormat::Hex),
+            Err(GenerationError::InvalidDumpValue(value)) if value == "0x1g"
+        ));
+    }
+}
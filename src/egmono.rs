@@ -0,0 +1,117 @@
+//! Output in the raw image layout of the `MonoFont` of the embedded-graphics crate.
+
+use std::io::Write;
+
+use crate::{GenerationError, Glyph, SheetLayout};
+
+/// Generate an atlas of glyphs as image data of an embedded-graphics `MonoFont`, keeping the
+/// grid of the atlas. Returns the number of bytes written.
+///
+/// See [`generate_eg_mono_tiled`] for the layout of the output.
+///
+/// # Errors
+/// An error is returned when the image data can not be decoded as png, its width or height is
+/// not a multiple of the glyph size or writing to the `out` writer fails.
+pub fn generate_eg_mono(
+    data: &[u8],
+    glyph_width: usize,
+    glyph_height: usize,
+    out: &mut impl Write,
+) -> crate::Result<usize> {
+    let atlas = Glyph::decode(data)?;
+    let columns = atlas.width().checked_div(glyph_width).unwrap_or(0);
+    write_eg_mono(&atlas, glyph_width, glyph_height, columns, out)
+}
+
+/// Generate an atlas of glyphs as image data of an embedded-graphics `MonoFont` with `columns`
+/// glyphs in every row of the strip. Returns the number of bytes written.
+///
+/// The glyphs are read from the atlas left to right, top to bottom and tiled in the same order
+/// into a strip image `columns * glyph_width` pixels wide, cells after the last glyph stay off.
+/// The strip is written row by row, the most significant bit of a byte is the leftmost pixel and
+/// a set bit is an on pixel. Every row of the strip starts on a fresh byte, the last byte of a
+/// row is padded with zero bits. A single column of 3x5 glyphs uses one byte per row with the
+/// pixels in its top 3 bits.
+///
+/// # Errors
+/// An error is returned when the image data can not be decoded as png, its width or height is
+/// not a multiple of the glyph size or writing to the `out` writer fails.
+pub fn generate_eg_mono_tiled(
+    data: &[u8],
+    glyph_width: usize,
+    glyph_height: usize,
+    columns: usize,
+    out: &mut impl Write,
+) -> crate::Result<usize> {
+    let atlas = Glyph::decode(data)?;
+    write_eg_mono(&atlas, glyph_width, glyph_height, columns, out)
+}
+
+/// Split the atlas into glyphs and write them tiled into a strip of `columns` glyphs per row
+fn write_eg_mono(
+    atlas: &Glyph,
+    glyph_width: usize,
+    glyph_height: usize,
+    columns: usize,
+    out: &mut impl Write,
+) -> crate::Result<usize> {
+    let (width, height) = (atlas.width(), atlas.height());
+    let fits = |len: usize, cell: usize| cell != 0 && len.is_multiple_of(cell);
+    if !fits(width, glyph_width) || !fits(height, glyph_height) {
+        return Err(GenerationError::AtlasDimensionMismatch {
+            width,
+            height,
+            cell_width: glyph_width,
+            cell_height: glyph_height,
+        });
+    }
+
+    let glyphs = SheetLayout::new(glyph_width, glyph_height).split(atlas);
+    let columns = columns.max(1);
+    let strip_width = columns.saturating_mul(glyph_width);
+    let mut written = 0usize;
+    for strip_row in glyphs.chunks(columns) {
+        for y in 0..glyph_height {
+            let mut bytes = vec![0u8; strip_width.div_ceil(8)];
+            for (column, glyph) in strip_row.iter().enumerate() {
+                for x in (0..glyph_width).filter(|&x| glyph.get(x, y)) {
+                    let px = column * glyph_width + x;
+                    bytes[px / 8] |= 0x80 >> (px % 8);
+                }
+            }
+            out.write_all(&bytes)
+                .map_err(GenerationError::OutputWriterError)?;
+            written = written.saturating_add(bytes.len());
+        }
+    }
+    Ok(written)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::testutil;
+
+    /// A 3x5 glyph uses one padded byte per row, two glyphs side by side share the row bytes
+    #[test]
+    fn padded_rows() {
+        let atlas = testutil::picture(&["#.#..#", ".#.###", "###...", "#....#", "..###."]);
+        let mut single = Vec::new();
+        let written = generate_eg_mono_tiled(&atlas, 3, 5, 1, &mut single).unwrap();
+        assert_eq!(written, 10);
+        assert_eq!(
+            single,
+            [0xA0, 0x40, 0xE0, 0x80, 0x20, 0x20, 0xE0, 0x00, 0x20, 0xC0]
+        );
+
+        let mut strip = Vec::new();
+        generate_eg_mono(&atlas, 3, 5, &mut strip).unwrap();
+        assert_eq!(strip, [0xA4, 0x5C, 0xE0, 0x84, 0x38]);
+
+        let res = generate_eg_mono(&atlas, 4, 5, &mut Vec::new());
+        assert!(matches!(
+            res,
+            Err(GenerationError::AtlasDimensionMismatch { width: 6, .. })
+        ));
+    }
+}
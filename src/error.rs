@@ -1,27 +1,268 @@
 //! Error type definition for this crate.
 
-use std::io;
+use alloc::string::String;
+#[cfg(feature = "std")]
+use std::{io, path::PathBuf};
 
 use crate::imagedecode::{ColorSpace, PngDecodeErrors};
+#[cfg(feature = "std")]
+use crate::settings::DirSettingsParseError;
 
 /// An error that can occur during font generation
-#[derive(thiserror::Error, Debug)]
+///
+/// Variants that name files or wrap io errors only exist with the `std` feature.
+#[derive(Debug)]
+#[cfg_attr(feature = "std", derive(thiserror::Error))]
 pub enum GenerationError {
     /// The given image has an unsupported colorspace see:
-    /// [SupportedColorSpace](crate::imagedecode::SupportedColorSpace)
-    #[error("The given colorspace {0:?} is not supported")]
+    /// [`SupportedColorSpace`](crate::SupportedColorSpace)
+    #[cfg_attr(feature = "std", error("The given colorspace {0:?} is not supported"))]
     UnsupportedColorspace(ColorSpace),
+    /// The decoder returned the pixels in a sample type that can't be read
+    #[cfg_attr(feature = "std", error("The decoded color depth is not supported"))]
+    UnsupportedColorDepth,
+    /// Columns can only be aligned to 8, 16 or 32 pixels
+    #[cfg_attr(
+        feature = "std",
+        error("Unsupported column alignment of {0} pixels, expected 8, 16 or 32")
+    )]
+    UnsupportedColumnAlign(u8),
     /// An error occured while decoding a given png.
-    #[error("Error while decoding png: {0:?}")]
+    #[cfg_attr(feature = "std", error("Error while decoding png: {0:?}"))]
     PngDecodingError(PngDecodeErrors),
     /// Error that occurs when writing to the given
+    #[cfg(feature = "std")]
     #[error("Error while writing to the output writer: {0}")]
     OutputWriterError(io::Error),
+    /// Grayscale pixels can't be packed with the given number of bits
+    #[cfg_attr(feature = "std", error("Unsupported number of bits per pixel: {0}"))]
+    UnsupportedBitsPerPixel(u8),
+    /// A glyph has less on pixels than required
+    #[cfg(feature = "std")]
+    #[error("The glyph {file} has a coverage of {coverage:.1}% but at least {min}% are required")]
+    InsufficientCoverage {
+        /// The file the glyph was generated from
+        file: PathBuf,
+        /// The percentage of on pixels of the glyph
+        coverage: f64,
+        /// The required percentage of on pixels
+        min: f64,
+    },
+    /// The settings file of a directory is invalid
+    #[cfg(feature = "std")]
+    #[error("Invalid settings file {file}: {source}")]
+    InvalidSettings {
+        /// The path of the settings file
+        file: PathBuf,
+        /// The error of the invalid line
+        source: DirSettingsParseError,
+    },
+    /// A rendered dump contains a value that is not a byte of its format
+    #[cfg_attr(feature = "std", error("Invalid value in dump: {0}"))]
+    InvalidDumpValue(String),
+    /// The bytes parsed back from a rendered dump differ from the dumped bytes
+    #[cfg_attr(
+        feature = "std",
+        error("The dump doesn't reproduce the input, first difference at byte {0}")
+    )]
+    DumpMismatch(usize),
+    /// The ascii dump needs the size of the glyphs to reconstruct their pixels
+    #[cfg_attr(
+        feature = "std",
+        error("The ascii dump needs the width and height of the glyphs")
+    )]
+    MissingGlyphSize,
+    /// A rendered dump of this format can not be parsed back into bytes
+    #[cfg(feature = "std")]
+    #[error("The {0:?} dump format can not be parsed back")]
+    UnparsableDumpFormat(crate::DumpFormat),
+    /// A buffer of raw pixels doesn't match the given dimensions
+    #[cfg_attr(
+        feature = "std",
+        error("Expected {expected} bytes of pixel data but got {found}")
+    )]
+    PixelBufferSize {
+        /// The number of bytes required by the dimensions
+        expected: usize,
+        /// The length of the buffer
+        found: usize,
+    },
+    /// Run length encoded data ends in the middle of a pair or contains an empty run
+    #[cfg_attr(feature = "std", error("Malformed run length encoding at byte {0}"))]
+    MalformedRle(usize),
+    /// A serialized patch ends in the middle of its header or a record
+    #[cfg_attr(feature = "std", error("Malformed patch at byte {0}"))]
+    MalformedPatch(usize),
+    /// A patch record reaches past the end of the patched font
+    #[cfg_attr(
+        feature = "std",
+        error("Patch record of {len} bytes at offset {offset} is out of range")
+    )]
+    PatchOutOfRange {
+        /// The offset of the record
+        offset: usize,
+        /// The number of bytes of the record
+        len: usize,
+    },
+    /// Two glyphs of a directory would be written to the same file of the split output
+    #[cfg(feature = "std")]
+    #[error("The glyphs {first} and {second} would both be written to {file}")]
+    SplitOutputCollision {
+        /// The output file
+        file: PathBuf,
+        /// The first glyph written to the file
+        first: PathBuf,
+        /// The second glyph written to the file
+        second: PathBuf,
+    },
+    /// An image has no pixels because its width or height is 0
+    #[cfg_attr(feature = "std", error("Image of {width}x{height} pixels is empty"))]
+    EmptyImage {
+        /// The width of the image
+        width: usize,
+        /// The height of the image
+        height: usize,
+    },
+    /// An atlas image can not be divided into whole cells of the given size
+    #[cfg_attr(
+        feature = "std",
+        error("Atlas of {width}x{height} pixels is no grid of {cell_width}x{cell_height} cells")
+    )]
+    AtlasDimensionMismatch {
+        /// The width of the atlas
+        width: usize,
+        /// The height of the atlas
+        height: usize,
+        /// The width of a single cell
+        cell_width: usize,
+        /// The height of a single cell
+        cell_height: usize,
+    },
+    /// The dimensions of an image overflow the pixel count or exceed the configured maximum
+    #[cfg_attr(
+        feature = "std",
+        error("Image of {width}x{height} pixels is too large")
+    )]
+    ImageTooLarge {
+        /// The width of the image
+        width: usize,
+        /// The height of the image
+        height: usize,
+    },
+    /// A luminance lookup table doesn't have exactly one entry for every luminance
+    #[cfg_attr(
+        feature = "std",
+        error("Expected a luminance table of 256 bytes but got {0}")
+    )]
+    InvalidLuminanceLut(usize),
+    /// An image that is no png could not be decoded by the image crate
+    #[cfg_attr(feature = "std", error("Error while decoding image: {0}"))]
+    ImageDecodingError(String),
+    /// An SVG image could not be parsed or rasterized
+    #[cfg_attr(feature = "std", error("Error while rasterizing svg: {0}"))]
+    SvgError(String),
+    /// The clipboard could not be accessed
+    #[cfg_attr(feature = "std", error("Error while accessing the clipboard: {0}"))]
+    ClipboardError(String),
+    /// A packed glyph is larger than the byte budget of a glyph
+    #[cfg(feature = "std")]
+    #[error("The glyph {file} needs {size} bytes but at most {max} bytes are allowed")]
+    GlyphTooLarge {
+        /// The file the glyph was generated from
+        file: PathBuf,
+        /// The number of bytes of the packed glyph
+        size: usize,
+        /// The maximum number of bytes of a glyph
+        max: usize,
+    },
+    /// A value doesn't fit into its field of a glyph index entry
+    #[cfg_attr(
+        feature = "std",
+        error("The {field} {value} is too large to be stored in the glyph index")
+    )]
+    IndexValueTooLarge {
+        /// The name of the field
+        field: &'static str,
+        /// The value that should have been stored
+        value: usize,
+    },
+    /// A serialized glyph index doesn't consist of complete entries
+    #[cfg_attr(feature = "std", error("Malformed glyph index of {0} bytes"))]
+    MalformedIndex(usize),
+    /// Data starting with the font magic has no valid font header
+    #[cfg_attr(feature = "std", error("Malformed font header: {0}"))]
+    MalformedFontHeader(String),
+    /// A glyph was referenced that is not part of the font
+    #[cfg_attr(feature = "std", error("The glyph {0} is not part of the font"))]
+    UnknownGlyph(usize),
+    /// A patched glyph doesn't have the size of the glyph it should replace
+    #[cfg_attr(
+        feature = "std",
+        error("Glyph {glyph} can't be patched, expected {expected} bytes but generated {found}")
+    )]
+    PatchSizeMismatch {
+        /// The number of the patched glyph
+        glyph: usize,
+        /// The size of the existing glyph
+        expected: usize,
+        /// The size of the regenerated glyph
+        found: usize,
+    },
+    /// An image of a directory doesn't have the size of the first one, see
+    /// [`FontGenerator::with_uniform`](crate::FontGenerator::with_uniform)
+    #[cfg(feature = "std")]
+    #[error(
+        "{} has {}x{} pixels but the glyphs of the font have {}x{}",
+        file.display(), found.0, found.1, expected.0, expected.1
+    )]
+    NonUniformGlyphSize {
+        /// The width and height of the first image
+        expected: (usize, usize),
+        /// The width and height of the offending image
+        found: (usize, usize),
+        /// The offending image
+        file: PathBuf,
+    },
+    /// Generating the glyph of a file of a directory failed
+    #[cfg(feature = "std")]
+    #[error("Error in {}: {source}", path.display())]
+    InFile {
+        /// The file whose glyph failed
+        path: PathBuf,
+        /// The error generating the glyph
+        source: Box<GenerationError>,
+    },
     /// Another generic io Error
+    #[cfg(feature = "std")]
     #[error("An unexpected io error occured: {0}")]
     IoError(#[from] io::Error),
 }
 
+/// Without `std` there is no message for every variant, the error is displayed like its debug
+/// representation
+#[cfg(not(feature = "std"))]
+impl core::fmt::Display for GenerationError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{self:?}")
+    }
+}
+
+#[cfg(feature = "std")]
+impl GenerationError {
+    /// Attach the file the error occured in, errors that already name their file are kept
+    pub(crate) fn in_file(self, path: PathBuf) -> Self {
+        match self {
+            Self::InsufficientCoverage { .. }
+            | Self::GlyphTooLarge { .. }
+            | Self::InFile { .. } => self,
+            source => Self::InFile {
+                path,
+                source: Box::new(source),
+            },
+        }
+    }
+}
+
 impl From<PngDecodeErrors> for GenerationError {
     fn from(e: PngDecodeErrors) -> Self {
         Self::PngDecodingError(e)
@@ -0,0 +1,94 @@
+//! Control over how often the output of a generation run is flushed.
+
+use std::{
+    io::{self, Write},
+    str::FromStr,
+};
+
+/// When the writer a font is generated into is flushed, for slow transports like a serial
+/// connection to a device
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FlushPolicy {
+    /// Flush once after the last byte of the run
+    #[default]
+    End,
+    /// Flush after every glyph
+    EveryGlyph,
+    /// Flush as soon as at least this many bytes were written since the last flush
+    EveryBytes(usize),
+}
+
+impl FromStr for FlushPolicy {
+    type Err = FlushPolicyParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "end" => Ok(Self::End),
+            "glyph" => Ok(Self::EveryGlyph),
+            s => match s.parse() {
+                Ok(bytes) if bytes != 0 => Ok(Self::EveryBytes(bytes)),
+                _ => Err(FlushPolicyParseError(s.to_owned())),
+            },
+        }
+    }
+}
+
+/// A flush policy was tried to be parsed that doesn't exist
+#[derive(Clone, Debug, thiserror::Error)]
+#[error("Unsupported flush policy, expected end, glyph or a number of bytes: {0}")]
+pub struct FlushPolicyParseError(String);
+
+/// Writer flushing its inner writer according to a [`FlushPolicy`]
+pub(crate) struct FlushWriter<W> {
+    /// The wrapped writer
+    inner: W,
+    /// When the inner writer is flushed
+    policy: FlushPolicy,
+    /// The number of bytes written since the last flush
+    pending: usize,
+}
+
+impl<W: Write> FlushWriter<W> {
+    /// Wrap `inner`, flushing it as described by the policy
+    pub(crate) fn new(inner: W, policy: FlushPolicy) -> Self {
+        Self {
+            inner,
+            policy,
+            pending: 0,
+        }
+    }
+
+    /// A complete glyph was written
+    pub(crate) fn glyph_done(&mut self) -> io::Result<()> {
+        if self.policy == FlushPolicy::EveryGlyph {
+            self.flush_pending()?;
+        }
+        Ok(())
+    }
+
+    /// Flush the inner writer if anything was written since the last flush
+    pub(crate) fn flush_pending(&mut self) -> io::Result<()> {
+        if self.pending != 0 {
+            self.flush()?;
+        }
+        Ok(())
+    }
+}
+
+impl<W: Write> Write for FlushWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.pending = self.pending.saturating_add(written);
+        if let FlushPolicy::EveryBytes(bytes) = self.policy {
+            if self.pending >= bytes {
+                self.flush()?;
+            }
+        }
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.pending = 0;
+        self.inner.flush()
+    }
+}
@@ -0,0 +1,1273 @@
+//! Configurable generation of monochromatic fonts.
+
+use std::{
+    collections::HashMap,
+    io::{Read, Write},
+    str::FromStr,
+};
+
+use crate::{
+    codepoint::CodepointRanges,
+    glyph::{BoundingBox, Glyph},
+    imagedecode,
+    pack::BitPacker,
+    BboxRule, BitFlow, BitRunEndianness, ColumnByteOrder, Dither, FlushPolicy, FontMode, LumaMode,
+    OnColor, Orientation, PaddingMode, SheetLayout,
+};
+
+/// Builder holding all options used to generate a monochromatic font from png data.
+#[derive(Debug, Clone, Default)]
+pub struct FontGenerator {
+    /// The mode in which the font should be generated
+    font_mode: FontMode,
+    /// The flow of the bits inside each byte
+    bit_flow: BitFlow,
+    /// How the alignment padding of the font mode is filled
+    padding_mode: PaddingMode,
+    /// The order of the blocks of 8 rows in the byte column mode
+    column_byte_order: ColumnByteOrder,
+    /// Number of scanned pixels after which the packer starts a fresh byte
+    glyph_stride: Option<usize>,
+    /// The color of on pixels, replacing the threshold if set
+    on_color: Option<OnColor>,
+    /// Size of the cell fully blank glyphs are normalized to
+    blank_cell: Option<(usize, usize)>,
+    /// Length and byte order of bit runs spanning multiple bytes
+    bit_run: Option<(usize, BitRunEndianness)>,
+    /// Wether the glyphs of a font are reordered so similar glyphs are adjacent
+    optimize_order: bool,
+    /// Codepoints of the glyphs to include when generating a directory
+    include_codepoints: Option<CodepointRanges>,
+    /// Wether glyphs of a directory without a codepoint in their file name are skipped
+    codepoint_from_filename: bool,
+    /// Minimum percentage of on pixels every glyph file must have
+    min_coverage: Option<f64>,
+    /// Wether a horizontally mirrored copy of the font is appended
+    with_mirror: bool,
+    /// Wether the blank columns left and right of every glyph are removed
+    trim: bool,
+    /// Wether glyphs are cropped to the bounding box of their on pixels in both directions
+    auto_crop: bool,
+    /// Wether files that fail to generate are skipped when generating a directory
+    continue_on_error: bool,
+    /// Wether the subdirectories of a directory are generated too
+    recursive: bool,
+    /// Wether files of a directory that are no images fail the generation
+    strict: bool,
+    /// Wether all images of a directory must have the same size
+    uniform: bool,
+    /// Wether the glyphs of a directory are decoded on a thread pool
+    #[cfg(feature = "rayon")]
+    parallel: bool,
+    /// Factor the advance widths of the metrics are multiplied with, 1 if not set
+    advance_scale: Option<f64>,
+    /// Wether the advance widths of the metrics are stored as 8.8 fixed point numbers
+    advance_fixed_point: bool,
+    /// Wether every glyph is followed by its mask plane
+    mask_plane: bool,
+    /// Sequence appended after the last glyph of a directory
+    end_sentinel: Option<EndSentinel>,
+    /// Maximum number of bytes of a single packed glyph
+    max_glyph_bytes: Option<usize>,
+    /// Cutoff deciding wether a pixel is on, the default threshold if not set
+    threshold: Option<u8>,
+    /// Maximum number of scanned pixels of an image, the default maximum if not set
+    max_image_pixels: Option<usize>,
+    /// Table mapping the luminance of a pixel to its effective luminance before thresholding
+    luminance_lut: Option<[u8; 256]>,
+    /// Which pixels count towards the bounding box used for trimming
+    bbox_rule: BboxRule,
+    /// How midtones are distributed into on and off pixels
+    dither: Dither,
+    /// How the color of a pixel decides wether it is on
+    luma_mode: LumaMode,
+    /// Advance widths replacing the content width of the glyphs with these codepoints
+    force_width: HashMap<u32, usize>,
+    /// Byte order of bit runs spanning exactly one row of each glyph
+    row_bit_run: Option<BitRunEndianness>,
+    /// Size of the flash pages every glyph of a directory starts at
+    glyph_page_align: Option<usize>,
+    /// Wether every glyph is stored with the compression producing the fewest bytes
+    compress: bool,
+    /// Wether the orientation metadata of png images is applied
+    normalize_orientation: bool,
+    /// Wether the fully blank rows of every glyph are dropped in row mode
+    collapse_blank_rows: bool,
+    /// Wether every glyph of a directory is prefixed by its width, height and advance
+    glyph_header: bool,
+    /// When the output of a directory is flushed
+    flush_policy: FlushPolicy,
+    /// Wether on pixels are written as 0 and off pixels as 1
+    invert: bool,
+}
+
+impl FontGenerator {
+    /// Create a new generator using the default options
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the mode in which the font should be generated
+    #[must_use]
+    pub fn with_mode(mut self, font_mode: FontMode) -> Self {
+        self.font_mode = font_mode;
+        self
+    }
+
+    /// Set the flow of the bits inside each byte
+    #[must_use]
+    pub fn with_bit_flow(mut self, bit_flow: BitFlow) -> Self {
+        self.bit_flow = bit_flow;
+        self
+    }
+
+    /// Decide wether a pixel is on by its color instead of its luminance, see [`OnColor`]. The
+    /// threshold, dither, luminance table and luma mode are ignored while an on color is set.
+    #[must_use]
+    pub fn with_on_color(mut self, on_color: Option<OnColor>) -> Self {
+        self.on_color = on_color;
+        self
+    }
+
+    /// Start a fresh byte after every `glyph_stride` scanned pixels, for images holding several
+    /// glyphs one after another in scan order, e.g. one glyph per row in [`FontMode::Row`].
+    /// Without a stride, or a stride of 0, only the end of the image is padded to a full byte.
+    #[must_use]
+    pub fn with_glyph_stride(mut self, glyph_stride: Option<usize>) -> Self {
+        self.glyph_stride = glyph_stride.filter(|&stride| stride != 0);
+        self
+    }
+
+    /// Set the order of the blocks of 8 rows of glyphs taller than 8 pixels, only used in
+    /// [`FontMode::ByteColumn`] and with blocks as tall as the columns in
+    /// [`FontMode::AlignedColumn`]
+    #[must_use]
+    pub fn with_column_byte_order(mut self, column_byte_order: ColumnByteOrder) -> Self {
+        self.column_byte_order = column_byte_order;
+        self
+    }
+
+    /// Set how the alignment padding of the font mode is filled
+    #[must_use]
+    pub fn with_padding_mode(mut self, padding_mode: PaddingMode) -> Self {
+        self.padding_mode = padding_mode;
+        self
+    }
+
+    /// Emit every glyph without any on pixel as blank glyph of exactly `width` x `height`.
+    ///
+    /// This is useful for intentionally empty glyphs like space which might be exported with a
+    /// slightly different size than the rest of the font.
+    #[must_use]
+    pub fn with_blank_cell(mut self, width: usize, height: usize) -> Self {
+        self.blank_cell = Some((width, height));
+        self
+    }
+
+    /// Group the scanned pixels into runs of `run_len` pixels, e.g. one row of a glyph.
+    ///
+    /// Every run is padded to full bytes and its bytes are written in the order given by
+    /// `endianness`. Without bit runs every byte is written as soon as it is filled.
+    #[must_use]
+    pub fn with_bit_run(mut self, run_len: usize, endianness: BitRunEndianness) -> Self {
+        self.bit_run = Some((run_len, endianness));
+        self
+    }
+
+    /// Group the scanned pixels into runs of exactly the width of each glyph, see
+    /// [`Self::with_bit_run`]. In [`FontMode::Row`] every row of a glyph starts in a new byte.
+    /// A fixed length set by [`Self::with_bit_run`] takes precedence.
+    #[must_use]
+    pub fn with_row_bit_run(mut self, endianness: BitRunEndianness) -> Self {
+        self.row_bit_run = Some(endianness);
+        self
+    }
+
+    /// Preset for the graphic display RAM of ST7920 LCDs: rows scanned left to right with the
+    /// first pixel as most significant bit, every glyph row padded to full bytes and written
+    /// high byte first, as expected by the 16 bit words of the horizontal addressing.
+    #[must_use]
+    pub fn with_st7920(self) -> Self {
+        self.with_mode(FontMode::Row)
+            .with_bit_flow(BitFlow::Mtl)
+            .with_row_bit_run(BitRunEndianness::Big)
+    }
+
+    /// Prefix every glyph of a directory with a header of three bytes: the width, the height and
+    /// the advance width scaled by the advance scale, each saturated at 255. The header is part
+    /// of the glyph, its offset points to the header and its length includes it.
+    #[must_use]
+    pub fn with_glyph_header(mut self, glyph_header: bool) -> Self {
+        self.glyph_header = glyph_header;
+        self
+    }
+
+    /// Preset for self describing variable width fonts: every glyph is trimmed to its content
+    /// and prefixed by the header of [`with_glyph_header`](Self::with_glyph_header), so a font
+    /// can be drawn without any further table.
+    #[must_use]
+    pub fn with_self_describing(self) -> Self {
+        self.with_mode(FontMode::Row)
+            .with_trim(true)
+            .with_glyph_header(true)
+    }
+
+    /// Reorder the glyphs of a font so similar glyphs are adjacent, which improves the ratio of
+    /// delta or dictionary compression. The [`GlyphIndex`](crate::GlyphIndex) still lists the
+    /// glyphs in their original order.
+    #[must_use]
+    pub fn with_optimize_order(mut self, optimize_order: bool) -> Self {
+        self.optimize_order = optimize_order;
+        self
+    }
+
+    /// Only generate the glyphs of a directory whose codepoint, as derived by
+    /// [`codepoint_from_filename`](crate::codepoint_from_filename), is part of the ranges.
+    #[must_use]
+    pub fn with_include_codepoints(mut self, include_codepoints: CodepointRanges) -> Self {
+        self.include_codepoints = Some(include_codepoints);
+        self
+    }
+
+    /// Require the file name of every glyph of a directory to name its codepoint, as derived by
+    /// [`codepoint_from_filename`](crate::codepoint_from_filename). Files without a codepoint are
+    /// skipped with a warning, so every glyph appears in the codepoint map of the report.
+    #[must_use]
+    pub fn with_codepoint_from_filename(mut self, codepoint_from_filename: bool) -> Self {
+        self.codepoint_from_filename = codepoint_from_filename;
+        self
+    }
+
+    /// Require every glyph file to have at least `percent` percent on pixels to catch under
+    /// inked glyphs. Blank glyphs normalized by [`Self::with_blank_cell`] are exempt.
+    #[must_use]
+    pub fn with_min_coverage(mut self, percent: f64) -> Self {
+        self.min_coverage = Some(percent);
+        self
+    }
+
+    /// Append a horizontally mirrored copy of every glyph after the normal font, for example for
+    /// transparent double sided displays.
+    #[must_use]
+    pub fn with_mirror(mut self, with_mirror: bool) -> Self {
+        self.with_mirror = with_mirror;
+        self
+    }
+
+    /// Remove the blank columns left and right of every glyph, the height is kept. Combined with
+    /// [`FontMode::ByteColumn`] this packs proportional fonts tightly, the width of every glyph is
+    /// part of its record and the glyph index.
+    #[must_use]
+    pub fn with_trim(mut self, trim: bool) -> Self {
+        self.trim = trim;
+        self
+    }
+
+    /// Skip files that fail to generate when generating a directory instead of aborting, the
+    /// errors are collected in the report.
+    #[must_use]
+    pub fn with_continue_on_error(mut self, continue_on_error: bool) -> Self {
+        self.continue_on_error = continue_on_error;
+        self
+    }
+
+    /// Generate the files of the subdirectories of a directory too, depth-first with the entries
+    /// of every directory sorted by name.
+    #[must_use]
+    pub fn with_recursive(mut self, recursive: bool) -> Self {
+        self.recursive = recursive;
+        self
+    }
+
+    /// Fail when a file of a directory is no image instead of skipping it with a warning
+    #[must_use]
+    pub fn with_strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Require all images of a directory to have the size of the first one, as needed for fixed
+    /// cell fonts. The sizes are read from the image headers before any glyph is generated.
+    #[must_use]
+    pub fn with_uniform(mut self, uniform: bool) -> Self {
+        self.uniform = uniform;
+        self
+    }
+
+    /// Decode and pack the glyphs of a directory concurrently on the rayon thread pool. The
+    /// glyphs are still written in the order of their file names, so the output is identical to
+    /// the sequential generation.
+    #[cfg(feature = "rayon")]
+    #[must_use]
+    pub fn with_parallel(mut self, parallel: bool) -> Self {
+        self.parallel = parallel;
+        self
+    }
+
+    /// Multiply the advance widths of the metrics table by this factor, for example for fonts
+    /// drawn at a different scale than they are rendered at.
+    #[must_use]
+    pub fn with_advance_scale(mut self, scale: f64) -> Self {
+        self.advance_scale = Some(scale);
+        self
+    }
+
+    /// Store the advance widths of the metrics table as unsigned 8.8 fixed point numbers to keep
+    /// fractional advances of scaled glyphs.
+    #[must_use]
+    pub fn with_advance_fixed_point(mut self, advance_fixed_point: bool) -> Self {
+        self.advance_fixed_point = advance_fixed_point;
+        self
+    }
+
+    /// Follow every glyph by a mask plane of the same size, the glyph dilated by one pixel. This
+    /// allows drawing an outline or background behind the glyph.
+    #[must_use]
+    pub fn with_mask_plane(mut self, mask_plane: bool) -> Self {
+        self.mask_plane = mask_plane;
+        self
+    }
+
+    /// Append a sentinel after the last glyph of a directory, so firmware can detect the end of
+    /// the font.
+    #[must_use]
+    pub fn with_end_sentinel(mut self, end_sentinel: EndSentinel) -> Self {
+        self.end_sentinel = Some(end_sentinel);
+        self
+    }
+
+    /// Reject glyph files that are packed to more than `max` bytes, including the mask plane.
+    #[must_use]
+    pub fn with_max_glyph_bytes(mut self, max: usize) -> Self {
+        self.max_glyph_bytes = Some(max);
+        self
+    }
+
+    /// Set the cutoff deciding wether a pixel is on, 128 by default. Pixels have to be at least
+    /// as opaque as the threshold and, in images without alpha, have a color component below it.
+    /// Anti-aliased glyphs get thinner with a lower and bolder with a higher threshold.
+    #[must_use]
+    pub fn with_threshold(mut self, threshold: u8) -> Self {
+        self.threshold = Some(threshold);
+        self
+    }
+
+    /// Reject images with more scanned pixels than this, including the alignment padding of the
+    /// font mode, before they are decoded. The size is read from the png headers, so a crafted
+    /// header can't make the generator allocate huge buffers. 2^24 pixels by default.
+    #[must_use]
+    pub fn with_max_image_pixels(mut self, max_image_pixels: usize) -> Self {
+        self.max_image_pixels = Some(max_image_pixels);
+        self
+    }
+
+    /// Map the luminance of every pixel through the table, indexed by the luminance of the
+    /// pixel, before it is thresholded. This matches the gamma curve of calibrated displays.
+    /// In images with alpha the table decides which of the opaque pixels are on.
+    #[must_use]
+    pub fn with_luminance_lut(mut self, lut: [u8; 256]) -> Self {
+        self.luminance_lut = Some(lut);
+        self
+    }
+
+    /// Crop every glyph to the bounding box of its on pixels before packing, removing blank
+    /// margins on all four sides. Blank glyphs are cropped to zero pixels. The region of the
+    /// image a glyph was cropped to is returned by [`Self::cropped_glyph`] and stored in its
+    /// [`crate::GlyphRecord`].
+    ///
+    /// Cropping replaces trimming, [`Self::with_trim`] has no effect on cropped glyphs.
+    #[must_use]
+    pub fn with_auto_crop(mut self, auto_crop: bool) -> Self {
+        self.auto_crop = auto_crop;
+        self
+    }
+
+    /// Choose which pixels count towards the bounding box used by [`Self::with_trim`],
+    /// independently of which pixels are on in the packed glyph. This only applies to png data,
+    /// glyphs from raw pixels are always trimmed to their on pixels.
+    #[must_use]
+    pub fn with_bbox_rule(mut self, bbox_rule: BboxRule) -> Self {
+        self.bbox_rule = bbox_rule;
+        self
+    }
+
+    /// Replace the advance width of the glyphs with the given codepoints in the metrics, for
+    /// example to give the digits of a proportional font one width for tabular figures. All
+    /// other glyphs keep the width of their content.
+    #[must_use]
+    pub fn with_force_width(mut self, force_width: HashMap<u32, usize>) -> Self {
+        self.force_width = force_width;
+        self
+    }
+
+    /// Start every glyph of a directory at a multiple of `page_size` bytes, for example the
+    /// erase pages of NOR flash, so single glyphs can be updated without touching their
+    /// neighbours. The gaps are filled with `0xFF`, the value of erased flash.
+    #[must_use]
+    pub fn with_glyph_page_align(mut self, page_size: usize) -> Self {
+        self.glyph_page_align = Some(page_size).filter(|&size| size != 0);
+        self
+    }
+
+    /// Try to run length encode every glyph of a directory and store whichever of the encoded
+    /// and the plain bytes is smaller. The chosen [`Compression`](crate::Compression) is part of
+    /// the glyph record and can be stored in the glyph index.
+    #[must_use]
+    pub fn with_compress(mut self, compress: bool) -> Self {
+        self.compress = compress;
+        self
+    }
+
+    /// Write on pixels as 0 and off pixels as 1, for displays where a set bit turns a pixel off.
+    /// The padding of partial bytes, bit runs and the font mode follows the inverted polarity.
+    #[must_use]
+    pub fn with_invert(mut self, invert: bool) -> Self {
+        self.invert = invert;
+        self
+    }
+
+    /// Set when the output of a directory is flushed, by default it is flushed once at the end
+    #[must_use]
+    pub fn with_flush_policy(mut self, flush_policy: FlushPolicy) -> Self {
+        self.flush_policy = flush_policy;
+        self
+    }
+
+    /// Distribute the midtones of every image into a pattern of on and off pixels, an ordered
+    /// dither replaces the threshold while the Floyd–Steinberg dither diffuses the error of the
+    /// threshold over the image.
+    #[must_use]
+    pub fn with_dither(mut self, dither: Dither) -> Self {
+        self.dither = dither;
+        self
+    }
+
+    /// Decide wether opaque pixels are on by their luminance weighted as in the luma mode instead
+    /// of their darkest color component, so colored glyphs are thresholded like their brightness.
+    /// This also applies to the opaque pixels of images with alpha.
+    #[must_use]
+    pub fn with_luma_mode(mut self, luma_mode: LumaMode) -> Self {
+        self.luma_mode = luma_mode;
+        self
+    }
+
+    /// Transform every png image as described by its EXIF orientation metadata before it is
+    /// packed, so glyphs stored sideways are generated upright.
+    #[must_use]
+    pub fn with_normalize_orientation(mut self, normalize_orientation: bool) -> Self {
+        self.normalize_orientation = normalize_orientation;
+        self
+    }
+
+    /// Drop the fully blank rows of every glyph when generating in [`FontMode::Row`], for
+    /// displays where vertical compression is acceptable. The number of dropped rows is part of
+    /// the glyph record and the metrics table so they can be restored.
+    #[must_use]
+    pub fn with_collapse_blank_rows(mut self, collapse_blank_rows: bool) -> Self {
+        self.collapse_blank_rows = collapse_blank_rows;
+        self
+    }
+
+    /// Decode the png data into a glyph with all options applied.
+    ///
+    /// # Errors
+    /// An error is returned when the given image data can not be decoded as png or the image
+    /// has more pixels than allowed.
+    pub fn glyph(&self, data: &[u8]) -> crate::Result<Glyph> {
+        self.cropped_glyph(data).map(|(glyph, _)| glyph)
+    }
+
+    /// Decode the png data into a glyph with all options applied, together with the region of
+    /// the image it was cropped to. The region is only returned if the generator crops glyphs,
+    /// see [`Self::with_auto_crop`].
+    ///
+    /// # Errors
+    /// An error is returned when the given image data can not be decoded as png or the image
+    /// has more pixels than allowed.
+    pub fn cropped_glyph(&self, data: &[u8]) -> crate::Result<(Glyph, Option<BoundingBox>)> {
+        let info = imagedecode::inspect_png(data, self.font_mode)?;
+        let pixels = imagedecode::checked_char_size(self.font_mode, info.width, info.height)?;
+        if pixels > self.max_image_pixels() {
+            return Err(crate::GenerationError::ImageTooLarge {
+                width: info.width,
+                height: info.height,
+            });
+        }
+        let orientation = if self.normalize_orientation {
+            Orientation::of_png(data)?
+        } else {
+            Orientation::Normal
+        };
+        let glyph = match &self.on_color {
+            Some(on_color) => Glyph::decode_classified(data, on_color)?,
+            None => Glyph::decode_with(
+                data,
+                self.threshold(),
+                self.luminance_lut,
+                self.dither,
+                self.luma_mode,
+            )?,
+        };
+        let mut glyph = glyph.oriented(orientation);
+        let crop = self.auto_crop.then(|| glyph.bbox().unwrap_or_default());
+        if let Some(crop) = crop {
+            glyph = glyph.crop(crop.x, crop.y, crop.width, crop.height);
+        }
+        let bbox = match self.bbox_rule {
+            _ if !self.trim || crop.is_some() => None,
+            BboxRule::Packing => glyph.bbox(),
+            rule => Glyph::decode_content(data, rule, self.luminance_lut, self.luma_mode)?
+                .oriented(orientation)
+                .bbox(),
+        };
+        Ok((self.apply(glyph, bbox), crop))
+    }
+
+    /// Convert a buffer of RGBA pixels into a glyph with all options applied.
+    ///
+    /// # Errors
+    /// An error is returned when the size of the buffer doesn't match the dimensions.
+    pub fn glyph_from_pixels(
+        &self,
+        rgba: &[u8],
+        width: usize,
+        height: usize,
+    ) -> crate::Result<Glyph> {
+        let glyph = Glyph::from_rgba(rgba, width, height)?;
+        let bbox = glyph.bbox();
+        Ok(self.apply(glyph, bbox))
+    }
+
+    /// Apply the glyph level options to a decoded glyph, `bbox` is the content trimmed to
+    fn apply(&self, mut glyph: Glyph, bbox: Option<BoundingBox>) -> Glyph {
+        if self.trim {
+            glyph = glyph.trimmed_to(bbox);
+        }
+        match self.blank_cell {
+            Some((width, height)) if glyph.is_blank() => Glyph::blank(width, height),
+            _ => glyph,
+        }
+    }
+
+    /// Generate a single glyph from the png data and write it to `out`.
+    ///
+    /// Returns the number of bytes written.
+    ///
+    /// # Errors
+    /// An error is returned when the given image data can not be decoded as png or writing to the
+    /// `out` writer fails.
+    pub fn generate(&self, data: &[u8], out: &mut impl Write) -> crate::Result<usize> {
+        let glyph = self.glyph(data)?;
+        self.write_glyph(&glyph, out)
+    }
+
+    /// Generate a single glyph from png data read from `reader` and write it to `out`.
+    ///
+    /// The reader is read to its end into a single buffer, which is dropped once the image is
+    /// decoded and before the glyph is packed.
+    ///
+    /// Returns the number of bytes written.
+    ///
+    /// # Errors
+    /// An error is returned when reading fails, the data can not be decoded as png or writing to
+    /// the `out` writer fails.
+    pub fn generate_from_reader(
+        &self,
+        mut reader: impl Read,
+        out: &mut impl Write,
+    ) -> crate::Result<usize> {
+        let glyph = {
+            let mut data = Vec::new();
+            reader
+                .read_to_end(&mut data)
+                .map_err(crate::GenerationError::IoError)?;
+            self.glyph(&data)?
+        };
+        self.write_glyph(&glyph, out)
+    }
+
+    /// Decode an atlas holding glyphs in a grid of cells once and write every cell, left to
+    /// right, top to bottom, as its own glyph starting on a fresh byte.
+    ///
+    /// Returns the number of bytes written.
+    ///
+    /// # Errors
+    /// An error is returned when the image data can not be decoded as png, its size is not a
+    /// multiple of the cell size or writing to the `out` writer fails.
+    pub fn generate_atlas(
+        &self,
+        data: &[u8],
+        cell_width: usize,
+        cell_height: usize,
+        out: &mut impl Write,
+    ) -> crate::Result<usize> {
+        let atlas = self.glyph(data)?;
+        let (width, height) = (atlas.width(), atlas.height());
+        let fits = |len: usize, cell: usize| cell != 0 && len.is_multiple_of(cell);
+        if !fits(width, cell_width) || !fits(height, cell_height) {
+            return Err(crate::GenerationError::AtlasDimensionMismatch {
+                width,
+                height,
+                cell_width,
+                cell_height,
+            });
+        }
+
+        let mut written = 0usize;
+        for glyph in SheetLayout::new(cell_width, cell_height).split(&atlas) {
+            written = written.saturating_add(self.write_glyph(&glyph, out)?);
+        }
+        Ok(written)
+    }
+
+    /// Pack an already decoded glyph and write it to `out`, followed by its mask plane if
+    /// enabled.
+    ///
+    /// Returns the number of bytes written.
+    ///
+    /// # Errors
+    /// An error is returned when writing to the `out` writer fails.
+    pub fn write_glyph(&self, glyph: &Glyph, out: &mut impl Write) -> crate::Result<usize> {
+        let collapsed;
+        let glyph = if self.collapses_blank_rows() {
+            collapsed = glyph.without_blank_rows();
+            &collapsed
+        } else {
+            glyph
+        };
+        let written = self.write_plane(glyph, out)?;
+        if self.mask_plane {
+            let mask = self.write_plane(&glyph.dilated(), out)?;
+            return Ok(written.saturating_add(mask));
+        }
+        Ok(written)
+    }
+
+    /// Pack a single plane of a glyph and write it to `out`
+    fn write_plane(&self, glyph: &Glyph, out: &mut impl Write) -> crate::Result<usize> {
+        let bit_run = self.bit_run.or_else(|| {
+            self.row_bit_run
+                .map(|endianness| (glyph.width(), endianness))
+        });
+        let mut packer = BitPacker::new(out, self.bit_flow)
+            .with_bit_run(bit_run)
+            .with_invert(self.invert);
+        let scanned = glyph.scan(self.font_mode, self.padding_mode);
+        let bottom_first = self.column_byte_order == ColumnByteOrder::BottomFirst;
+        let pixels: Box<dyn Iterator<Item = bool> + '_> =
+            if let Some(align) = self.font_mode.column_align().filter(|_| bottom_first) {
+                let scanned: Vec<bool> = scanned.collect();
+                let reordered: Vec<bool> = scanned
+                    .chunks(glyph.width().saturating_mul(usize::from(align)).max(1))
+                    .rev()
+                    .flatten()
+                    .copied()
+                    .collect();
+                Box::new(reordered.into_iter())
+            } else {
+                Box::new(scanned)
+            };
+        for (n, pix) in pixels.enumerate() {
+            packer.push(pix)?;
+            if self
+                .glyph_stride
+                .is_some_and(|stride| n.saturating_add(1).is_multiple_of(stride))
+            {
+                packer.align()?;
+            }
+        }
+        packer.finish()
+    }
+
+    /// Wether fully blank rows are dropped, only done in row mode
+    pub fn collapses_blank_rows(&self) -> bool {
+        self.collapse_blank_rows && self.font_mode == FontMode::Row
+    }
+
+    /// The number of rows of the glyph that are dropped when it is written
+    pub fn dropped_rows(&self, glyph: &Glyph) -> usize {
+        if self.collapses_blank_rows() {
+            glyph.blank_rows()
+        } else {
+            0
+        }
+    }
+
+    /// The mode in which the font is generated
+    pub fn font_mode(&self) -> FontMode {
+        self.font_mode
+    }
+
+    /// The flow of the bits inside each byte
+    pub fn bit_flow(&self) -> BitFlow {
+        self.bit_flow
+    }
+
+    /// The order of the blocks of 8 rows in the byte column mode
+    pub fn column_byte_order(&self) -> ColumnByteOrder {
+        self.column_byte_order
+    }
+
+    /// The color of on pixels, if pixels are classified by color
+    pub fn on_color(&self) -> Option<OnColor> {
+        self.on_color
+    }
+
+    /// Number of scanned pixels after which a fresh byte is started, if any
+    pub fn glyph_stride(&self) -> Option<usize> {
+        self.glyph_stride
+    }
+
+    /// Wether the glyphs of a font are reordered by similarity
+    pub fn optimize_order(&self) -> bool {
+        self.optimize_order
+    }
+
+    /// The minimum percentage of on pixels a glyph file must have, `None` if glyphs aren't
+    /// checked. Blank glyphs are never checked if blank cell normalization is enabled.
+    pub fn min_coverage(&self, glyph: &Glyph) -> Option<f64> {
+        match self.blank_cell {
+            Some(_) if glyph.is_blank() => None,
+            _ => self.min_coverage,
+        }
+    }
+
+    /// Wether a mirrored copy of the font is appended
+    pub fn mirror(&self) -> bool {
+        self.with_mirror
+    }
+
+    /// Wether files that fail to generate are skipped when generating a directory
+    pub fn continue_on_error(&self) -> bool {
+        self.continue_on_error
+    }
+
+    /// Wether the subdirectories of a directory are generated too
+    pub fn recursive(&self) -> bool {
+        self.recursive
+    }
+
+    /// Wether files of a directory that are no images fail the generation
+    pub fn strict(&self) -> bool {
+        self.strict
+    }
+
+    /// Wether all images of a directory must have the same size
+    pub fn uniform(&self) -> bool {
+        self.uniform
+    }
+
+    /// Wether the glyphs of a directory are decoded concurrently
+    #[cfg(feature = "rayon")]
+    pub fn parallel(&self) -> bool {
+        self.parallel
+    }
+
+    /// The factor the advance widths of the metrics are multiplied with
+    pub fn advance_scale(&self) -> f64 {
+        self.advance_scale.unwrap_or(1.0)
+    }
+
+    /// When the output of a directory is flushed
+    pub fn flush_policy(&self) -> FlushPolicy {
+        self.flush_policy
+    }
+
+    /// Wether every glyph of a directory is prefixed by its width, height and advance
+    pub fn glyph_header(&self) -> bool {
+        self.glyph_header
+    }
+
+    /// Wether the advance widths of the metrics are stored as 8.8 fixed point numbers
+    pub fn advance_fixed_point(&self) -> bool {
+        self.advance_fixed_point
+    }
+
+    /// The sentinel appended after the last glyph of a directory
+    pub fn end_sentinel(&self) -> Option<EndSentinel> {
+        self.end_sentinel
+    }
+
+    /// The maximum number of bytes of a single packed glyph
+    pub fn max_glyph_bytes(&self) -> Option<usize> {
+        self.max_glyph_bytes
+    }
+
+    /// Wether every glyph is stored with the compression producing the fewest bytes
+    pub fn compress(&self) -> bool {
+        self.compress
+    }
+
+    /// The cutoff deciding wether a pixel is on
+    pub fn threshold(&self) -> u8 {
+        self.threshold.unwrap_or(imagedecode::DEFAULT_THRESHOLD)
+    }
+
+    /// The maximum number of scanned pixels of an image
+    pub fn max_image_pixels(&self) -> usize {
+        self.max_image_pixels
+            .unwrap_or(imagedecode::DEFAULT_MAX_IMAGE_PIXELS)
+    }
+
+    /// The size of the flash pages every glyph of a directory starts at, if aligned
+    pub fn glyph_page_align(&self) -> Option<usize> {
+        self.glyph_page_align
+    }
+
+    /// The advance width forced for the glyph with the codepoint, if any
+    pub fn forced_width(&self, codepoint: u32) -> Option<usize> {
+        self.force_width.get(&codepoint).copied()
+    }
+
+    /// The codepoints of the glyphs to include when generating a directory
+    pub fn include_codepoints(&self) -> Option<&CodepointRanges> {
+        self.include_codepoints.as_ref()
+    }
+
+    /// Wether glyphs of a directory without a codepoint in their file name are skipped
+    pub fn codepoint_from_filename(&self) -> bool {
+        self.codepoint_from_filename
+    }
+}
+
+/// A sequence of a repeated byte marking the end of a font, parsed from `BYTE` or `BYTE:LEN`
+/// like `0xff:8`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EndSentinel {
+    /// The byte the sentinel consists of
+    pub byte: u8,
+    /// The number of bytes of the sentinel, the size of the last glyph if not set
+    pub len: Option<usize>,
+}
+
+impl EndSentinel {
+    /// The sentinel bytes for a font whose last glyph has `last_glyph_len` bytes, at least one
+    /// byte long if the length is taken from the glyph.
+    pub fn bytes(&self, last_glyph_len: usize) -> Vec<u8> {
+        vec![self.byte; self.len.unwrap_or(last_glyph_len.max(1))]
+    }
+}
+
+impl FromStr for EndSentinel {
+    type Err = EndSentinelParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || EndSentinelParseError(s.to_owned());
+        let (byte, len) = match s.split_once(':') {
+            Some((byte, len)) => (byte, Some(len.trim().parse().map_err(|_| err())?)),
+            None => (s, None),
+        };
+        let byte = byte.trim();
+        let byte = match byte.strip_prefix("0x") {
+            Some(hex) => u8::from_str_radix(hex, 16),
+            None => byte.parse(),
+        }
+        .map_err(|_| err())?;
+
+        Ok(Self { byte, len })
+    }
+}
+
+/// An end sentinel was tried to be parsed that is not a valid byte and length
+#[derive(Clone, Debug, thiserror::Error)]
+#[error("Invalid end sentinel: {0}")]
+pub struct EndSentinelParseError(String);
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::testutil;
+
+    /// A sideways image tagged with its orientation generates the upright glyph
+    #[test]
+    fn normalize_orientation() {
+        let upright = testutil::picture(&["###.....", "#.......", "#.......", "........"]);
+        // The upright glyph rotated by 90 degrees counter clockwise, it has to be rotated
+        // clockwise to be displayed
+        let sideways = [
+            "....", "....", "....", "....", "....", "#...", "#...", "###.",
+        ];
+        let tagged = testutil::with_exif_orientation(&testutil::picture(&sideways), 6);
+
+        let generator = FontGenerator::new().with_normalize_orientation(true);
+        let mut expected = Vec::new();
+        generator.generate(&upright, &mut expected).unwrap();
+        let mut out = Vec::new();
+        generator.generate(&tagged, &mut out).unwrap();
+        assert_eq!(out, expected);
+
+        let mut ignored = Vec::new();
+        FontGenerator::new()
+            .generate(&tagged, &mut ignored)
+            .unwrap();
+        assert_ne!(ignored, expected);
+    }
+
+    /// The ST7920 preset pads every row to full bytes, high byte first
+    #[test]
+    fn st7920_layout() {
+        let png = testutil::picture(&["############", "#..........#", "..##....##.."]);
+        let mut out = Vec::new();
+        FontGenerator::new()
+            .with_mode(FontMode::ByteColumn)
+            .with_st7920()
+            .generate(&png, &mut out)
+            .unwrap();
+        assert_eq!(out, [0xFF, 0xF0, 0x80, 0x10, 0x30, 0xC0]);
+    }
+
+    /// Auto crop cuts a 16x16 canvas down to its 4x4 content and reports the offsets, blank
+    /// glyphs are cropped to nothing
+    #[test]
+    fn auto_crop() {
+        let rows: Vec<String> = (0..16)
+            .map(|y| {
+                (0..16)
+                    .map(|x| {
+                        if (2..6).contains(&x) && (3..7).contains(&y) {
+                            '#'
+                        } else {
+                            '.'
+                        }
+                    })
+                    .collect()
+            })
+            .collect();
+        let rows: Vec<&str> = rows.iter().map(String::as_str).collect();
+        let generator = FontGenerator::new().with_auto_crop(true);
+
+        let (glyph, crop) = generator.cropped_glyph(&testutil::picture(&rows)).unwrap();
+        assert_eq!((glyph.width(), glyph.height()), (4, 4));
+        assert_eq!(glyph.on_count(), 16);
+        assert_eq!(
+            crop,
+            Some(BoundingBox {
+                x: 2,
+                y: 3,
+                width: 4,
+                height: 4
+            })
+        );
+
+        let blank = testutil::picture(&["........"; 4]);
+        let (glyph, crop) = generator.cropped_glyph(&blank).unwrap();
+        assert_eq!((glyph.width(), glyph.height()), (0, 0));
+        assert_eq!(crop, Some(BoundingBox::default()));
+        let mut out = Vec::new();
+        assert_eq!(generator.write_glyph(&glyph, &mut out).unwrap(), 0);
+        assert!(out.is_empty());
+    }
+
+    /// Trimming on alpha keeps the transparent margin that packing on luminance ignores
+    #[test]
+    fn bbox_rule_alpha() {
+        let png = testutil::picture(&["..#.....", "...#...."]);
+        let generate = |rule| {
+            let generator = FontGenerator::new().with_trim(true).with_bbox_rule(rule);
+            let glyph = generator.glyph(&png).unwrap();
+            (glyph.width(), glyph.bbox().unwrap().x)
+        };
+
+        assert_eq!(generate(BboxRule::Packing), (2, 0));
+        assert_eq!(generate(BboxRule::Luminance), (2, 0));
+        // Every pixel of a grayscale image is opaque, so the whole image is content
+        assert_eq!(generate(BboxRule::Alpha), (8, 2));
+
+        let transparent_dark = testutil::rgba(
+            3,
+            1,
+            &[[0, 0, 0, 0], [0, 0, 0, 0xFF], [0xFF, 0xFF, 0xFF, 0]],
+        );
+        let generator = FontGenerator::new()
+            .with_trim(true)
+            .with_bbox_rule(BboxRule::Luminance);
+        let glyph = generator.glyph(&transparent_dark).unwrap();
+        assert_eq!((glyph.width(), glyph.bbox().unwrap().x), (2, 1));
+    }
+
+    /// An inverting luminance table flips every on and off decision
+    #[test]
+    fn inverting_luminance_lut() {
+        let png = testutil::gray(8, 1, &[0, 100, 200, 255, 0, 100, 200, 255]);
+        let mut lut = [0u8; 256];
+        for (entry, value) in lut.iter_mut().zip((0..=u8::MAX).rev()) {
+            *entry = value;
+        }
+
+        let mut out = Vec::new();
+        FontGenerator::new().generate(&png, &mut out).unwrap();
+        assert_eq!(out, [0xCC]);
+
+        let mut out = Vec::new();
+        FontGenerator::new()
+            .with_luminance_lut(lut)
+            .generate(&png, &mut out)
+            .unwrap();
+        assert_eq!(out, [0x33]);
+    }
+
+    /// A green that is darker than half in Rec. 601 but brighter in Rec. 709 is only on in the
+    /// first, any channel counts every color with a dark component
+    #[test]
+    fn luma_modes() {
+        let png = testutil::rgb(4, 1, &[[60, 160, 60], [0, 255, 0], [0, 0, 0], [255; 3]]);
+        let generate = |luma_mode| {
+            let mut out = Vec::new();
+            FontGenerator::new()
+                .with_luma_mode(luma_mode)
+                .generate(&png, &mut out)
+                .unwrap();
+            out
+        };
+
+        assert_eq!(generate(LumaMode::AnyChannel), [0xE0]);
+        assert_eq!(generate(LumaMode::Rec601), [0xA0]);
+        assert_eq!(generate(LumaMode::Rec709), [0x20]);
+    }
+
+    /// The luminance table decides which opaque pixels of an image with alpha are on
+    #[test]
+    fn alpha_luminance_lut() {
+        let pixels = [
+            [0, 0, 0, 255],
+            [100, 100, 100, 255],
+            [200, 200, 200, 255],
+            [255; 4],
+        ];
+        let transparent = pixels.map(|[r, g, b, _]| [r, g, b, 0]);
+        let png = testutil::rgba(8, 1, &[pixels, transparent].concat());
+        let mut lut = [0u8; 256];
+        for (entry, value) in lut.iter_mut().zip((0..=u8::MAX).rev()) {
+            *entry = value;
+        }
+
+        let mut out = Vec::new();
+        FontGenerator::new().generate(&png, &mut out).unwrap();
+        assert_eq!(out, [0xF0]);
+
+        let mut out = Vec::new();
+        FontGenerator::new()
+            .with_luminance_lut(lut)
+            .generate(&png, &mut out)
+            .unwrap();
+        assert_eq!(out, [0x30]);
+    }
+
+    /// A weighted luma mode thresholds the opaque pixels of an image with alpha like those of an
+    /// image without alpha
+    #[test]
+    fn alpha_luma_modes() {
+        let png = testutil::rgba(
+            4,
+            1,
+            &[
+                [60, 160, 60, 255],
+                [0, 255, 0, 255],
+                [0, 0, 0, 255],
+                [255; 4],
+            ],
+        );
+        let generate = |luma_mode| {
+            let mut out = Vec::new();
+            FontGenerator::new()
+                .with_luma_mode(luma_mode)
+                .generate(&png, &mut out)
+                .unwrap();
+            out
+        };
+
+        assert_eq!(generate(LumaMode::AnyChannel), [0xF0]);
+        assert_eq!(generate(LumaMode::Rec601), [0xA0]);
+        assert_eq!(generate(LumaMode::Rec709), [0x20]);
+    }
+
+    /// The inverted output of a byte aligned glyph is the bitwise complement
+    #[test]
+    fn invert() {
+        let png = testutil::picture(&["#..##..#", ".######.", "........"]);
+        let mut normal = Vec::new();
+        FontGenerator::new().generate(&png, &mut normal).unwrap();
+        let mut inverted = Vec::new();
+        FontGenerator::new()
+            .with_invert(true)
+            .generate(&png, &mut inverted)
+            .unwrap();
+
+        assert_eq!(normal, [0x99, 0x7E, 0x00]);
+        let complement: Vec<u8> = normal.iter().map(|b| !b).collect();
+        assert_eq!(inverted, complement);
+    }
+
+    /// The two bytes of every column of a 16 pixel tall byte column glyph swap their blocks
+    #[test]
+    fn column_byte_order() {
+        let mut rows = [".."; 16];
+        rows[0] = "##";
+        rows[15] = "#.";
+        let png = testutil::picture(&rows);
+        let generate = |order| {
+            let mut out = Vec::new();
+            FontGenerator::new()
+                .with_mode(FontMode::ByteColumn)
+                .with_column_byte_order(order)
+                .generate(&png, &mut out)
+                .unwrap();
+            out
+        };
+        assert_eq!(
+            generate(ColumnByteOrder::TopFirst),
+            [0x80, 0x80, 0x01, 0x00]
+        );
+        assert_eq!(
+            generate(ColumnByteOrder::BottomFirst),
+            [0x01, 0x00, 0x80, 0x80]
+        );
+    }
+
+    /// Columns aligned to 16 and 32 pixels hold two and four bytes of a column before the next
+    /// column starts, the height is padded to the alignment
+    #[test]
+    fn aligned_column() {
+        let mut rows = [".."; 20];
+        rows[0] = "#.";
+        rows[15] = ".#";
+        rows[16] = "#.";
+        rows[19] = ".#";
+        let png = testutil::picture(&rows);
+        let generate = |align| {
+            let mut out = Vec::new();
+            FontGenerator::new()
+                .with_mode(FontMode::aligned_column(align).unwrap())
+                .generate(&png, &mut out)
+                .unwrap();
+            out
+        };
+        assert_eq!(generate(8).len(), 6);
+        assert_eq!(
+            generate(16),
+            [0x80, 0x00, 0x00, 0x01, 0x80, 0x00, 0x10, 0x00]
+        );
+        assert_eq!(
+            generate(32),
+            [0x80, 0x00, 0x80, 0x00, 0x00, 0x01, 0x10, 0x00]
+        );
+
+        assert!(matches!(
+            FontMode::aligned_column(12),
+            Err(crate::GenerationError::UnsupportedColumnAlign(12))
+        ));
+        let mode: FontMode = "column-16".parse().unwrap();
+        assert_eq!(mode, FontMode::aligned_column(16).unwrap());
+        assert_eq!(mode.to_string(), "column-16");
+        let header = crate::FontHeader::new(mode, BitFlow::Mtl).to_bytes();
+        let (parsed, _) = crate::FontHeader::parse(&header).unwrap().unwrap();
+        assert_eq!(parsed.font_mode, mode);
+    }
+
+    /// A stride of 5 pixels packs a row of 10 pixels as two byte aligned glyphs
+    #[test]
+    fn glyph_stride() {
+        let png = testutil::picture(&["##..#.#.##"]);
+        let generate = |stride| {
+            let mut out = Vec::new();
+            FontGenerator::new()
+                .with_glyph_stride(stride)
+                .generate(&png, &mut out)
+                .unwrap();
+            out
+        };
+        assert_eq!(generate(None), [0xCA, 0xC0]);
+        assert_eq!(generate(Some(5)), [0xC8, 0x58]);
+        assert_eq!(generate(Some(10)), [0xCA, 0xC0]);
+    }
+
+    /// The padding of a byte column glyph is filled with on pixels and flipped by the inversion
+    #[test]
+    fn padding_one() {
+        let png = testutil::picture(&["#.", ".#", "##"]);
+        let generator = FontGenerator::new()
+            .with_mode(FontMode::ByteColumn)
+            .with_padding_mode(PaddingMode::One);
+
+        let mut out = Vec::new();
+        generator.generate(&png, &mut out).unwrap();
+        assert_eq!(out, [0xBF, 0x7F]);
+
+        let mut inverted = Vec::new();
+        generator
+            .with_invert(true)
+            .generate(&png, &mut inverted)
+            .unwrap();
+        assert_eq!(inverted, [0x40, 0x80]);
+    }
+
+    /// The options of the builder combine, a column font of a light gray glyph inverted
+    #[test]
+    fn builder_options() {
+        let png = testutil::gray(2, 8, &[[160, 255]; 8].concat());
+        let mut default = Vec::new();
+        FontGenerator::new().generate(&png, &mut default).unwrap();
+        assert_eq!(default, [0x00, 0x00]);
+
+        let mut out = Vec::new();
+        let written = FontGenerator::new()
+            .with_mode(FontMode::Column)
+            .with_bit_flow(BitFlow::Ltm)
+            .with_threshold(200)
+            .with_invert(true)
+            .generate(&png, &mut out)
+            .unwrap();
+        assert_eq!(written, 2);
+        assert_eq!(out, [0x00, 0xFF]);
+    }
+
+    /// A blank glyph of the wrong size is emitted with the size of the cell
+    #[test]
+    fn blank_cell_normalization() {
+        let png = testutil::rgba(7, 9, &[[0, 0, 0, 0]; 7 * 9]);
+        let generator = FontGenerator::new().with_blank_cell(8, 8);
+
+        let mut out = Vec::new();
+        generator.generate(&png, &mut out).unwrap();
+        assert_eq!(out, [0u8; 8]);
+
+        let mut out = Vec::new();
+        FontGenerator::new().generate(&png, &mut out).unwrap();
+        assert_eq!(out.len(), (7 * 9usize).div_ceil(8));
+    }
+
+    /// Every row of a 12 pixel wide glyph is written as little endian 16 bit field
+    #[test]
+    fn bit_run_rows() {
+        let png = testutil::picture(&["#.......##.#", "........#..."]);
+        let generator = FontGenerator::new().with_bit_run(12, BitRunEndianness::Little);
+
+        let mut out = Vec::new();
+        assert_eq!(generator.generate(&png, &mut out).unwrap(), 4);
+        assert_eq!(out, [0xD0, 0x80, 0x80, 0x00]);
+    }
+
+    /// A single on pixel becomes a 3x3 block in the mask plane
+    #[test]
+    fn mask_plane() {
+        let png = testutil::picture(&["........", "........", "..#.....", "........"]);
+        let generator = FontGenerator::new().with_mask_plane(true);
+
+        let mut out = Vec::new();
+        assert_eq!(generator.generate(&png, &mut out).unwrap(), 8);
+        let (glyph, mask) = out.split_at(4);
+        assert_eq!(glyph, [0x00, 0x00, 0x20, 0x00]);
+        assert_eq!(mask, [0x00, 0x70, 0x70, 0x70]);
+    }
+
+    /// Glyphs with content keep their size
+    #[test]
+    fn blank_cell_keeps_content() {
+        let png = testutil::picture(&["#...", "....", "....", "...."]);
+        let generator = FontGenerator::new().with_blank_cell(8, 8);
+
+        let mut out = Vec::new();
+        generator.generate(&png, &mut out).unwrap();
+        assert_eq!(out, [0b1000_0000, 0]);
+    }
+}
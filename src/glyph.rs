@@ -0,0 +1,539 @@
+//! Binarized glyph bitmaps, used as intermediate representation between decoding and packing.
+
+use std::io::{self, Write};
+
+use crate::{
+    dither,
+    imagedecode::{self, MonochromaticColorIter, PaddedPixel, RgbaColorIter},
+    AlphaMode, BboxRule, Dither, FontMode, GenerationError, LumaMode, Orientation, PaddingMode,
+    PixelClassifier,
+};
+
+/// A monochromatic glyph, the pixels are stored left to right, top to bottom.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Glyph {
+    /// The width of the glyph in pixels
+    width: usize,
+    /// The height of the glyph in pixels
+    height: usize,
+    /// Wether each pixel is on
+    pixels: Vec<bool>,
+}
+
+impl Glyph {
+    /// Decode a glyph from png data.
+    ///
+    /// # Errors
+    /// An error is returned when the given image data can not be decoded as png.
+    pub fn decode(data: &[u8]) -> crate::Result<Self> {
+        Ok(Self::from_pixels(MonochromaticColorIter::new(
+            data,
+            FontMode::Row,
+        )?))
+    }
+
+    /// Decode png data into a glyph with `threshold` as cutoff for on pixels, mapping the
+    /// luminance of every pixel through the table before it is thresholded. The threshold of
+    /// every pixel is varied by the dither mode, the luma mode decides how colors are compared
+    /// against it.
+    ///
+    /// # Errors
+    /// An error is returned when the given image data can not be decoded as png.
+    pub fn decode_with(
+        data: &[u8],
+        threshold: u8,
+        luminance_lut: Option<[u8; 256]>,
+        dither: Dither,
+        luma_mode: LumaMode,
+    ) -> crate::Result<Self> {
+        if dither == Dither::FloydSteinberg {
+            let mut iter = RgbaColorIter::new(data, FontMode::Row)?
+                .with_luminance_lut(luminance_lut)
+                .with_luma_mode(luma_mode);
+            let width = iter.width();
+            let height = iter.height();
+            let rgba: Vec<_> = iter.by_ref().collect();
+            let levels: Vec<u8> = rgba
+                .into_iter()
+                .map(|p| iter.ink_level(p, AlphaMode::Modulate))
+                .collect();
+            return Ok(Self {
+                width,
+                height,
+                pixels: dither::floyd_steinberg(&levels, width, threshold),
+            });
+        }
+
+        let iter = MonochromaticColorIter::with_threshold(data, FontMode::Row, threshold)?
+            .with_luminance_lut(luminance_lut)
+            .with_luma_mode(luma_mode)
+            .with_dither(dither);
+        Ok(Self::from_pixels(iter))
+    }
+
+    /// Decode png data as glyph with the classifier deciding which pixels are on
+    ///
+    /// # Errors
+    /// An error is returned when the given image data can not be decoded as png.
+    pub fn decode_classified(
+        data: &[u8],
+        classifier: &impl PixelClassifier,
+    ) -> crate::Result<Self> {
+        let iter = RgbaColorIter::new(data, FontMode::Row)?;
+        let width = iter.width();
+        let height = iter.height();
+        let has_alpha = iter.has_alpha();
+        let pixels = iter.map(|px| classifier.is_on(px, has_alpha)).collect();
+
+        Ok(Self {
+            width,
+            height,
+            pixels,
+        })
+    }
+
+    /// Collect the decoded pixels of an image scanned row by row
+    fn from_pixels(iter: MonochromaticColorIter) -> Self {
+        let width = iter.width();
+        let height = iter.height();
+        let pixels = iter.collect();
+
+        Self {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    /// Decode the pixels of png data that count towards the bounding box under the rule as glyph,
+    /// the luminance is weighted as in the luma mode and mapped through the table first.
+    ///
+    /// # Errors
+    /// An error is returned when the given image data can not be decoded as png.
+    pub fn decode_content(
+        data: &[u8],
+        rule: BboxRule,
+        luminance_lut: Option<[u8; 256]>,
+        luma_mode: LumaMode,
+    ) -> crate::Result<Self> {
+        let mut iter = RgbaColorIter::new(data, FontMode::Row)?
+            .with_luminance_lut(luminance_lut)
+            .with_luma_mode(luma_mode);
+        let width = iter.width();
+        let height = iter.height();
+        let rgba: Vec<_> = iter.by_ref().collect();
+        let pixels = rgba.into_iter().map(|p| iter.is_content(p, rule)).collect();
+
+        Ok(Self {
+            width,
+            height,
+            pixels,
+        })
+    }
+
+    /// Create a glyph from a buffer of RGBA pixels, stored left to right, top to bottom. Pixels
+    /// are on if they are more than half opaque.
+    ///
+    /// # Errors
+    /// An error is returned when the size of the buffer doesn't match the dimensions.
+    pub fn from_rgba(rgba: &[u8], width: usize, height: usize) -> crate::Result<Self> {
+        let expected = width.saturating_mul(height).saturating_mul(4);
+        if rgba.len() != expected {
+            return Err(GenerationError::PixelBufferSize {
+                expected,
+                found: rgba.len(),
+            });
+        }
+
+        let pixels = rgba
+            .chunks_exact(4)
+            .map(|rgba| rgba[3] > u8::MAX / 2)
+            .collect();
+        Ok(Self {
+            width,
+            height,
+            pixels,
+        })
+    }
+
+    /// Create a glyph where every pixel is off.
+    pub fn blank(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![false; width.saturating_mul(height)],
+        }
+    }
+
+    /// Create a glyph where the pixel at `x`, `y` is on if `is_on(x, y)` returns true.
+    pub fn from_fn(width: usize, height: usize, is_on: impl Fn(usize, usize) -> bool) -> Self {
+        let pixels = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .map(|(x, y)| is_on(x, y))
+            .collect();
+
+        Self {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    /// The width of the glyph in pixels
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// The height of the glyph in pixels
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Wether no pixel of this glyph is on
+    pub fn is_blank(&self) -> bool {
+        !self.pixels.contains(&true)
+    }
+
+    /// The number of pixels that are on
+    pub fn on_count(&self) -> usize {
+        self.pixels.iter().filter(|&&pix| pix).count()
+    }
+
+    /// The percentage of pixels that are on, an empty glyph has a coverage of 0
+    pub fn coverage(&self) -> f64 {
+        match (
+            u32::try_from(self.on_count()),
+            u32::try_from(self.pixels.len()),
+        ) {
+            (Ok(on), Ok(total)) if total != 0 => f64::from(on) * 100.0 / f64::from(total),
+            _ => 0.0,
+        }
+    }
+
+    /// Wether the pixel at the given position is on, pixels outside of the glyph are off
+    pub fn get(&self, x: usize, y: usize) -> bool {
+        x < self.width
+            && self
+                .pixels
+                .get(y.saturating_mul(self.width).saturating_add(x))
+                .copied()
+                .unwrap_or(false)
+    }
+
+    /// Write the glyph as binary portable bitmap (P4) with its own header. Every row is padded
+    /// to full bytes with the first pixel as most significant bit, on pixels are black.
+    ///
+    /// # Errors
+    /// An error is returned when writing to `out` fails.
+    pub fn write_pbm(&self, out: &mut impl Write) -> io::Result<()> {
+        write!(out, "P4\n{} {}\n", self.width, self.height)?;
+        for y in 0..self.height {
+            let row: Vec<u8> = (0..self.width.div_ceil(8))
+                .map(|byte| {
+                    (0..8).fold(0u8, |acc, bit| {
+                        let on = self.get(byte.saturating_mul(8).saturating_add(bit), y);
+                        (acc << 1) | u8::from(on)
+                    })
+                })
+                .collect();
+            out.write_all(&row)?;
+        }
+        Ok(())
+    }
+
+    /// Create a horizontally mirrored copy, the columns are in reverse order
+    pub fn mirrored(&self) -> Self {
+        let pixels = if self.width == 0 {
+            Vec::new()
+        } else {
+            self.pixels
+                .chunks(self.width)
+                .flat_map(|row| row.iter().rev().copied())
+                .collect()
+        };
+
+        Self {
+            width: self.width,
+            height: self.height,
+            pixels,
+        }
+    }
+
+    /// Transform the glyph as described by the orientation so it is upright
+    pub fn oriented(&self, orientation: Orientation) -> Self {
+        let (width, height) = if orientation.swaps_axes() {
+            (self.height, self.width)
+        } else {
+            (self.width, self.height)
+        };
+        let pixels = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .map(|(x, y)| {
+                let (sx, sy) = orientation.source(x, y, self.width, self.height);
+                self.get(sx, sy)
+            })
+            .collect();
+
+        Self {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    /// Remove the blank columns left and right of the content, the height is kept. Blank glyphs
+    /// are returned unchanged.
+    pub fn trimmed(&self) -> Self {
+        self.trimmed_to(self.bbox())
+    }
+
+    /// Remove the columns left and right of the given bounding box, the height is kept. Without
+    /// a bounding box the glyph is returned unchanged.
+    pub fn trimmed_to(&self, bbox: Option<BoundingBox>) -> Self {
+        let Some(bbox) = bbox else {
+            return self.clone();
+        };
+        let pixels = (0..self.height)
+            .flat_map(|y| (bbox.x..bbox.x.wrapping_add(bbox.width)).map(move |x| (x, y)))
+            .map(|(x, y)| self.get(x, y))
+            .collect();
+
+        Self {
+            width: bbox.width,
+            height: self.height,
+            pixels,
+        }
+    }
+
+    /// Cut out the rectangle with the top left corner at `x`, `y`. Pixels outside of this glyph
+    /// are off.
+    pub fn crop(&self, x: usize, y: usize, width: usize, height: usize) -> Self {
+        let pixels = (0..height)
+            .flat_map(|cy| (0..width).map(move |cx| (cx, cy)))
+            .map(|(cx, cy)| self.get(x.saturating_add(cx), y.saturating_add(cy)))
+            .collect();
+
+        Self {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    /// The number of rows without any on pixel
+    pub fn blank_rows(&self) -> usize {
+        self.rows().filter(|row| !row.contains(&true)).count()
+    }
+
+    /// A copy of this glyph with all fully blank rows removed
+    pub fn without_blank_rows(&self) -> Self {
+        let pixels: Vec<bool> = self
+            .rows()
+            .filter(|row| row.contains(&true))
+            .flatten()
+            .copied()
+            .collect();
+
+        Self {
+            width: self.width,
+            height: self.height.saturating_sub(self.blank_rows()),
+            pixels,
+        }
+    }
+
+    /// Iterate over the rows of pixels, a glyph without width has no pixels and so no rows
+    fn rows(&self) -> impl Iterator<Item = &[bool]> {
+        self.pixels.chunks(self.width.max(1))
+    }
+
+    /// Dilate the glyph by one pixel, every pixel next to an on pixel, including diagonally, is
+    /// turned on. The size of the glyph is kept, so content at the border is cut off.
+    pub fn dilated(&self) -> Self {
+        let pixels = (0..self.height)
+            .flat_map(|y| (0..self.width).map(move |x| (x, y)))
+            .map(|(x, y)| {
+                (y.saturating_sub(1)..=y.saturating_add(1)).any(|ny| {
+                    (x.saturating_sub(1)..=x.saturating_add(1)).any(|nx| self.get(nx, ny))
+                })
+            })
+            .collect();
+
+        Self {
+            width: self.width,
+            height: self.height,
+            pixels,
+        }
+    }
+
+    /// Calculate the tight bounding box of all on pixels, `None` if the glyph is blank.
+    pub fn bbox(&self) -> Option<BoundingBox> {
+        let mut min_x = usize::MAX;
+        let mut min_y = usize::MAX;
+        let mut max_x = 0;
+        let mut max_y = 0;
+        for y in 0..self.height {
+            for x in (0..self.width).filter(|&x| self.get(x, y)) {
+                min_x = min_x.min(x);
+                min_y = min_y.min(y);
+                max_x = max_x.max(x);
+                max_y = max_y.max(y);
+            }
+        }
+
+        (min_x != usize::MAX).then(|| BoundingBox {
+            x: min_x,
+            y: min_y,
+            width: max_x.wrapping_sub(min_x).wrapping_add(1),
+            height: max_y.wrapping_sub(min_y).wrapping_add(1),
+        })
+    }
+
+    /// Calculate the metrics of this glyph from its bounding box
+    pub fn metrics(&self) -> GlyphMetrics {
+        self.bbox()
+            .map_or(GlyphMetrics::default(), |bbox| GlyphMetrics {
+                advance: bbox.width,
+                y_offset: bbox.y,
+            })
+    }
+
+    /// Iterate over the pixels in the order given by the font mode, alignment padding is filled
+    /// according to the padding mode.
+    pub fn scan(
+        &self,
+        font_mode: FontMode,
+        padding_mode: PaddingMode,
+    ) -> impl Iterator<Item = bool> + '_ {
+        let char_size =
+            imagedecode::calc_char_size(font_mode, self.width, self.height).unwrap_or(0);
+        (0..char_size).map(move |idx| {
+            let Some(n) = imagedecode::scan_index(font_mode, self.width, self.height, idx) else {
+                return false;
+            };
+            match imagedecode::padded_pixel(padding_mode, self.width, self.height, n) {
+                PaddedPixel::Image(n) => self.pixels.get(n).copied().unwrap_or(false),
+                PaddedPixel::Fill(on) => on,
+            }
+        })
+    }
+}
+
+/// The rectangle enclosing all on pixels of a glyph
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BoundingBox {
+    /// Column of the leftmost on pixel
+    pub x: usize,
+    /// Row of the topmost on pixel
+    pub y: usize,
+    /// Width of the box in pixels
+    pub width: usize,
+    /// Height of the box in pixels
+    pub height: usize,
+}
+
+/// Metrics of a glyph needed by rendering engines, blank glyphs have all metrics set to 0.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct GlyphMetrics {
+    /// The advance width, this is the width of the content of the glyph
+    pub advance: usize,
+    /// The offset of the first row with content from the top of the cell
+    pub y_offset: usize,
+}
+
+impl GlyphMetrics {
+    /// The advance width multiplied by `scale` as unsigned 8.8 fixed point number, saturating at
+    /// `u16::MAX`.
+    pub fn fixed_point_advance(&self, scale: f64) -> u16 {
+        to_u16_saturating(scale_advance(self.advance, scale) * 256.0)
+    }
+
+    /// The advance width multiplied by `scale` rounded to whole pixels, saturating at `u8::MAX`.
+    pub fn scaled_advance(&self, scale: f64) -> u8 {
+        u8::try_from(to_u16_saturating(scale_advance(self.advance, scale))).unwrap_or(u8::MAX)
+    }
+}
+
+/// Multiply an advance width by a scale factor
+fn scale_advance(advance: usize, scale: f64) -> f64 {
+    // Advance widths are far below 2^52, so the conversion is exact
+    #![allow(clippy::as_conversions, clippy::cast_precision_loss)]
+    advance as f64 * scale
+}
+
+/// Round a float to the nearest u16, negative values become 0 and values above the range
+/// `u16::MAX`
+fn to_u16_saturating(val: f64) -> u16 {
+    // Float to int casts saturate and the value is rounded before
+    #![allow(
+        clippy::as_conversions,
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss
+    )]
+    val.round() as u16
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::testutil;
+
+    /// The offset is measured from the top of the cell to the first row with content
+    #[test]
+    fn baseline_offset() {
+        let png = testutil::picture(&[".....", ".....", ".....", ".###.", "..#..", "....."]);
+        let glyph = Glyph::decode(&png).unwrap();
+        assert_eq!(
+            glyph.bbox(),
+            Some(BoundingBox {
+                x: 1,
+                y: 3,
+                width: 3,
+                height: 2
+            })
+        );
+        assert_eq!(
+            glyph.metrics(),
+            GlyphMetrics {
+                advance: 3,
+                y_offset: 3
+            }
+        );
+        assert_eq!(Glyph::blank(4, 4).metrics(), GlyphMetrics::default());
+    }
+
+    /// Scaled advances are stored as 8.8 fixed point numbers
+    #[test]
+    fn fixed_point_advance() {
+        let metrics = GlyphMetrics {
+            advance: 5,
+            y_offset: 0,
+        };
+        assert_eq!(metrics.fixed_point_advance(1.5), 0x0780);
+        assert_eq!(metrics.fixed_point_advance(1.0), 0x0500);
+        assert_eq!(metrics.scaled_advance(1.5), 8);
+        assert_eq!(metrics.fixed_point_advance(1000.0), u16::MAX);
+    }
+
+    /// The padding of byte columns repeats the bottom row
+    #[test]
+    fn padding_repeat_edge() {
+        let png = testutil::picture(&["##", "..", "#."]);
+        let glyph = Glyph::decode(&png).unwrap();
+
+        let scanned: Vec<_> = glyph
+            .scan(FontMode::ByteColumn, PaddingMode::RepeatEdge)
+            .collect();
+        let first_column = [true, false, true, true, true, true, true, true];
+        let second_column = [true, false, false, false, false, false, false, false];
+        assert_eq!(scanned[..8], first_column);
+        assert_eq!(scanned[8..], second_column);
+
+        let zero: Vec<_> = glyph
+            .scan(FontMode::ByteColumn, PaddingMode::Zero)
+            .collect();
+        assert_eq!(
+            zero[..8],
+            [true, false, true, false, false, false, false, false]
+        );
+    }
+}
@@ -0,0 +1,251 @@
+//! An optional header identifying a generated font blob.
+
+use std::{fmt, io::Write, path::Path};
+
+use crate::{
+    generate_dir, BitFlow, ColumnAlign, FontGenerator, FontMode, FontReport, GenerationError,
+};
+
+/// The magic bytes every font header starts with
+pub const FONT_MAGIC: [u8; 4] = *b"EFNT";
+
+/// The version of the font header layout written by this crate
+pub const FONT_HEADER_VERSION: u8 = 1;
+
+/// The header written before the bitmap data of a font blob.
+///
+/// The header is [`FontHeader::LEN`] bytes long:
+///
+/// | Bytes | Content |
+/// |-------|---------|
+/// | 0..4  | The magic `EFNT` |
+/// | 4     | The version, currently 1 |
+/// | 5     | The font mode in bits 0 and 1, 0 row, 1 column, 2 byte column and 3 aligned column with the column height in bit 2, 0 for 16 and 1 for 32 pixels, the bit flow in bit 7, 0 mtl and 1 ltm |
+/// | 6..8  | The width of every glyph as little endian `u16` |
+/// | 8..10 | The height of every glyph as little endian `u16` |
+///
+/// Width and height are 0 if the glyphs differ in size.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FontHeader {
+    /// The mode the font was generated in
+    pub font_mode: FontMode,
+    /// The flow of the bits inside each byte
+    pub bit_flow: BitFlow,
+    /// The width and height shared by all glyphs, none if they differ in size
+    pub glyph_size: Option<(u16, u16)>,
+}
+
+impl FontHeader {
+    /// The number of bytes of a serialized header
+    pub const LEN: usize = 10;
+
+    /// Create a header for glyphs of varying size
+    pub fn new(font_mode: FontMode, bit_flow: BitFlow) -> Self {
+        Self {
+            font_mode,
+            bit_flow,
+            glyph_size: None,
+        }
+    }
+
+    /// Set the size shared by all glyphs, sizes that don't fit into `u16` are stored as varying
+    #[must_use]
+    pub fn with_glyph_size(mut self, width: usize, height: usize) -> Self {
+        self.glyph_size = u16::try_from(width).ok().zip(u16::try_from(height).ok());
+        self
+    }
+
+    /// Create the header of a generated font, with a glyph size if all glyphs have the same size
+    pub fn for_report(report: &FontReport) -> Self {
+        let header = Self::new(report.font_mode, report.bit_flow);
+        let mut sizes = report.glyphs.iter().map(|g| (g.width, g.height));
+        match sizes.next() {
+            Some(first) if sizes.all(|size| size == first) => {
+                header.with_glyph_size(first.0, first.1)
+            }
+            _ => header,
+        }
+    }
+
+    /// Serialize the header
+    pub fn to_bytes(&self) -> [u8; Self::LEN] {
+        let mode = match self.font_mode {
+            FontMode::Row => 0,
+            FontMode::Column => 1,
+            FontMode::ByteColumn => 2,
+            FontMode::AlignedColumn(ColumnAlign::Sixteen) => 3,
+            FontMode::AlignedColumn(ColumnAlign::ThirtyTwo) => 3 | 1 << 2,
+        };
+        let flow = match self.bit_flow {
+            BitFlow::Mtl => 0,
+            BitFlow::Ltm => 0x80,
+        };
+        let (width, height) = self.glyph_size.unwrap_or((0, 0));
+        let [m0, m1, m2, m3] = FONT_MAGIC;
+        let [w0, w1] = width.to_le_bytes();
+        let [h0, h1] = height.to_le_bytes();
+        [
+            m0,
+            m1,
+            m2,
+            m3,
+            FONT_HEADER_VERSION,
+            mode | flow,
+            w0,
+            w1,
+            h0,
+            h1,
+        ]
+    }
+
+    /// Write the serialized header to `out`, returns the number of bytes written.
+    ///
+    /// # Errors
+    /// An error is returned when writing to the `out` writer fails.
+    pub fn write(&self, out: &mut impl Write) -> crate::Result<usize> {
+        out.write_all(&self.to_bytes())
+            .map_err(GenerationError::OutputWriterError)?;
+        Ok(Self::LEN)
+    }
+
+    /// Read the header at the start of a font blob, returns none if the data doesn't start with
+    /// the magic. Otherwise the header is returned with the bitmap data following it.
+    ///
+    /// # Errors
+    /// An error is returned when the data starts with the magic but the header is truncated, has
+    /// an unknown version or an invalid mode byte.
+    pub fn parse(data: &[u8]) -> crate::Result<Option<(Self, &[u8])>> {
+        if !data.starts_with(&FONT_MAGIC) {
+            return Ok(None);
+        }
+        let Some((header, rest)) = data.split_first_chunk::<{ Self::LEN }>() else {
+            return Err(GenerationError::MalformedFontHeader(format!(
+                "only {} bytes",
+                data.len()
+            )));
+        };
+        let [_, _, _, _, version, modes, w0, w1, h0, h1] = *header;
+        if version != FONT_HEADER_VERSION {
+            return Err(GenerationError::MalformedFontHeader(format!(
+                "unknown version {version}"
+            )));
+        }
+        let font_mode = match modes & 0x7F {
+            0 => FontMode::Row,
+            1 => FontMode::Column,
+            2 => FontMode::ByteColumn,
+            3 => FontMode::AlignedColumn(ColumnAlign::Sixteen),
+            7 => FontMode::AlignedColumn(ColumnAlign::ThirtyTwo),
+            _ => {
+                return Err(GenerationError::MalformedFontHeader(format!(
+                    "invalid mode byte {modes:#04x}"
+                )))
+            }
+        };
+        let bit_flow = if modes & 0x80 == 0 {
+            BitFlow::Mtl
+        } else {
+            BitFlow::Ltm
+        };
+        let size = (u16::from_le_bytes([w0, w1]), u16::from_le_bytes([h0, h1]));
+        let header = Self {
+            font_mode,
+            bit_flow,
+            glyph_size: (size != (0, 0)).then_some(size),
+        };
+        Ok(Some((header, rest)))
+    }
+}
+
+impl fmt::Display for FontHeader {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "EFNT version {}, mode {}, bit flow {}, ",
+            FONT_HEADER_VERSION, self.font_mode, self.bit_flow
+        )?;
+        match self.glyph_size {
+            Some((width, height)) => write!(f, "glyphs of {}x{} pixels", width, height),
+            None => f.write_str("glyphs of varying size"),
+        }
+    }
+}
+
+/// Generate a single monochromatic font preceded by a [`FontHeader`] with the size of the
+/// image, returns the number of bytes written including the header.
+///
+/// # Errors
+/// An error is returned when the given image data can not be decoded as png or writing to the
+/// `out` writer fails.
+pub fn generate_monochromatic_with_header(
+    data: &[u8],
+    font_mode: FontMode,
+    bit_flow: BitFlow,
+    out: &mut impl Write,
+) -> crate::Result<usize> {
+    let generator = FontGenerator::new()
+        .with_mode(font_mode)
+        .with_bit_flow(bit_flow);
+    let glyph = generator.glyph(data)?;
+    let header =
+        FontHeader::new(font_mode, bit_flow).with_glyph_size(glyph.width(), glyph.height());
+    let header_len = header.write(out)?;
+    let written = generator.write_glyph(&glyph, out)?;
+    Ok(header_len.saturating_add(written))
+}
+
+/// Generate all images in a directory as font preceded by a [`FontHeader`], see
+/// [`generate_dir`].
+///
+/// The font is generated in memory since the header depends on all glyphs. The offsets of the
+/// report count from the end of the header.
+///
+/// # Errors
+/// An error is returned when the directory can not be read, writing to `out` fails or generating
+/// any glyph fails and the generator doesn't continue on errors.
+pub fn generate_dir_with_header(
+    dir_path: &Path,
+    generator: &FontGenerator,
+    out: &mut impl Write,
+) -> crate::Result<FontReport> {
+    let mut font = Vec::new();
+    let report = generate_dir(dir_path, generator, &mut font)?;
+    FontHeader::for_report(&report).write(out)?;
+    out.write_all(&font)
+        .map_err(GenerationError::OutputWriterError)?;
+    Ok(report)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::testutil;
+
+    /// A written header is read back with the bitmap data following it
+    #[test]
+    fn header_roundtrip() {
+        let png = testutil::picture(&["#..", ".#.", "..#"]);
+        let mut out = Vec::new();
+        let written =
+            generate_monochromatic_with_header(&png, FontMode::Column, BitFlow::Ltm, &mut out)
+                .unwrap();
+        assert_eq!(written, FontHeader::LEN + 2);
+        assert_eq!(out[..6], *b"EFNT\x01\x81");
+
+        let (header, bitmap) = FontHeader::parse(&out).unwrap().unwrap();
+        assert_eq!(
+            header,
+            FontHeader {
+                font_mode: FontMode::Column,
+                bit_flow: BitFlow::Ltm,
+                glyph_size: Some((3, 3)),
+            }
+        );
+        let mut plain = Vec::new();
+        crate::generate_monochromatic(&png, FontMode::Column, BitFlow::Ltm, &mut plain).unwrap();
+        assert_eq!(bitmap, plain);
+
+        assert!(FontHeader::parse(&plain).unwrap().is_none());
+        assert!(FontHeader::parse(&out[..7]).is_err());
+    }
+}
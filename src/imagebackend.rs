@@ -0,0 +1,99 @@
+//! Decoding of image formats other than png with the image crate.
+
+use std::io::Cursor;
+
+use crate::{
+    imagedecode::{DecodedImage, Rgba, SupportedColorSpace},
+    GenerationError,
+};
+
+/// An image decoded by the image crate, converted to 8 bit rgba
+pub(crate) struct ImageCrateImage {
+    /// The converted pixels
+    image: image::RgbaImage,
+    /// The color space of the source, images without alpha are rgb
+    color_space: SupportedColorSpace,
+}
+
+impl ImageCrateImage {
+    /// Decode the data in the format detected from its signature
+    pub(crate) fn decode(data: &[u8]) -> crate::Result<Self> {
+        let image = image::load_from_memory(data).map_err(image_error)?;
+        let color_space = if image.color().has_alpha() {
+            SupportedColorSpace::Rgba
+        } else {
+            SupportedColorSpace::Rgb
+        };
+        Ok(Self {
+            image: image.to_rgba8(),
+            color_space,
+        })
+    }
+
+    /// The width and height of the image read from its headers
+    pub(crate) fn dimensions_of(data: &[u8]) -> crate::Result<(usize, usize)> {
+        let (width, height) = image::io::Reader::new(Cursor::new(data))
+            .with_guessed_format()
+            .map_err(GenerationError::IoError)?
+            .into_dimensions()
+            .map_err(image_error)?;
+        Ok((to_usize(width), to_usize(height)))
+    }
+}
+
+impl DecodedImage for ImageCrateImage {
+    fn dimensions(&self) -> (usize, usize) {
+        (to_usize(self.image.width()), to_usize(self.image.height()))
+    }
+
+    fn color_space(&self) -> SupportedColorSpace {
+        self.color_space
+    }
+
+    fn nth_rgba(&mut self, n: usize) -> Option<Rgba> {
+        let start = n.checked_mul(4)?;
+        match *self.image.as_raw().get(start..start.checked_add(4)?)? {
+            [r, g, b, a] => Some(Rgba::new(r, g, b, a)),
+            _ => None,
+        }
+    }
+}
+
+/// Convert a dimension of the image crate, which fits into usize on all supported targets
+fn to_usize(len: u32) -> usize {
+    usize::try_from(len).unwrap_or(usize::MAX)
+}
+
+/// Wrap an error of the image crate
+fn image_error(e: image::ImageError) -> GenerationError {
+    GenerationError::ImageDecodingError(e.to_string())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{generate_monochromatic, testutil, BitFlow, FontMode};
+
+    /// A BMP decodes to the same bits as the equivalent png
+    #[test]
+    fn bmp_matches_png() {
+        let rows = ["#..##..#", ".#....#.", "..####..", "#.#..#.#", "........"];
+        let png = testutil::picture(&rows);
+        let pixels: Vec<u8> = rows
+            .iter()
+            .flat_map(|row| row.chars())
+            .flat_map(|c| if c == '#' { [0; 3] } else { [u8::MAX; 3] })
+            .collect();
+        let mut bmp = Vec::new();
+        image::codecs::bmp::BmpEncoder::new(&mut bmp)
+            .encode(&pixels, 8, 5, image::ColorType::Rgb8)
+            .unwrap();
+
+        for font_mode in [FontMode::Row, FontMode::ByteColumn] {
+            let mut from_png = Vec::new();
+            generate_monochromatic(&png, font_mode, BitFlow::Mtl, &mut from_png).unwrap();
+            let mut from_bmp = Vec::new();
+            generate_monochromatic(&bmp, font_mode, BitFlow::Mtl, &mut from_bmp).unwrap();
+            assert_eq!(from_bmp, from_png);
+        }
+    }
+}
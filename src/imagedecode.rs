@@ -1,21 +1,206 @@
-//! Decode png images as source files for the generated
+//! Decode png images as source files for the generated fonts
+//!
+//! Png images are decoded with zune-png. With the `image-backend` feature other formats like BMP,
+//! GIF and JPEG are decoded with the image crate, the format is detected from the signature of
+//! the data.
+
+use alloc::{borrow::ToOwned, boxed::Box, string::String, vec::Vec};
+use core::str::FromStr;
 
 pub use zune_png::{error::PngDecodeErrors, zune_core::colorspace::ColorSpace};
 
 use zune_png::{zune_core::result::DecodingResult, PngDecoder};
 
-use crate::FontMode;
+use crate::{AlphaMode, BboxRule, Dither, FontMode, LumaMode, PaddingMode};
+
+/// The threshold deciding wether a pixel is on if none is given
+pub const DEFAULT_THRESHOLD: u8 = 128;
+/// The default maximum number of scanned pixels of an image, including the alignment padding
+#[cfg(feature = "std")]
+pub const DEFAULT_MAX_IMAGE_PIXELS: usize = 1 << 24;
+/// The vertical alignment of the byte column mode in pixels
+pub(crate) const BYTE_COLUMN_ALIGN: u8 = 8;
+/// The signature every png file starts with
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+/// A decoded image the pixels are read from, independent of the decoder
+pub(crate) trait DecodedImage {
+    /// The width and height of the image
+    fn dimensions(&self) -> (usize, usize);
+
+    /// The color space the pixels were stored in
+    fn color_space(&self) -> SupportedColorSpace;
+
+    /// Get the nth rgba pixel in the image, counting starts in the top left corner and goes from
+    /// left to right, top to bottom.
+    fn nth_rgba(&mut self, n: usize) -> Option<Rgba>;
+}
+
+/// Wether the data starts with the png signature
+pub(crate) fn is_png(data: &[u8]) -> bool {
+    data.starts_with(PNG_SIGNATURE)
+}
+
+/// Decode png data with zune-png, other formats with the image crate if enabled
+fn decode_image(data: &[u8]) -> crate::Result<Box<dyn DecodedImage>> {
+    #[cfg(feature = "image-backend")]
+    if !is_png(data) {
+        return Ok(Box::new(crate::imagebackend::ImageCrateImage::decode(
+            data,
+        )?));
+    }
+    check_not_empty(data)?;
+    Ok(Box::new(PngImage::decode(data)?))
+}
+
+/// The width and height of an image read from its headers, without decoding the pixels
+fn image_dimensions(data: &[u8]) -> crate::Result<(usize, usize)> {
+    #[cfg(feature = "image-backend")]
+    if !is_png(data) {
+        return crate::imagebackend::ImageCrateImage::dimensions_of(data);
+    }
+    check_not_empty(data)?;
+    let mut decoder = PngDecoder::new(data);
+    decoder.decode_headers()?;
+    decoder
+        .get_info()
+        .map(|info| (info.width, info.height))
+        .ok_or(crate::GenerationError::PngDecodingError(
+            PngDecodeErrors::GenericStatic("Unable to get image width/height"),
+        ))
+}
+
+/// The dimensions of a glyph image and the size of its packed monochromatic font
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GlyphInfo {
+    /// The width of the image in pixels
+    pub width: usize,
+    /// The height of the image in pixels
+    pub height: usize,
+    /// The number of bytes the packed monochromatic font of the image occupies
+    pub byte_len: usize,
+}
 
-/// An iterator
-pub struct MonochromaticColorIter(RgbaColorIter);
+/// Read the dimensions of a png image from its headers without decoding the pixels, together
+/// with the number of bytes its monochromatic font occupies in the font mode. With the
+/// `image-backend` feature the headers of other formats are read too.
+///
+/// # Errors
+/// An error is returned when the headers of the png can not be decoded or the size of the image
+/// overflows.
+pub fn inspect_png(data: &[u8], font_mode: FontMode) -> crate::Result<GlyphInfo> {
+    let (width, height) = image_dimensions(data)?;
+    Ok(GlyphInfo {
+        width,
+        height,
+        byte_len: checked_char_size(font_mode, width, height)?.div_ceil(8),
+    })
+}
+
+/// An iterator over the pixels of an image in the scan order of a font mode, yielding wether
+/// each pixel is on.
+///
+/// The pixels are decoded and thresholded exactly like the generator does, so custom packers
+/// can be built on top of it:
+///
+/// ```
+/// use embedded_font_generator::{
+///     decode_monochromatic, BitFlow, FontMode, MonochromaticColorIter,
+/// };
+///
+/// // A 3x1 image with the first and last pixel on
+/// let png = decode_monochromatic(&[0xA0], 3, 1, FontMode::Row, BitFlow::Mtl)?;
+/// let pixels = MonochromaticColorIter::new(&png, FontMode::Row)?;
+/// assert_eq!((pixels.width(), pixels.height()), (3, 1));
+/// let bits: Vec<bool> = pixels.collect();
+/// assert_eq!(bits, [true, false, true]);
+/// # Ok::<(), embedded_font_generator::GenerationError>(())
+/// ```
+pub struct MonochromaticColorIter {
+    /// The iterator over the decoded pixels
+    inner: RgbaColorIter,
+    /// The cutoff deciding wether a pixel is on, see [`RgbaColorIter::is_on_at`]
+    threshold: u8,
+    /// How the threshold varies with the coordinates of the pixel
+    dither: Dither,
+    /// The scan index of the next pixel
+    idx: usize,
+}
 
 impl MonochromaticColorIter {
     /// Create a new iterator yielding monochromatic pixel values from the given png data.
     ///
-    /// The font mode describes how the
+    /// The font mode describes the order the pixels are scanned in and how the image is padded.
+    ///
+    /// # Errors
+    /// An error is returned when the data can not be decoded, the image has no pixels or its
+    /// color space is not supported.
     pub fn new(data: &[u8], font_mode: FontMode) -> crate::Result<Self> {
-        let rgba_iter = RgbaColorIter::new(data, font_mode)?;
-        Ok(Self(rgba_iter))
+        Self::with_threshold(data, font_mode, DEFAULT_THRESHOLD)
+    }
+
+    /// Create a new iterator yielding monochromatic pixel values from the given png data, using
+    /// `threshold` as cutoff for the alpha and the color of a pixel, see
+    /// [`RgbaColorIter::is_on_at`].
+    ///
+    /// # Errors
+    /// An error is returned in the same cases as for [`MonochromaticColorIter::new`].
+    pub fn with_threshold(data: &[u8], font_mode: FontMode, threshold: u8) -> crate::Result<Self> {
+        let inner = RgbaColorIter::new(data, font_mode)?;
+        Ok(Self {
+            inner,
+            threshold,
+            dither: Dither::None,
+            idx: 0,
+        })
+    }
+
+    /// Vary the threshold of every pixel with its coordinates in the image, see
+    /// [`Dither::threshold_at`]
+    #[must_use]
+    pub fn with_dither(self, dither: Dither) -> Self {
+        Self { dither, ..self }
+    }
+
+    /// Map the luminance of every pixel through the table before it is thresholded, see
+    /// [`RgbaColorIter::with_luminance_lut`]
+    #[must_use]
+    pub fn with_luminance_lut(self, lut: Option<[u8; 256]>) -> Self {
+        Self {
+            inner: self.inner.with_luminance_lut(lut),
+            ..self
+        }
+    }
+
+    /// Decide wether a pixel is on by its weighted luminance, see
+    /// [`RgbaColorIter::with_luma_mode`]
+    #[must_use]
+    pub fn with_luma_mode(self, luma_mode: LumaMode) -> Self {
+        Self {
+            inner: self.inner.with_luma_mode(luma_mode),
+            ..self
+        }
+    }
+
+    /// Fill the alignment padding according to the padding mode. Padding pixels of
+    /// [`PaddingMode::Zero`] and [`PaddingMode::One`] are off and on regardless of the threshold,
+    /// a repeated edge is thresholded like the pixel it repeats.
+    #[must_use]
+    pub fn with_padding(self, padding_mode: PaddingMode) -> Self {
+        Self {
+            inner: self.inner.with_padding(padding_mode),
+            ..self
+        }
+    }
+
+    /// The width of the decoded image
+    pub fn width(&self) -> usize {
+        self.inner.width()
+    }
+
+    /// The height of the decoded image
+    pub fn height(&self) -> usize {
+        self.inner.height()
     }
 }
 
@@ -23,23 +208,34 @@ impl Iterator for MonochromaticColorIter {
     type Item = bool;
 
     fn next(&mut self) -> Option<Self::Item> {
-        /// The middle of the u8 range
-        const U8_HALF: u8 = u8::MAX / 2;
+        let rgba = self.inner.next()?;
+        let idx = self.idx;
+        self.idx = self.idx.saturating_add(1);
 
-        let rgba = self.0.next()?;
-        let is_on = if self.0.color_space.suppports_alpha() {
-            rgba.a > U8_HALF
-        } else {
-            rgba.a > U8_HALF && (rgba.r < U8_HALF || rgba.g < U8_HALF || rgba.b < U8_HALF)
+        let (font_mode, width, height) = (self.inner.font_mode, self.width(), self.height());
+        let pos = scan_index(font_mode, width, height, idx);
+        if let Some(PaddedPixel::Fill(on)) =
+            pos.map(|n| padded_pixel(self.inner.padding_mode, width, height, n))
+        {
+            return Some(on);
+        }
+        let threshold = match pos {
+            Some(pos) if width != 0 => {
+                self.dither
+                    .threshold_at(pos % width, pos / width, self.threshold)
+            }
+            _ => self.threshold,
         };
-        Some(is_on)
+        Some(self.inner.is_on_at(rgba, threshold))
     }
 }
 
-/// An iterator over RGBA pixels of a PNG
+/// An iterator over the RGBA pixels of an image in the scan order of a font mode. Pixels in the
+/// alignment padding of the byte column mode are filled according to the padding mode, see
+/// [`RgbaColorIter::with_padding`].
 pub struct RgbaColorIter {
-    /// The inner storage of decoded values
-    inner: RgbaColorIterInner,
+    /// The decoded image
+    image: Box<dyn DecodedImage>,
     /// The used color space
     color_space: SupportedColorSpace,
     /// The mode in which the font should be generated
@@ -52,64 +248,243 @@ pub struct RgbaColorIter {
     char_size: usize,
     /// The current iteration index
     idx: usize,
+    /// Table mapping the luminance of a pixel to its effective luminance
+    luminance_lut: Option<[u8; 256]>,
+    /// How the color of a pixel decides wether it is on
+    luma_mode: LumaMode,
+    /// How the alignment padding is filled
+    padding_mode: PaddingMode,
 }
 
 impl RgbaColorIter {
-    /// Create a new iterator over rgba pixels from png data
+    /// Create a new iterator over rgba pixels from png data, images without pixels are an error
+    ///
+    /// # Errors
+    /// An error is returned when the data can not be decoded, the image has no pixels or its
+    /// color space is not supported.
     pub fn new(data: &[u8], font_mode: FontMode) -> crate::Result<Self> {
-        let mut decoder = PngDecoder::new(data);
-        decoder.decode_headers()?;
-        let color_space = decoder.get_colorspace().unwrap_or(ColorSpace::Unknown);
-        let color_space = SupportedColorSpace::new(color_space)
-            .ok_or(crate::GenerationError::UnsupportedColorspace(color_space))?;
+        let image = decode_image(data)?;
+        let (width, height) = image.dimensions();
+        let char_size = checked_char_size(font_mode, width, height)?;
 
-        let info = decoder.get_info().ok_or_else(|| {
-            crate::GenerationError::PngDecodingError(PngDecodeErrors::GenericStatic(
-                "Unable to get image width/height",
-            ))
-        })?;
-
-        let width = info.width;
-        let height = info.height;
-        let char_size = calc_char_size(font_mode, width, height);
-
-        let decoded = decoder.decode()?;
         let me = Self {
-            inner: match decoded {
-                DecodingResult::U8(v) => RgbaColorIterInner::U8(v),
-                DecodingResult::U16(v) => RgbaColorIterInner::U16(v),
-                DecodingResult::F32(v) => RgbaColorIterInner::F32(v),
-                _ => unimplemented!("Unsupported color depth"),
-            },
-            color_space,
+            color_space: image.color_space(),
+            image,
             font_mode,
             width,
             height,
             char_size,
             idx: 0usize,
+            luminance_lut: None,
+            luma_mode: LumaMode::AnyChannel,
+            padding_mode: PaddingMode::Zero,
         };
 
         Ok(me)
     }
+
+    /// Map the luminance of every pixel through the table, indexed by the luminance of the
+    /// pixel, before it is thresholded or quantized. This matches the gamma curve of calibrated
+    /// displays.
+    ///
+    /// In color spaces with alpha the table decides wether an opaque pixel is on, the ink level
+    /// is still the opacity of a pixel.
+    #[must_use]
+    pub fn with_luminance_lut(mut self, lut: Option<[u8; 256]>) -> Self {
+        self.luminance_lut = lut;
+        self
+    }
+
+    /// Decide wether a pixel is on by its weighted luminance instead of its darkest component,
+    /// the weights are also used for the luminance table. In color spaces with alpha the
+    /// luminance decides wether an opaque pixel is on.
+    #[must_use]
+    pub fn with_luma_mode(mut self, luma_mode: LumaMode) -> Self {
+        self.luma_mode = luma_mode;
+        self
+    }
+
+    /// Fill the alignment padding according to the padding mode. Off padding pixels are
+    /// transparent black, on padding pixels opaque black and a repeated edge yields the pixels
+    /// of the last row of the image.
+    #[must_use]
+    pub fn with_padding(mut self, padding_mode: PaddingMode) -> Self {
+        self.padding_mode = padding_mode;
+        self
+    }
+
+    /// Wether the given pixel of this image counts as on in a monochromatic font, using the
+    /// default threshold
+    pub fn is_on(&self, rgba: Rgba) -> bool {
+        self.is_on_at(rgba, DEFAULT_THRESHOLD)
+    }
+
+    /// Wether the given pixel of this image counts as on in a monochromatic font.
+    ///
+    /// A pixel has to be at least as opaque as the threshold. If a luminance table or a weighted
+    /// luma mode is set its luminance also has to be below the threshold, otherwise any of its
+    /// color components has to be below the threshold in color spaces without alpha.
+    pub fn is_on_at(&self, rgba: Rgba, threshold: u8) -> bool {
+        if self.luminance_lut.is_some() || self.luma_mode != LumaMode::AnyChannel {
+            rgba.a >= threshold && self.luminance(rgba) < threshold
+        } else if self.color_space.suppports_alpha() {
+            rgba.a >= threshold
+        } else {
+            rgba.a >= threshold && (rgba.r < threshold || rgba.g < threshold || rgba.b < threshold)
+        }
+    }
+
+    /// Wether the given pixel of this image counts towards the bounding box under the rule
+    pub fn is_content(&self, rgba: Rgba, rule: BboxRule) -> bool {
+        /// The middle of the u8 range
+        const U8_HALF: u8 = u8::MAX / 2;
+
+        match rule {
+            BboxRule::Packing => self.is_on(rgba),
+            BboxRule::Alpha => rgba.a > U8_HALF,
+            BboxRule::Luminance => self.luminance(rgba) < U8_HALF,
+        }
+    }
+
+    /// The luminance of the given pixel, mapped through the luminance table if set
+    pub fn luminance(&self, rgba: Rgba) -> u8 {
+        let luma = rgba.luma(self.luma_mode);
+        match &self.luminance_lut {
+            Some(lut) => lut[usize::from(luma)],
+            None => luma,
+        }
+    }
+
+    /// The amount of ink of the given pixel of this image, from 0 for background to `u8::MAX` for
+    /// full ink.
+    ///
+    /// In color spaces without alpha the ink is the darkness of the pixel. In color spaces with
+    /// alpha the ink is the opacity, which is either thresholded like [`Self::is_on`] or used
+    /// directly as level depending on the alpha mode.
+    pub fn ink_level(&self, rgba: Rgba, alpha_mode: AlphaMode) -> u8 {
+        if self.color_space.suppports_alpha() {
+            match alpha_mode {
+                AlphaMode::Threshold if self.is_on(rgba) => u8::MAX,
+                AlphaMode::Threshold => 0,
+                AlphaMode::Modulate => rgba.a,
+            }
+        } else {
+            u8::MAX.saturating_sub(self.luminance(rgba))
+        }
+    }
+
+    /// The width of the decoded image
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// The height of the decoded image
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Wether the color space of the decoded image has an alpha channel
+    pub fn has_alpha(&self) -> bool {
+        self.color_space.suppports_alpha()
+    }
+}
+
+/// Return [`GenerationError::EmptyImage`](crate::GenerationError::EmptyImage) if the `IHDR`
+/// chunk, which directly follows the signature, has a width or height of 0. The decoder rejects
+/// such images too, but only with a generic error.
+fn check_not_empty(data: &[u8]) -> crate::Result<()> {
+    let dimension = |range| {
+        data.get(range)
+            .and_then(|bytes: &[u8]| bytes.try_into().ok())
+            .map(u32::from_be_bytes)
+            .and_then(|val| usize::try_from(val).ok())
+    };
+    if data.get(12..16) != Some(b"IHDR") {
+        return Ok(());
+    }
+    match (dimension(16..20), dimension(20..24)) {
+        (Some(width), Some(height)) if width == 0 || height == 0 => {
+            Err(crate::GenerationError::EmptyImage { width, height })
+        }
+        _ => Ok(()),
+    }
 }
 
 /// Calculate the size the complete char has in theory, this might be larger than width * height
-/// because of aligment
-fn calc_char_size(font_mode: FontMode, width: usize, height: usize) -> usize {
+/// because of aligment. Returns `None` if the size overflows.
+pub(crate) fn calc_char_size(font_mode: FontMode, width: usize, height: usize) -> Option<usize> {
+    match font_mode.column_align() {
+        Some(align) => aligned_char_size(width, height, align),
+        None => width.checked_mul(height),
+    }
+}
+
+/// The size of a char scanned in columns of `align` pixels, its height is padded to the next
+/// multiple of `align`. Returns `None` if the size overflows or `align` is no power of two.
+pub(crate) fn aligned_char_size(width: usize, height: usize, align: u8) -> Option<usize> {
+    if !align.is_power_of_two() {
+        return None;
+    }
+    // Exact multiples are their own next multiple, so they get no padding
+    width.checked_mul(height.checked_next_multiple_of(usize::from(align))?)
+}
+
+/// The size of the complete char like [`calc_char_size`], an overflowing size is an error
+pub(crate) fn checked_char_size(
+    font_mode: FontMode,
+    width: usize,
+    height: usize,
+) -> crate::Result<usize> {
+    calc_char_size(font_mode, width, height)
+        .ok_or(crate::GenerationError::ImageTooLarge { width, height })
+}
+
+/// Map the `idx`th scanned pixel of the given font mode to the index of the pixel in the image,
+/// counting from the top left corner left to right, top to bottom.
+///
+/// The returned index might be out of the image bounds if `idx` lies in the alignment padding.
+pub(crate) fn scan_index(
+    font_mode: FontMode,
+    width: usize,
+    height: usize,
+    idx: usize,
+) -> Option<usize> {
     match font_mode {
-        FontMode::Row => width.wrapping_mul(height),
-        FontMode::ByteColumn => {
-            if height % 8 == 0 {
-                width.wrapping_mul(height)
-            } else {
-                // 8 - (height % 8) + height to calculate next multiple of 8 as height
-                let height = 8usize.wrapping_sub(height % 8).wrapping_add(height);
-                width.wrapping_mul(height)
-            }
+        FontMode::Row => Some(idx),
+        FontMode::Column => {
+            let column = idx.checked_div(height)?;
+            let row = idx.checked_rem(height)?;
+            Some(row.wrapping_mul(width).wrapping_add(column))
+        }
+        FontMode::ByteColumn | FontMode::AlignedColumn(_) => {
+            aligned_scan_index(width, font_mode.column_align()?, idx)
         }
     }
 }
 
+/// Map the `idx`th scanned pixel of a char scanned in columns of `align` pixels to the index of
+/// the pixel in the image, like [`scan_index`]. Returns `None` if `align` is no power of two.
+pub(crate) fn aligned_scan_index(width: usize, align: u8, idx: usize) -> Option<usize> {
+    if !align.is_power_of_two() {
+        return None;
+    }
+    let align = usize::from(align);
+    // Calculate the start pixel of the current width * align block
+    let block_px = width.saturating_mul(align);
+    let block_idx = idx.checked_div(block_px)?;
+    let block_start = block_idx.wrapping_mul(block_px);
+    // Calculate the column index
+    let column = idx.wrapping_sub(block_start) / align;
+    // Calculate row index
+    let row = idx.wrapping_sub(block_start) % align;
+
+    Some(
+        block_start
+            .wrapping_add(column)
+            .wrapping_add(row.wrapping_mul(width)),
+    )
+}
+
 impl Iterator for RgbaColorIter {
     type Item = Rgba;
 
@@ -118,34 +493,107 @@ impl Iterator for RgbaColorIter {
             return None;
         }
 
-        let n = match self.font_mode {
-            FontMode::Row => self.idx,
-            FontMode::ByteColumn => {
-                let idx = self.idx;
-                let width = self.width;
-                // Calculate the start pixel of the current width * 8 block
-                let block_px = width.saturating_mul(8);
-                let block_idx = idx.checked_div(block_px)?;
-                let block_start = block_idx.wrapping_mul(block_px);
-                // Calculate the column index
-                let column = self.idx.wrapping_sub(block_start) / 8;
-                // Calculate row index
-                let row = self.idx.wrapping_sub(block_start) % 8;
-
-                block_start
-                    .wrapping_add(column)
-                    .wrapping_add(row.wrapping_mul(width))
-            }
-        };
+        let n = scan_index(self.font_mode, self.width, self.height, self.idx)?;
 
         self.idx = self.idx.saturating_add(1);
 
-        if n >= self.width.wrapping_mul(self.height) {
-            return Some(Rgba::ZERO);
+        match padded_pixel(self.padding_mode, self.width, self.height, n) {
+            PaddedPixel::Image(n) => self.image.nth_rgba(n),
+            PaddedPixel::Fill(false) => Some(Rgba::ZERO),
+            PaddedPixel::Fill(true) => Some(Rgba {
+                a: u8::MAX,
+                ..Rgba::ZERO
+            }),
         }
+    }
+}
+
+/// The source of a scanned pixel, see [`padded_pixel`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum PaddedPixel {
+    /// The pixel with this index in the image
+    Image(usize),
+    /// A padding pixel with this fixed value
+    Fill(bool),
+}
+
+/// Resolve the index of a pixel returned by [`scan_index`] to the pixel it is read from. Indices
+/// past the image lie in the alignment padding, they are filled according to the padding mode.
+pub(crate) fn padded_pixel(
+    padding_mode: PaddingMode,
+    width: usize,
+    height: usize,
+    n: usize,
+) -> PaddedPixel {
+    if n < width.saturating_mul(height) {
+        return PaddedPixel::Image(n);
+    }
+    match padding_mode {
+        PaddingMode::Zero => PaddedPixel::Fill(false),
+        PaddingMode::One => PaddedPixel::Fill(true),
+        PaddingMode::RepeatEdge => match (n.checked_rem(width), height.checked_sub(1)) {
+            (Some(x), Some(last_row)) => {
+                PaddedPixel::Image(last_row.saturating_mul(width).saturating_add(x))
+            }
+            _ => PaddedPixel::Fill(false),
+        },
+    }
+}
+
+/// A png image decoded by zune-png
+struct PngImage {
+    /// The decoded values
+    pixels: RgbaColorIterInner,
+    /// The color space of the values
+    color_space: SupportedColorSpace,
+    /// The width of the image
+    width: usize,
+    /// The height of the image
+    height: usize,
+}
+
+impl PngImage {
+    /// Decode the png data
+    fn decode(data: &[u8]) -> crate::Result<Self> {
+        let mut decoder = PngDecoder::new(data);
+        decoder.decode_headers()?;
+        let color_space = decoder.get_colorspace().unwrap_or(ColorSpace::Unknown);
+        let color_space = SupportedColorSpace::new(color_space)
+            .ok_or(crate::GenerationError::UnsupportedColorspace(color_space))?;
 
-        let rgba = self.inner.get_nth_rgba(n, self.color_space)?;
-        Some(rgba)
+        let info = decoder
+            .get_info()
+            .ok_or(crate::GenerationError::PngDecodingError(
+                PngDecodeErrors::GenericStatic("Unable to get image width/height"),
+            ))?;
+        let (width, height) = (info.width, info.height);
+
+        let pixels = match decoder.decode()? {
+            DecodingResult::U8(v) => RgbaColorIterInner::U8(v),
+            DecodingResult::U16(v) => RgbaColorIterInner::U16(v),
+            DecodingResult::F32(v) => RgbaColorIterInner::F32(v),
+            _ => return Err(crate::GenerationError::UnsupportedColorDepth),
+        };
+        Ok(Self {
+            pixels,
+            color_space,
+            width,
+            height,
+        })
+    }
+}
+
+impl DecodedImage for PngImage {
+    fn dimensions(&self) -> (usize, usize) {
+        (self.width, self.height)
+    }
+
+    fn color_space(&self) -> SupportedColorSpace {
+        self.color_space
+    }
+
+    fn nth_rgba(&mut self, n: usize) -> Option<Rgba> {
+        self.pixels.get_nth_rgba(n, self.color_space)
     }
 }
 
@@ -172,10 +620,7 @@ impl RgbaColorIterInner {
                 &mut bytes,
             ),
             Self::U16(ref mut v) => fill_bytes(
-                v.iter().skip(byte_offset).copied().map(|val| {
-                    u8::try_from(val / 256)
-                        .unwrap_or_else(|_| unreachable!("Every u16 / 256 should be a valid u8"))
-                }),
+                v.iter().skip(byte_offset).copied().map(u16_to_u8),
                 color_space.num_components(),
                 &mut bytes,
             ),
@@ -214,6 +659,16 @@ fn fill_bytes<I: Iterator<Item = u8>>(
     true
 }
 
+/// Scale a 16 bit sample to the nearest 8 bit value, 65535 maps to 255 and values halfway
+/// between two 8 bit values are rounded up
+fn u16_to_u8(val: u16) -> u8 {
+    let scaled = u32::from(val)
+        .saturating_mul(u32::from(u8::MAX))
+        .saturating_add(u32::from(u16::MAX / 2))
+        / u32::from(u16::MAX);
+    u8::try_from(scaled).unwrap_or(u8::MAX)
+}
+
 /// Best effor conversion of a f32 from 0.0 to 1.0 to u8
 fn f32_to_u8(val: f32) -> u8 {
     // This is the only real way to convert a f32 between 0 and 1 to a u8
@@ -274,21 +729,61 @@ fn parse_rgba(bytes: [u8; 4], color_space: SupportedColorSpace) -> Rgba {
 }
 
 /// A single Rgba Pixel
-#[derive(Clone, Copy, Debug)]
+///
+/// The components of the pixels of an image can be read to map them to bits in other ways than
+/// the generator does:
+///
+/// ```
+/// use embedded_font_generator::{decode_monochromatic, BitFlow, FontMode, Rgba, RgbaColorIter};
+///
+/// // A 2x1 image with a black and a white pixel
+/// let png = decode_monochromatic(&[0x80], 2, 1, FontMode::Row, BitFlow::Mtl)?;
+/// let pixels: Vec<Rgba> = RgbaColorIter::new(&png, FontMode::Row)?.collect();
+/// assert_eq!(pixels[0].r, 0);
+/// assert_eq!(pixels[1], Rgba::new(255, 255, 255, 255));
+/// # Ok::<(), embedded_font_generator::GenerationError>(())
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct Rgba {
     /// Red value
-    r: u8,
+    pub r: u8,
     /// Green value
-    g: u8,
+    pub g: u8,
     /// Blue value
-    b: u8,
+    pub b: u8,
     /// Alpha value
-    a: u8,
+    pub a: u8,
 }
 
 impl Rgba {
+    /// Create a pixel from its components
+    pub const fn new(r: u8, g: u8, b: u8, a: u8) -> Self {
+        Self { r, g, b, a }
+    }
+
+    /// The color components in the order green, red, blue as used by WS2812 leds
+    pub fn grb(self) -> [u8; 3] {
+        [self.g, self.r, self.b]
+    }
+
+    /// The color components in the order red, green, blue
+    pub fn rgb(self) -> [u8; 3] {
+        [self.r, self.g, self.b]
+    }
+
+    /// The brightness of the pixel ignoring alpha, weighted by the perceived brightness of the
+    /// components in the luma mode
+    fn luma(self, luma_mode: LumaMode) -> u8 {
+        let [r, g, b] = luma_mode.weights();
+        let weighted = u32::from(self.r)
+            .saturating_mul(r)
+            .saturating_add(u32::from(self.g).saturating_mul(g))
+            .saturating_add(u32::from(self.b).saturating_mul(b));
+        u8::try_from(weighted / 10000).unwrap_or(u8::MAX)
+    }
+
     /// ZERO value of an Rgba pixel with all components = 0 (completely transparent black)
-    const ZERO: Self = Self {
+    pub const ZERO: Self = Self {
         r: 0,
         g: 0,
         b: 0,
@@ -296,8 +791,40 @@ impl Rgba {
     };
 }
 
-/// Enumeration of all supported color spaces
-#[derive(Debug, Clone, Copy)]
+impl FromStr for Rgba {
+    type Err = RgbaParseError;
+
+    /// Parse an opaque color written as hexadecimal `RRGGBB`, optionally prefixed with `#`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let hex = s.strip_prefix('#').unwrap_or(s);
+        if hex.len() != 6 || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(RgbaParseError(s.to_owned()));
+        }
+        let [_, r, g, b] = u32::from_str_radix(hex, 16)
+            .map_err(|_| RgbaParseError(s.to_owned()))?
+            .to_be_bytes();
+        Ok(Self::new(r, g, b, u8::MAX))
+    }
+}
+
+/// A color was tried to be parsed that is no hexadecimal `RRGGBB` color
+#[derive(Debug)]
+#[cfg_attr(
+    feature = "std",
+    derive(thiserror::Error),
+    error("Invalid color {0}, expected RRGGBB")
+)]
+pub struct RgbaParseError(String);
+
+display_without_std!(RgbaParseError);
+
+/// Enumeration of all supported color spaces.
+///
+/// Palette images have no color space of their own, the decoder expands their palette entries
+/// to RGB, or RGBA if the palette has transparency, at any bit depth. Images in any other
+/// [`ColorSpace`] are rejected with
+/// [`GenerationError::UnsupportedColorspace`](crate::GenerationError::UnsupportedColorspace).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum SupportedColorSpace {
     /// RGB color space, alpha will be set to u8::MAX
     Rgb,
@@ -355,13 +882,301 @@ impl SupportedColorSpace {
 #[cfg(test)]
 mod test {
     use super::*;
+    #[cfg(feature = "std")]
+    use crate::{BitFlow, GenerationError};
 
     /// Prevent regression of wrong size calculations
     #[test]
     fn calc_char_size_test() {
-        assert_eq!(calc_char_size(FontMode::Row, 10, 16), 10 * 16);
-        assert_eq!(calc_char_size(FontMode::Row, 10, 20), 10 * 20);
-        assert_eq!(calc_char_size(FontMode::ByteColumn, 10, 16), 10 * 16);
-        assert_eq!(calc_char_size(FontMode::ByteColumn, 10, 20), 10 * 24);
+        assert_eq!(calc_char_size(FontMode::Row, 10, 16), Some(10 * 16));
+        assert_eq!(calc_char_size(FontMode::Row, 10, 20), Some(10 * 20));
+        assert_eq!(calc_char_size(FontMode::ByteColumn, 10, 16), Some(10 * 16));
+        assert_eq!(calc_char_size(FontMode::ByteColumn, 10, 20), Some(10 * 24));
+        assert_eq!(calc_char_size(FontMode::Column, 10, 16), Some(10 * 16));
+        assert_eq!(calc_char_size(FontMode::Column, 10, 20), Some(10 * 20));
+        assert_eq!(aligned_char_size(10, 20, 8), Some(10 * 24));
+        assert_eq!(aligned_char_size(10, 16, 16), Some(10 * 16));
+        assert_eq!(aligned_char_size(10, 20, 16), Some(10 * 32));
+        assert_eq!(aligned_char_size(10, 20, 32), Some(10 * 32));
+        assert_eq!(aligned_char_size(10, 20, 12), None);
+    }
+
+    /// Columns of 16 pixels are scanned top to bottom before the next column starts
+    #[cfg(feature = "std")]
+    #[test]
+    fn aligned_scan_16() {
+        let scanned: Vec<usize> = (0..aligned_char_size(2, 20, 16).unwrap())
+            .map(|idx| aligned_scan_index(2, 16, idx).unwrap())
+            .collect();
+        let column = |x: usize, rows: std::ops::Range<usize>| rows.map(move |y| y * 2 + x);
+        let expected: Vec<usize> = column(0, 0..16)
+            .chain(column(1, 0..16))
+            .chain(column(0, 16..32))
+            .chain(column(1, 16..32))
+            .collect();
+        assert_eq!(scanned, expected);
+        assert_eq!(
+            aligned_scan_index(2, BYTE_COLUMN_ALIGN, 9),
+            scan_index(FontMode::ByteColumn, 2, 20, 9)
+        );
+    }
+
+    /// Sizes that overflow are rejected, a crafted header fails before the pixels are decoded
+    #[cfg(feature = "std")]
+    #[test]
+    fn image_too_large() {
+        assert_eq!(calc_char_size(FontMode::Row, usize::MAX, 2), None);
+        assert_eq!(calc_char_size(FontMode::ByteColumn, 2, usize::MAX), None);
+        let res = crate::decode_monochromatic(&[], usize::MAX, 3, FontMode::Row, BitFlow::Mtl);
+        assert!(matches!(
+            res,
+            Err(GenerationError::ImageTooLarge {
+                width: usize::MAX,
+                height: 3
+            })
+        ));
+
+        // Only the header claims this size, the image data holds a single pixel
+        let png = crate::testutil::gray(16000, 16000, &[0]);
+        let res = crate::FontGenerator::new().generate(&png, &mut Vec::new());
+        assert!(matches!(
+            res,
+            Err(GenerationError::ImageTooLarge {
+                width: 16000,
+                height: 16000
+            })
+        ));
+        let small = crate::testutil::gray(4, 4, &[0; 16]);
+        let res = crate::FontGenerator::new()
+            .with_max_image_pixels(15)
+            .generate(&small, &mut Vec::new());
+        assert!(matches!(res, Err(GenerationError::ImageTooLarge { .. })));
+    }
+
+    /// The first `height` pixels of the column mode all come from column 0
+    #[test]
+    fn column_iteration_order() {
+        let png = crate::testutil::picture(&["#..", "#..", "#.#", "##."]);
+        let pixels: Vec<_> = MonochromaticColorIter::new(&png, FontMode::Column)
+            .unwrap()
+            .collect();
+        assert_eq!(pixels.len(), 3 * 4);
+        assert_eq!(pixels[..4], [true; 4]);
+        assert_eq!(pixels[4..8], [false, false, false, true]);
+        assert_eq!(pixels[8..], [false, false, true, false]);
+
+        let indices: Vec<_> = (0..4)
+            .map(|idx| scan_index(FontMode::Column, 3, 4, idx).unwrap())
+            .collect();
+        assert_eq!(indices, [0, 3, 6, 9]);
+    }
+
+    /// The dimensions and packed size are read from the headers
+    #[test]
+    fn inspect_dimensions() {
+        let png = crate::testutil::gray(10, 13, &[0; 10 * 13]);
+        let info = inspect_png(&png, FontMode::Row).unwrap();
+        assert_eq!(
+            info,
+            GlyphInfo {
+                width: 10,
+                height: 13,
+                byte_len: 17
+            }
+        );
+        assert_eq!(
+            inspect_png(&png, FontMode::ByteColumn).unwrap().byte_len,
+            20
+        );
+    }
+
+    /// A header with a height of 0 is reported as empty image instead of a decoding error
+    #[cfg(feature = "std")]
+    #[test]
+    fn empty_image() {
+        let png = crate::testutil::gray(4, 0, &[]);
+        assert!(matches!(
+            RgbaColorIter::new(&png, FontMode::Row),
+            Err(GenerationError::EmptyImage {
+                width: 4,
+                height: 0
+            })
+        ));
+        let res = crate::generate_monochromatic(&png, FontMode::Row, BitFlow::Mtl, &mut Vec::new());
+        assert!(matches!(res, Err(GenerationError::EmptyImage { .. })));
+    }
+
+    /// 16 bit samples are rounded to the nearest 8 bit value before they are thresholded
+    #[test]
+    fn gray16_threshold() {
+        assert_eq!(u16_to_u8(0), 0);
+        assert_eq!(u16_to_u8(25829), 101);
+        assert_eq!(u16_to_u8(32768), 128);
+        assert_eq!(u16_to_u8(u16::MAX), u8::MAX);
+
+        let png = crate::testutil::gray16(4, 1, &[32767, 32768, 0, u16::MAX]);
+        let pixels: Vec<_> = MonochromaticColorIter::new(&png, FontMode::Row)
+            .unwrap()
+            .collect();
+        assert_eq!(pixels, [true, false, true, false]);
+    }
+
+    /// A 4 color palette image with 2 bits per pixel decodes like its expanded colors
+    #[cfg(feature = "std")]
+    #[test]
+    fn indexed_palette() {
+        let palette = [[0, 0, 0], [255, 255, 255], [40, 40, 40], [200, 0, 0]];
+        let indices = [0, 1, 2, 3, 1, 1, 0, 2];
+        let png = crate::testutil::indexed(4, 2, 2, &palette, &indices);
+
+        let pixels: Vec<_> = MonochromaticColorIter::new(&png, FontMode::Row)
+            .unwrap()
+            .collect();
+        assert_eq!(pixels, [true, false, true, true, false, false, true, true]);
+
+        let mut out = Vec::new();
+        crate::generate_monochromatic(&png, FontMode::Row, crate::BitFlow::Mtl, &mut out).unwrap();
+        assert_eq!(out, [0xB3]);
+    }
+
+    /// A 1 bit grayscale image is expanded to full 8 bit levels and packs like its 8 bit twin
+    #[cfg(feature = "std")]
+    #[test]
+    fn one_bit_gray() {
+        let png = crate::testutil::gray_bits(5, 2, 1, &[0, 1, 0, 1, 1, 1, 0, 1, 1, 0]);
+        let pixels: Vec<Rgba> = RgbaColorIter::new(&png, FontMode::Row).unwrap().collect();
+        assert_eq!(pixels[0], Rgba::new(0, 0, 0, 255));
+        assert_eq!(pixels[1], Rgba::new(255, 255, 255, 255));
+
+        let mut one_bit = Vec::new();
+        crate::generate_monochromatic(&png, FontMode::Row, BitFlow::Mtl, &mut one_bit).unwrap();
+        let twin = crate::testutil::picture(&["#.#..", ".#..#"]);
+        let mut eight_bit = Vec::new();
+        crate::generate_monochromatic(&twin, FontMode::Row, BitFlow::Mtl, &mut eight_bit).unwrap();
+        assert_eq!(one_bit, eight_bit);
+        assert_eq!(one_bit, [0xA2, 0x40]);
+    }
+
+    /// Pixels stored in BGR order are swapped into logical rgb order, so the weighted luma sees
+    /// the red component as red
+    #[cfg(feature = "std")]
+    #[test]
+    fn bgr_channel_order() {
+        let image = |pixels, color_space| PngImage {
+            pixels,
+            color_space,
+            width: 2,
+            height: 1,
+        };
+        let orange = Rgba::new(255, 128, 0, u8::MAX);
+
+        let mut bgr = image(
+            RgbaColorIterInner::U8(vec![0, 128, 255, 200, 10, 20]),
+            SupportedColorSpace::Bgr,
+        );
+        assert_eq!(bgr.nth_rgba(0), Some(orange));
+        assert_eq!(bgr.nth_rgba(1), Some(Rgba::new(20, 10, 200, u8::MAX)));
+        assert_eq!(bgr.nth_rgba(2), None);
+
+        let mut bgra = image(
+            RgbaColorIterInner::U8(vec![0, 128, 255, 64, 200, 10, 20, 255]),
+            SupportedColorSpace::Bgra,
+        );
+        assert_eq!(bgra.nth_rgba(0), Some(Rgba::new(255, 128, 0, 64)));
+        assert_eq!(bgra.nth_rgba(1), Some(Rgba::new(20, 10, 200, 255)));
+
+        let mut bgr16 = image(
+            RgbaColorIterInner::U16(vec![0, 0x8080, u16::MAX, 0, 0, 0]),
+            SupportedColorSpace::Bgr,
+        );
+        assert_eq!(bgr16.nth_rgba(0), Some(orange));
+
+        // Swapped components would put the luma of orange below the threshold
+        assert!(orange.luma(LumaMode::Rec601) >= DEFAULT_THRESHOLD);
+        assert!(Rgba::new(0, 128, 255, u8::MAX).luma(LumaMode::Rec601) < DEFAULT_THRESHOLD);
+    }
+
+    /// Heights around multiples of 8 grow by a full block exactly after the boundary
+    #[test]
+    fn byte_column_boundaries() {
+        for boundary in [8, 16, 24] {
+            assert_eq!(
+                calc_char_size(FontMode::ByteColumn, 3, boundary - 1),
+                Some(3 * boundary)
+            );
+            assert_eq!(
+                calc_char_size(FontMode::ByteColumn, 3, boundary),
+                Some(3 * boundary)
+            );
+            assert_eq!(
+                calc_char_size(FontMode::ByteColumn, 3, boundary + 1),
+                Some(3 * (boundary + 8))
+            );
+
+            // The padded glyph scans the unpadded pixels first and only then the padding block
+            let unpadded = calc_char_size(FontMode::ByteColumn, 3, boundary).unwrap();
+            let padded = calc_char_size(FontMode::ByteColumn, 3, boundary + 1).unwrap();
+            for idx in 0..padded {
+                let n = scan_index(FontMode::ByteColumn, 3, boundary + 1, idx).unwrap();
+                if idx < unpadded {
+                    assert!(n < 3 * boundary);
+                } else {
+                    assert!(n >= 3 * boundary);
+                }
+            }
+        }
+    }
+
+    /// On padding is on whatever the threshold and packs like the padding of the generator
+    #[cfg(feature = "std")]
+    #[test]
+    fn one_padding() {
+        let png = crate::testutil::picture(&["#.", ".#", ".."]);
+        let pixels = |threshold| -> Vec<bool> {
+            MonochromaticColorIter::with_threshold(&png, FontMode::ByteColumn, threshold)
+                .unwrap()
+                .with_padding(PaddingMode::One)
+                .collect()
+        };
+        let column = |a, b| [a, b, false, true, true, true, true, true];
+        assert_eq!(
+            pixels(128),
+            [column(true, false), column(false, true)].concat()
+        );
+        assert_eq!(pixels(0), [column(false, false); 2].concat());
+
+        let mut packed = Vec::new();
+        let Ok(_) = crate::pack_monochromatic(pixels(128), crate::BitFlow::Mtl, &mut packed);
+        let mut generated = Vec::new();
+        crate::FontGenerator::new()
+            .with_mode(FontMode::ByteColumn)
+            .with_padding_mode(PaddingMode::One)
+            .generate(&png, &mut generated)
+            .unwrap();
+        assert_eq!(packed, generated);
+    }
+
+    /// The padding of a byte column repeats the pixel of the last row in its column, without a
+    /// padding mode it is transparent black
+    #[test]
+    fn repeat_edge_padding() {
+        let (w, r, g) = ([255, 255, 255, 255], [255, 0, 0, 255], [0, 255, 0, 255]);
+        let png = crate::testutil::rgba(2, 3, &[w, w, w, w, r, g]);
+        let scan = |padding_mode| -> Vec<[u8; 4]> {
+            RgbaColorIter::new(&png, FontMode::ByteColumn)
+                .unwrap()
+                .with_padding(padding_mode)
+                .map(|px| [px.r, px.g, px.b, px.a])
+                .collect()
+        };
+        let column = |edge| [w, w, edge, edge, edge, edge, edge, edge];
+        assert_eq!(
+            scan(PaddingMode::RepeatEdge),
+            [column(r), column(g)].concat()
+        );
+
+        let zero = scan(PaddingMode::Zero);
+        assert_eq!(zero[..3], [w, w, r]);
+        assert_eq!(zero[3..8], [[0; 4]; 5]);
     }
 }
@@ -0,0 +1,588 @@
+//! Index tables to locate glyphs inside a generated font.
+
+use std::{
+    io::{self, Write},
+    ops::Range,
+    str::FromStr,
+};
+
+use crate::{Compression, FontGenerator, FontReport, GenerationError};
+
+/// A single entry of a [`GlyphIndex`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IndexEntry {
+    /// Offset of the first byte of the glyph inside the font
+    pub offset: u32,
+    /// The width of the glyph in pixels
+    pub width: u16,
+    /// The height of the glyph in pixels
+    pub height: u16,
+    /// The CRC-32 of the bytes of the glyph, only serialized if the index stores CRCs
+    pub crc: u32,
+    /// How the bytes of the glyph are stored, only serialized if the index stores compression
+    /// flags
+    pub compression: Compression,
+}
+
+/// Table to locate every glyph inside a font.
+///
+/// The entries are in the order the glyphs were generated in, which is not necessarily the order
+/// they are stored in the font. Every glyph ends where the glyph with the next larger offset
+/// starts.
+///
+/// The table is stored as little endian entries of 8 bytes each: the offset as `u32` followed by
+/// the width and height as `u16`. If the index stores CRCs every entry is followed by the CRC-32
+/// of the glyph as `u32`, which makes an entry 12 bytes long. If the index stores compression
+/// flags every entry ends with the [`Compression::flag`] of the glyph as single byte.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GlyphIndex {
+    /// The entries in the order the glyphs were generated in
+    pub entries: Vec<IndexEntry>,
+    /// Wether the CRC of every glyph is stored in the serialized table
+    pub crc: bool,
+    /// Wether the compression flag of every glyph is stored in the serialized table
+    pub compression: bool,
+}
+
+impl GlyphIndex {
+    /// The size of a single serialized entry in bytes
+    pub const ENTRY_SIZE: usize = 8;
+    /// The size of a single serialized entry including the CRC in bytes
+    pub const CRC_ENTRY_SIZE: usize = 12;
+
+    /// Build the index for the glyphs of a generation run
+    ///
+    /// # Errors
+    /// An error is returned when an offset or dimension doesn't fit the index entry.
+    pub fn from_report(report: &FontReport) -> crate::Result<Self> {
+        let entries = report
+            .glyphs
+            .iter()
+            .map(|glyph| {
+                Ok(IndexEntry {
+                    offset: index_value("offset", glyph.offset)?,
+                    width: index_value("width", glyph.width)?,
+                    height: index_value("height", glyph.height)?,
+                    crc: glyph.crc,
+                    compression: glyph.compression,
+                })
+            })
+            .collect::<crate::Result<_>>()?;
+
+        Ok(Self {
+            entries,
+            ..Self::default()
+        })
+    }
+
+    /// Set wether the CRC of every glyph is stored in the serialized table
+    #[must_use]
+    pub fn with_crc(mut self, crc: bool) -> Self {
+        self.crc = crc;
+        self
+    }
+
+    /// Set wether the compression flag of every glyph is stored in the serialized table
+    #[must_use]
+    pub fn with_compression(mut self, compression: bool) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    /// The size of a single serialized entry of this index in bytes
+    pub fn entry_size(&self) -> usize {
+        let size = if self.crc {
+            Self::CRC_ENTRY_SIZE
+        } else {
+            Self::ENTRY_SIZE
+        };
+        size.saturating_add(usize::from(self.compression))
+    }
+
+    /// Parse a serialized index table, `crc` tells wether the table stores CRCs
+    ///
+    /// # Errors
+    /// An error is returned when the length of `bytes` is not a multiple of the entry size.
+    pub fn parse(bytes: &[u8], crc: bool) -> crate::Result<Self> {
+        Self::default().with_crc(crc).read(bytes)
+    }
+
+    /// Parse a serialized index table stored with the CRCs and compression flags of this index,
+    /// the entries of this index are ignored.
+    ///
+    /// # Errors
+    /// An error is returned when the length of `bytes` is not a multiple of the entry size or a
+    /// compression flag is unknown.
+    pub fn read(&self, bytes: &[u8]) -> crate::Result<Self> {
+        if !bytes.len().is_multiple_of(self.entry_size()) {
+            return Err(GenerationError::MalformedIndex(bytes.len()));
+        }
+
+        let crc_len = if self.crc { 4 } else { 0 };
+        let entries = bytes
+            .chunks_exact(self.entry_size())
+            .map(|e| {
+                let compression = match e.get(Self::ENTRY_SIZE + crc_len) {
+                    Some(&flag) if self.compression => Compression::from_flag(flag)
+                        .ok_or(GenerationError::MalformedIndex(bytes.len()))?,
+                    _ => Compression::None,
+                };
+                Ok(IndexEntry {
+                    offset: u32::from_le_bytes([e[0], e[1], e[2], e[3]]),
+                    width: u16::from_le_bytes([e[4], e[5]]),
+                    height: u16::from_le_bytes([e[6], e[7]]),
+                    crc: match e.get(8..12) {
+                        Some(&[a, b, c, d]) if self.crc => u32::from_le_bytes([a, b, c, d]),
+                        _ => 0,
+                    },
+                    compression,
+                })
+            })
+            .collect::<crate::Result<_>>()?;
+
+        Ok(Self {
+            entries,
+            ..self.clone()
+        })
+    }
+
+    /// Write the serialized index table
+    ///
+    /// # Errors
+    /// An error is returned when writing to `out` fails.
+    pub fn write(&self, out: &mut impl Write) -> io::Result<()> {
+        for entry in &self.entries {
+            out.write_all(&entry.offset.to_le_bytes())?;
+            out.write_all(&entry.width.to_le_bytes())?;
+            out.write_all(&entry.height.to_le_bytes())?;
+            if self.crc {
+                out.write_all(&entry.crc.to_le_bytes())?;
+            }
+            if self.compression {
+                out.write_all(&[entry.compression.flag()])?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Write the font together with the index table placed at `position`.
+    ///
+    /// The offsets of the table are relative to the start of the written data. With
+    /// [`IndexPosition::Start`] the table comes first and every offset is shifted by its size.
+    /// With [`IndexPosition::End`] the table follows the font and is itself followed by its
+    /// offset as little endian `u32`, see [`Self::parse_trailing`].
+    ///
+    /// # Errors
+    /// An error is returned when a shifted offset doesn't fit the index entry or writing to
+    /// `out` fails.
+    pub fn write_embedded(
+        &self,
+        font: &[u8],
+        position: IndexPosition,
+        out: &mut impl Write,
+    ) -> crate::Result<()> {
+        match position {
+            IndexPosition::Start => {
+                let table_len = self.entries.len().saturating_mul(self.entry_size());
+                let shift: u32 = index_value("offset", table_len)?;
+                let entries = self
+                    .entries
+                    .iter()
+                    .map(|e| {
+                        let offset = e.offset.checked_add(shift).ok_or(
+                            GenerationError::IndexValueTooLarge {
+                                field: "offset",
+                                value: table_len,
+                            },
+                        )?;
+                        Ok(IndexEntry { offset, ..*e })
+                    })
+                    .collect::<crate::Result<_>>()?;
+                let shifted = Self {
+                    entries,
+                    ..self.clone()
+                };
+                shifted.write(out)?;
+                out.write_all(font)?;
+            }
+            IndexPosition::End => {
+                let pointer: u32 = index_value("offset", font.len())?;
+                out.write_all(font)?;
+                self.write(out)?;
+                out.write_all(&pointer.to_le_bytes())?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Parse the index table at the end of data written with [`IndexPosition::End`], `crc`
+    /// tells wether the table stores CRCs. Returns the index and the length of the font
+    /// preceding the table.
+    ///
+    /// # Errors
+    /// An error is returned when the footer doesn't point to a table of complete entries.
+    pub fn parse_trailing(bytes: &[u8], crc: bool) -> crate::Result<(Self, usize)> {
+        let malformed = || GenerationError::MalformedIndex(bytes.len());
+        let footer = bytes.len().checked_sub(4).ok_or_else(malformed)?;
+        let pointer = match bytes.get(footer..) {
+            Some(&[a, b, c, d]) => u32::from_le_bytes([a, b, c, d]),
+            _ => return Err(malformed()),
+        };
+        let font_len = usize::try_from(pointer)
+            .ok()
+            .filter(|&start| start <= footer)
+            .ok_or_else(malformed)?;
+        let table = bytes.get(font_len..footer).unwrap_or_default();
+        Ok((Self::parse(table, crc)?, font_len))
+    }
+
+    /// The byte range of the `n`th glyph in a font of `font_len` bytes
+    pub fn glyph_range(&self, n: usize, font_len: usize) -> Option<Range<usize>> {
+        let start = self.entries.get(n)?.offset;
+        let end = self
+            .entries
+            .iter()
+            .map(|e| e.offset)
+            .filter(|&offset| offset > start)
+            .min()
+            .map_or(Some(font_len), |end| usize::try_from(end).ok())?;
+        let start = usize::try_from(start).ok()?;
+        (start <= end && end <= font_len).then_some(start..end)
+    }
+
+    /// The glyph numbers in the order the glyphs are stored in the font
+    pub fn storage_order(&self) -> Vec<usize> {
+        let mut order: Vec<_> = (0..self.entries.len()).collect();
+        order.sort_by_key(|&n| self.entries.get(n).map(|e| e.offset));
+        order
+    }
+}
+
+/// Where the index table is placed when it is embedded in the font
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum IndexPosition {
+    /// The table is written before the glyphs
+    #[default]
+    Start,
+    /// The table is appended after the glyphs, followed by its offset as `u32`
+    End,
+}
+
+impl FromStr for IndexPosition {
+    type Err = IndexPositionParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "start" => Ok(Self::Start),
+            "end" => Ok(Self::End),
+            s => Err(IndexPositionParseError(s.to_owned())),
+        }
+    }
+}
+
+/// An error that occurs when trying to parse an index position that doesn't exist
+#[derive(Debug, Clone, thiserror::Error)]
+#[error("Unsupported index position: {0}")]
+pub struct IndexPositionParseError(String);
+
+/// The CRC-32 (IEEE 802.3) of `bytes`
+pub(crate) fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = u32::MAX;
+    for &byte in bytes {
+        crc ^= u32::from(byte);
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xEDB8_8320 & mask);
+        }
+    }
+    !crc
+}
+
+/// Convert a value for storage in an index entry
+fn index_value<T: TryFrom<usize>>(field: &'static str, value: usize) -> crate::Result<T> {
+    T::try_from(value).map_err(|_| GenerationError::IndexValueTooLarge { field, value })
+}
+
+/// Regenerate single glyphs of a font generated from a directory in place.
+///
+/// Every change is the number of the glyph in the report of the generation run and the png data
+/// it is regenerated from. The glyph is packed like [`generate_dir`](crate::generate_dir) packs
+/// it, including glyph header and compression, and has to have exactly the size of the glyph it
+/// replaces. Page padding, end sentinel and all other glyphs stay untouched.
+///
+/// # Errors
+/// An error is returned when a glyph is not part of the report or the font, the png data can
+/// not be decoded or the size of a regenerated glyph differs.
+pub fn patch_font<'a>(
+    existing: &mut [u8],
+    report: &FontReport,
+    changes: impl IntoIterator<Item = (usize, &'a [u8])>,
+    generator: &FontGenerator,
+) -> crate::Result<()> {
+    for (glyph, data) in changes {
+        let record = report
+            .glyphs
+            .get(glyph)
+            .ok_or(GenerationError::UnknownGlyph(glyph))?;
+        let range = record.offset..record.offset.saturating_add(record.byte_len);
+        if range.end > existing.len() {
+            return Err(GenerationError::UnknownGlyph(glyph));
+        }
+        let (_, _, patched) = crate::dir::decode_packed(&record.path, data, generator)?;
+        if patched.len() != range.len() {
+            return Err(GenerationError::PatchSizeMismatch {
+                glyph,
+                expected: range.len(),
+                found: patched.len(),
+            });
+        }
+        if let Some(region) = existing.get_mut(range) {
+            region.copy_from_slice(&patched);
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use super::*;
+    use crate::{generate_dir, testutil};
+
+    /// Only the bytes of the changed glyph differ after patching
+    #[test]
+    fn patch_single_glyph() {
+        let dir = tempfile::tempdir().unwrap();
+        let glyphs = [
+            ["#.......", "........"],
+            ["..#.....", "......#."],
+            ["########", "#......#"],
+        ];
+        for (i, rows) in glyphs.iter().enumerate() {
+            fs::write(dir.path().join(format!("{i}.png")), testutil::picture(rows)).unwrap();
+        }
+
+        let generator = FontGenerator::new();
+        let mut font = Vec::new();
+        let report = generate_dir(dir.path(), &generator, &mut font).unwrap();
+        let index = GlyphIndex::from_report(&report).unwrap();
+        let mut serialized = Vec::new();
+        index.write(&mut serialized).unwrap();
+        let parsed = GlyphIndex::parse(&serialized, false).unwrap();
+        let layout = |i: &GlyphIndex| -> Vec<_> {
+            i.entries
+                .iter()
+                .map(|e| (e.offset, e.width, e.height))
+                .collect()
+        };
+        assert_eq!(layout(&parsed), layout(&index));
+
+        let original = font.clone();
+        let changed = testutil::picture(&["...##...", "...##..."]);
+        patch_font(&mut font, &report, [(1, changed.as_slice())], &generator).unwrap();
+
+        assert_eq!(font[..2], original[..2]);
+        assert_eq!(font[2..4], [0x18, 0x18]);
+        assert_ne!(font[2..4], original[2..4]);
+        assert_eq!(font[4..], original[4..]);
+
+        let wrong_size = testutil::picture(&["#......."]);
+        assert!(matches!(
+            patch_font(&mut font, &report, [(0, wrong_size.as_slice())], &generator),
+            Err(GenerationError::PatchSizeMismatch { glyph: 0, .. })
+        ));
+    }
+
+    /// Patching keeps the glyph header, the page padding and the end sentinel
+    #[test]
+    fn patch_aligned_glyph() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("0.png"), testutil::picture(&["#......."])).unwrap();
+        fs::write(dir.path().join("1.png"), testutil::picture(&["......#."])).unwrap();
+
+        let generator = FontGenerator::new()
+            .with_glyph_header(true)
+            .with_glyph_page_align(8)
+            .with_end_sentinel("0xA5:2".parse().unwrap());
+        let mut font = Vec::new();
+        let report = generate_dir(dir.path(), &generator, &mut font).unwrap();
+        assert_eq!(font.len(), 8 + 4 + 2);
+
+        let original = font.clone();
+        let changed = testutil::picture(&["...#...."]);
+        patch_font(&mut font, &report, [(1, changed.as_slice())], &generator).unwrap();
+
+        assert_eq!(font[..11], original[..11]);
+        assert_eq!(font[11], 0x10);
+        assert_eq!(font[12..], original[12..]);
+        assert_eq!(font[12..], [0xA5, 0xA5]);
+    }
+
+    /// Glyphs of different sizes get little endian entries of 8 bytes with their own offset
+    #[test]
+    fn variable_size_table() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(
+            dir.path().join("a.png"),
+            testutil::picture(&["#.......", "........"]),
+        )
+        .unwrap();
+        fs::write(
+            dir.path().join("b.png"),
+            testutil::picture(&["#..........#", "............", "#..........#"]),
+        )
+        .unwrap();
+
+        let mut font = Vec::new();
+        let report = generate_dir(dir.path(), &FontGenerator::new(), &mut font).unwrap();
+        assert_eq!(font.len(), 2 + 5);
+
+        let mut table = Vec::new();
+        GlyphIndex::from_report(&report)
+            .unwrap()
+            .write(&mut table)
+            .unwrap();
+        assert_eq!(
+            table,
+            [
+                0, 0, 0, 0, 8, 0, 2, 0, //
+                2, 0, 0, 0, 12, 0, 3, 0,
+            ]
+        );
+    }
+
+    /// The footer points to the trailing index and the leading index is shifted past itself
+    #[test]
+    fn embedded_index() {
+        let dir = tempfile::tempdir().unwrap();
+        let glyphs = [["#.......", "........"], ["########", "#......#"]];
+        for (i, rows) in glyphs.iter().enumerate() {
+            fs::write(dir.path().join(format!("{i}.png")), testutil::picture(rows)).unwrap();
+        }
+
+        let mut font = Vec::new();
+        let report = generate_dir(dir.path(), &FontGenerator::new(), &mut font).unwrap();
+        let index = GlyphIndex::from_report(&report).unwrap().with_crc(true);
+
+        let mut trailing = Vec::new();
+        index
+            .write_embedded(&font, IndexPosition::End, &mut trailing)
+            .unwrap();
+        assert_eq!(
+            trailing.len(),
+            font.len() + 2 * GlyphIndex::CRC_ENTRY_SIZE + 4
+        );
+        assert_eq!(trailing[trailing.len() - 4..], [4, 0, 0, 0]);
+        let (parsed, font_len) = GlyphIndex::parse_trailing(&trailing, true).unwrap();
+        assert_eq!(font_len, font.len());
+        assert_eq!(parsed, index);
+        let range = parsed.glyph_range(1, font_len).unwrap();
+        assert_eq!(trailing[range], [0xFF, 0x81]);
+
+        let mut leading = Vec::new();
+        index
+            .write_embedded(&font, IndexPosition::Start, &mut leading)
+            .unwrap();
+        let table_len = 2 * GlyphIndex::CRC_ENTRY_SIZE;
+        let parsed = GlyphIndex::parse(&leading[..table_len], true).unwrap();
+        let range = parsed.glyph_range(1, leading.len()).unwrap();
+        assert_eq!(range, table_len + 2..table_len + 4);
+        assert_eq!(leading[range], [0xFF, 0x81]);
+
+        assert!(matches!(
+            GlyphIndex::parse_trailing(&[0, 0, 9, 0, 0, 0], false),
+            Err(GenerationError::MalformedIndex(6))
+        ));
+    }
+
+    /// Sparse glyphs are stored run length encoded, dense glyphs as they are
+    #[test]
+    fn per_glyph_compression() {
+        let dir = tempfile::tempdir().unwrap();
+        let sparse = vec!["........"; 16];
+        let dense: Vec<_> = (0..16)
+            .map(|y| if y % 2 == 0 { "#.#.#.#." } else { ".#.#.#.#" })
+            .collect();
+        fs::write(dir.path().join("0.png"), testutil::picture(&sparse)).unwrap();
+        fs::write(dir.path().join("1.png"), testutil::picture(&dense)).unwrap();
+
+        let mut plain = Vec::new();
+        generate_dir(dir.path(), &FontGenerator::new(), &mut plain).unwrap();
+        let mut font = Vec::new();
+        let generator = FontGenerator::new().with_compress(true);
+        let report = generate_dir(dir.path(), &generator, &mut font).unwrap();
+        assert_eq!(font.len(), 2 + 16);
+
+        let index = GlyphIndex::from_report(&report)
+            .unwrap()
+            .with_compression(true);
+        let mut serialized = Vec::new();
+        index.write(&mut serialized).unwrap();
+        assert_eq!(serialized.len(), 2 * (GlyphIndex::ENTRY_SIZE + 1));
+        assert_eq!([serialized[8], serialized[17]], [1, 0]);
+
+        let parsed = index.read(&serialized).unwrap();
+        let flags: Vec<_> = parsed.entries.iter().map(|e| e.compression).collect();
+        assert_eq!(flags, [Compression::Rle, Compression::None]);
+        for (n, entry) in parsed.entries.iter().enumerate() {
+            let stored = &font[parsed.glyph_range(n, font.len()).unwrap()];
+            let restored = entry.compression.decompress(stored).unwrap();
+            assert_eq!(restored, plain[n * 16..(n + 1) * 16]);
+        }
+    }
+
+    /// Table driven CRC-32 as reference for the bitwise implementation
+    fn reference_crc32(bytes: &[u8]) -> u32 {
+        let table: Vec<u32> = (0..256u32)
+            .map(|n| {
+                (0..8).fold(n, |c, _| {
+                    if c & 1 == 1 {
+                        0xEDB8_8320 ^ (c >> 1)
+                    } else {
+                        c >> 1
+                    }
+                })
+            })
+            .collect();
+        !bytes.iter().fold(u32::MAX, |c, &b| {
+            table[((c ^ u32::from(b)) & 0xFF) as usize] ^ (c >> 8)
+        })
+    }
+
+    /// Every stored CRC matches the bytes of its glyph
+    #[test]
+    fn glyph_crc() {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+
+        let dir = tempfile::tempdir().unwrap();
+        let glyphs = [
+            ["#.......", "........", "#......."],
+            ["..#.....", "......#.", "########"],
+            ["########", "#......#", "........"],
+        ];
+        for (i, rows) in glyphs.iter().enumerate() {
+            fs::write(dir.path().join(format!("{i}.png")), testutil::picture(rows)).unwrap();
+        }
+
+        let mut font = Vec::new();
+        let report = generate_dir(
+            dir.path(),
+            &FontGenerator::new().with_optimize_order(true),
+            &mut font,
+        )
+        .unwrap();
+        let index = GlyphIndex::from_report(&report).unwrap().with_crc(true);
+        let mut serialized = Vec::new();
+        index.write(&mut serialized).unwrap();
+        assert_eq!(serialized.len(), glyphs.len() * GlyphIndex::CRC_ENTRY_SIZE);
+
+        let parsed = GlyphIndex::parse(&serialized, true).unwrap();
+        assert_eq!(parsed, index);
+        for n in 0..glyphs.len() {
+            let range = parsed.glyph_range(n, font.len()).unwrap();
+            assert_eq!(parsed.entries[n].crc, reference_crc32(&font[range]));
+        }
+    }
+}
@@ -1,67 +1,493 @@
 //! Utility to create simple font files for embedded devices.
+//!
+//! Without the default `std` feature the crate is `no_std` and only needs `alloc`, it keeps the
+//! decoding of png images and the packing of pixels into a [`ByteSink`].
 
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+/// Display the parse errors, whose messages come from thiserror, without `std` as the rejected
+/// string
+macro_rules! display_without_std {
+    ($($error:ident),+) => {$(
+        #[cfg(not(feature = "std"))]
+        impl core::fmt::Display for $error {
+            fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+                write!(f, "Unsupported value: {}", self.0)
+            }
+        }
+    )+};
+}
+
+#[cfg(feature = "async")]
+mod asynchronous;
+#[cfg(feature = "std")]
+mod classifier;
+#[cfg(feature = "std")]
+mod clipboard;
+#[cfg(feature = "std")]
+mod codepoint;
+#[cfg(feature = "std")]
+mod compare;
+#[cfg(feature = "std")]
+mod compress;
+#[cfg(feature = "std")]
+mod delta;
+#[cfg(feature = "std")]
+mod dir;
+mod dither;
+#[cfg(feature = "std")]
+mod dump;
+#[cfg(feature = "std")]
+mod egmono;
 mod error;
+#[cfg(feature = "std")]
+mod flush;
+#[cfg(feature = "std")]
+mod generator;
+#[cfg(feature = "std")]
+mod glyph;
+#[cfg(feature = "std")]
+mod header;
+#[cfg(feature = "image-backend")]
+mod imagebackend;
 mod imagedecode;
+#[cfg(feature = "std")]
+mod index;
+mod orientation;
+mod pack;
+#[cfg(feature = "std")]
+mod palette;
+#[cfg(feature = "std")]
+mod pattern;
+#[cfg(feature = "std")]
+mod report;
+#[cfg(feature = "std")]
+mod settings;
+#[cfg(feature = "std")]
+mod sheet;
+mod sink;
+#[cfg(feature = "svg")]
+mod svg;
+#[cfg(test)]
+#[cfg_attr(not(feature = "std"), allow(dead_code))]
+mod testutil;
 
+#[cfg(feature = "async")]
+pub use asynchronous::{generate_monochromatic_async, YIELD_INTERVAL};
+#[cfg(feature = "std")]
+pub use classifier::{generate_monochromatic_with, DefaultClassifier, OnColor, PixelClassifier};
+#[cfg(feature = "clipboard")]
+pub use clipboard::SystemClipboard;
+#[cfg(feature = "std")]
+pub use clipboard::{dump_to_clipboard, ClipboardBackend};
+#[cfg(feature = "std")]
+pub use codepoint::{
+    codepoint_from_filename, codepoint_range_from_filename, CodepointRanges,
+    CodepointRangesParseError,
+};
+#[cfg(feature = "std")]
+pub use compare::{compare_fonts, BitPosition, DiffReport};
+#[cfg(feature = "std")]
+pub use compress::{rle_decode, rle_encode, Compression};
+#[cfg(feature = "std")]
+pub use delta::{apply_patch, make_patch, parse_patch, write_patch, PatchRecord};
+#[cfg(feature = "std")]
+pub use dir::{
+    generate_data, generate_dir, generate_dir_split, generate_dir_with_progress, generate_file,
+    generate_reader, pack_bins, Progress,
+};
+pub use dither::{Dither, DitherParseError};
+#[cfg(feature = "std")]
+pub use dump::{
+    dump, dump_with_options, parse_dump, verify_dump, DumpFormat, DumpFormatParseError, DumpOptions,
+};
+#[cfg(feature = "std")]
+pub use egmono::{generate_eg_mono, generate_eg_mono_tiled};
 pub use error::GenerationError;
+#[cfg(feature = "std")]
+pub use flush::{FlushPolicy, FlushPolicyParseError};
+#[cfg(feature = "std")]
+pub use generator::{EndSentinel, EndSentinelParseError, FontGenerator};
+#[cfg(feature = "std")]
+pub use glyph::{BoundingBox, Glyph, GlyphMetrics};
+#[cfg(feature = "std")]
+pub use header::{
+    generate_dir_with_header, generate_monochromatic_with_header, FontHeader, FONT_HEADER_VERSION,
+    FONT_MAGIC,
+};
+pub use imagedecode::{
+    inspect_png, ColorSpace, GlyphInfo, MonochromaticColorIter, Rgba, RgbaColorIter,
+    RgbaParseError, SupportedColorSpace,
+};
+#[cfg(feature = "std")]
+pub use index::{patch_font, GlyphIndex, IndexEntry, IndexPosition, IndexPositionParseError};
+pub use orientation::Orientation;
+pub use pack::{pack_monochromatic, BitRunEndianness, BitRunEndiannessParseError};
+#[cfg(feature = "std")]
+pub use pattern::{generate_test_pattern, TestPattern, TestPatternParseError};
+#[cfg(feature = "std")]
+pub use report::{FontReport, GlyphRecord};
+#[cfg(feature = "std")]
+pub use settings::{DirSettings, DirSettingsParseError, SETTINGS_FILE_NAME};
+#[cfg(feature = "std")]
+pub use sheet::{generate_atlas, SheetLayout};
+pub use sink::ByteSink;
+#[cfg(feature = "std")]
+pub use sink::WriteSink;
+#[cfg(feature = "svg")]
+pub use svg::generate_from_svg;
 
-use std::{io::Write, str::FromStr};
+use alloc::{borrow::ToOwned, string::String, vec::Vec};
+use core::{fmt, str::FromStr};
+#[cfg(feature = "std")]
+use std::io::{Read, Write};
 
 /// Result type that uses this crates error by default
-pub type Result<T, E = GenerationError> = std::result::Result<T, E>;
+pub type Result<T, E = GenerationError> = core::result::Result<T, E>;
 
-/// Generate a single monochromatic font
+/// Generate a single monochromatic font, returns the number of bytes written.
+///
+/// If the number of pixels is not a multiple of 8 the last byte is padded with off pixels, which
+/// take its least significant bits with [`BitFlow::Mtl`] and its most significant bits with
+/// [`BitFlow::Ltm`].
 ///
 /// # Errors
 /// An error is returned when the given image data can not be decoded as png or writing to the
 /// `out` writer fails.
+#[cfg(feature = "std")]
 pub fn generate_monochromatic(
     data: &[u8],
     font_mode: FontMode,
     bit_flow: BitFlow,
     out: &mut impl Write,
+) -> crate::Result<usize> {
+    generate_monochromatic_with_threshold(
+        data,
+        font_mode,
+        bit_flow,
+        imagedecode::DEFAULT_THRESHOLD,
+        out,
+    )
+}
+
+/// Generate a single monochromatic font from png data read from `reader`, returns the number of
+/// bytes written.
+///
+/// The png decoder works on a byte slice, so the reader is read to its end into a single buffer
+/// that is dropped once the image is decoded. Reading a file this way avoids keeping a second
+/// copy of it while the font is packed.
+///
+/// # Errors
+/// An error is returned when reading fails, the data can not be decoded as png or writing to the
+/// `out` writer fails.
+#[cfg(feature = "std")]
+pub fn generate_monochromatic_from_reader<R: Read>(
+    reader: R,
+    font_mode: FontMode,
+    bit_flow: BitFlow,
+    out: &mut impl Write,
+) -> crate::Result<usize> {
+    FontGenerator::new()
+        .with_mode(font_mode)
+        .with_bit_flow(bit_flow)
+        .generate_from_reader(reader, out)
+}
+
+/// Generate a single monochromatic font into a new buffer, see [`generate_monochromatic`].
+///
+/// # Errors
+/// An error is returned when the given image data can not be decoded as png. Writing to the
+/// buffer can not fail.
+#[cfg(feature = "std")]
+pub fn generate_monochromatic_to_vec(
+    data: &[u8],
+    font_mode: FontMode,
+    bit_flow: BitFlow,
+) -> crate::Result<Vec<u8>> {
+    let mut out = Vec::new();
+    generate_monochromatic(data, font_mode, bit_flow, &mut out)?;
+    Ok(out)
+}
+
+/// Generate a single monochromatic font, pixels are on if they are at least as opaque as the
+/// threshold and, in images without alpha, any color component is below the threshold. Returns
+/// the number of bytes written, including the padded last byte.
+///
+/// This is a shorthand for a [`FontGenerator`] with the given mode, bit flow and threshold, use
+/// the generator for any further options.
+///
+/// # Errors
+/// An error is returned when the given image data can not be decoded as png or writing to the
+/// `out` writer fails.
+#[cfg(feature = "std")]
+pub fn generate_monochromatic_with_threshold(
+    data: &[u8],
+    font_mode: FontMode,
+    bit_flow: BitFlow,
+    threshold: u8,
+    out: &mut impl Write,
+) -> crate::Result<usize> {
+    FontGenerator::new()
+        .with_mode(font_mode)
+        .with_bit_flow(bit_flow)
+        .with_threshold(threshold)
+        .generate(data, out)
+}
+
+/// Decode a single monochromatic font back into a grayscale png image of `width` x `height`
+/// pixels, the inverse of [`generate_monochromatic`]. On pixels are black, off pixels white.
+///
+/// # Errors
+/// An error is returned when the length of `data` doesn't match the packed size of a glyph of
+/// the given dimensions in the font mode or the size overflows.
+pub fn decode_monochromatic(
+    data: &[u8],
+    width: usize,
+    height: usize,
+    font_mode: FontMode,
+    bit_flow: BitFlow,
+) -> crate::Result<Vec<u8>> {
+    let expected = pack::packed_len(font_mode, width, height)?;
+    if data.len() != expected {
+        return Err(GenerationError::PixelBufferSize {
+            expected,
+            found: data.len(),
+        });
+    }
+
+    let pixels: Vec<u8> = pack::unpack_monochromatic(data, width, height, font_mode, bit_flow)
+        .into_iter()
+        .map(|on| if on { 0 } else { u8::MAX })
+        .collect();
+    let options = zune_png::zune_core::options::EncoderOptions::default()
+        .set_width(width)
+        .set_height(height)
+        .set_depth(zune_png::zune_core::bit_depth::BitDepth::Eight)
+        .set_colorspace(imagedecode::ColorSpace::Luma);
+    Ok(zune_png::PngEncoder::new(&pixels, options).encode())
+}
+
+/// Generate a single glyph as binary portable bitmap (P4) with its own header for netpbm
+/// tooling, with the options of the generator. Glyphs written one after another form a
+/// concatenated PBM stream.
+///
+/// # Errors
+/// An error is returned when the given image data can not be decoded as png or writing to the
+/// `out` writer fails.
+#[cfg(feature = "std")]
+pub fn generate_pbm(
+    data: &[u8],
+    generator: &FontGenerator,
+    out: &mut impl Write,
 ) -> crate::Result<()> {
-    let decoded = imagedecode::MonochromaticColorIter::new(data, font_mode)?;
-    let mut cur_byte = 0;
-    let mut i = 7u8;
-
-    for pix in decoded {
-        #[allow(clippy::arithmetic_side_effects)] // Wrongly flagged already fixed in 1.70
-        {
-            match bit_flow {
-                BitFlow::Mtl => {
-                    cur_byte <<= 1u32;
-                    cur_byte |= u8::from(pix);
-                }
-                BitFlow::Ltm => {
-                    cur_byte >>= 1u32;
-                    cur_byte |= u8::from(pix) << 7i32;
-                }
-            }
-        }
-        if let Some(v) = i.checked_sub(1) {
-            i = v;
+    generator
+        .glyph(data)?
+        .write_pbm(out)
+        .map_err(GenerationError::OutputWriterError)
+}
+
+/// Generate a single grayscale font with `bits_per_pixel` bits for every pixel, see
+/// [`generate_grayscale_with_alpha`].
+///
+/// # Errors
+/// An error is returned when the given image data can not be decoded as png, the number of bits
+/// per pixel is not supported or writing to the `out` writer fails.
+#[cfg(feature = "std")]
+pub fn generate_grayscale(
+    data: &[u8],
+    font_mode: FontMode,
+    bits_per_pixel: u8,
+    bit_flow: BitFlow,
+    out: &mut impl Write,
+) -> crate::Result<()> {
+    generate_grayscale_with_alpha(
+        data,
+        font_mode,
+        bits_per_pixel,
+        AlphaMode::default(),
+        bit_flow,
+        out,
+    )
+}
+
+/// Generate a single grayscale font with `bits_per_pixel` bits for every pixel.
+///
+/// The level of a pixel is its amount of ink, the darkness of the pixel or its opacity in images
+/// with alpha depending on the alpha mode. `bits_per_pixel` has to be 1, 2, 4 or 8 so that every
+/// byte holds complete pixels, a last byte that is not completely filled is padded with pixels
+/// without ink like in monochromatic fonts.
+///
+/// # Errors
+/// An error is returned when the given image data can not be decoded as png, the number of bits
+/// per pixel is not supported or writing to the `out` writer fails.
+#[cfg(feature = "std")]
+pub fn generate_grayscale_with_alpha(
+    data: &[u8],
+    font_mode: FontMode,
+    bits_per_pixel: u8,
+    alpha_mode: AlphaMode,
+    bit_flow: BitFlow,
+    out: &mut impl Write,
+) -> crate::Result<()> {
+    if !matches!(bits_per_pixel, 1 | 2 | 4 | 8) {
+        return Err(GenerationError::UnsupportedBitsPerPixel(bits_per_pixel));
+    }
+    let mut decoded = imagedecode::RgbaColorIter::new(data, font_mode)?;
+    let mut levels = Vec::new();
+    while let Some(rgba) = decoded.next() {
+        levels.push(decoded.ink_level(rgba, alpha_mode));
+    }
+    pack::pack_grayscale(levels.into_iter(), bits_per_pixel, bit_flow, out)?;
+    Ok(())
+}
+
+/// Generate a single glyph from a buffer of RGBA pixels, stored left to right, top to bottom,
+/// with the options of the generator. Pixels are on if they are more than half opaque.
+///
+/// Returns the number of bytes written.
+///
+/// # Errors
+/// An error is returned when the size of the buffer doesn't match the dimensions or writing to
+/// the `out` writer fails.
+#[cfg(feature = "std")]
+pub fn generate_from_pixels(
+    rgba: &[u8],
+    width: usize,
+    height: usize,
+    generator: &FontGenerator,
+    out: &mut impl Write,
+) -> crate::Result<usize> {
+    let glyph = generator.glyph_from_pixels(rgba, width, height)?;
+    generator.write_glyph(&glyph, out)
+}
+
+/// Generate the pixel data for WS2812-style RGB led matrices.
+///
+/// Each pixel is written as 3 bytes in the order green, red, blue. Pixels that are off in a
+/// monochromatic font are written as `[0, 0, 0]`.
+///
+/// # Errors
+/// An error is returned when the given image data can not be decoded as png or writing to the
+/// `out` writer fails.
+#[cfg(feature = "std")]
+pub fn generate_ws2812(
+    data: &[u8],
+    font_mode: FontMode,
+    out: &mut impl Write,
+) -> crate::Result<()> {
+    let mut decoded = imagedecode::RgbaColorIter::new(data, font_mode)?;
+    while let Some(rgba) = decoded.next() {
+        let grb = if decoded.is_on(rgba) {
+            rgba.grb()
         } else {
-            out.write(&[cur_byte])
-                .map_err(GenerationError::OutputWriterError)?;
-            i = 7;
-            cur_byte = 0;
-        }
+            [0; 3]
+        };
+        out.write_all(&grb)
+            .map_err(GenerationError::OutputWriterError)?;
+    }
+
+    Ok(())
+}
+
+/// Generate the pixel data for WS2812-style RGB led matrices with the colors reduced to a
+/// palette of at most `palette_size` colors, see [`generate_ws2812`].
+///
+/// The palette is computed with median cut over the colors of all on pixels, every on pixel is
+/// replaced by the closest palette color.
+///
+/// # Errors
+/// An error is returned when the given image data can not be decoded as png or writing to the
+/// `out` writer fails.
+#[cfg(feature = "std")]
+pub fn generate_ws2812_with_palette(
+    data: &[u8],
+    font_mode: FontMode,
+    palette_size: usize,
+    out: &mut impl Write,
+) -> crate::Result<()> {
+    let mut decoded = imagedecode::RgbaColorIter::new(data, font_mode)?;
+    let mut pixels = Vec::new();
+    while let Some(rgba) = decoded.next() {
+        pixels.push(decoded.is_on(rgba).then(|| rgba.rgb()));
+    }
+    let colors: Vec<_> = pixels.iter().flatten().copied().collect();
+    let palette = palette::median_cut(&colors, palette_size);
+
+    for pixel in pixels {
+        let grb = match pixel {
+            Some(rgb) => {
+                let [r, g, b] = palette::nearest(&palette, rgb);
+                [g, r, b]
+            }
+            None => [0; 3],
+        };
+        out.write_all(&grb)
+            .map_err(GenerationError::OutputWriterError)?;
     }
 
     Ok(())
 }
 
 /// The mode in which the font should be generated
-#[derive(Debug, Clone, Copy, Default)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
 pub enum FontMode {
     /// The image is read line by line and each pixel is inserted into the resulting font.
     /// There is no alignment.
     #[default]
     Row,
-    // Column,
+    /// The image is read column by column, each column top to bottom and the columns left to
+    /// right. There is no alignment.
+    Column,
     /// Works in Columns of 8, scans the columns left to right and then top to bottom, aligned by 8.
     ByteColumn,
+    /// Works like [`Self::ByteColumn`] in taller columns, for controllers with 16 or 32 pixel
+    /// pages. The height is aligned to the column height.
+    AlignedColumn(ColumnAlign),
+}
+
+impl FontMode {
+    /// The column mode aligned to `align` pixels, an alignment of 8 is [`Self::ByteColumn`].
+    ///
+    /// # Errors
+    /// An error is returned if the alignment is not 8, 16 or 32.
+    pub fn aligned_column(align: u8) -> crate::Result<Self> {
+        match align {
+            8 => Ok(Self::ByteColumn),
+            16 => Ok(Self::AlignedColumn(ColumnAlign::Sixteen)),
+            32 => Ok(Self::AlignedColumn(ColumnAlign::ThirtyTwo)),
+            align => Err(GenerationError::UnsupportedColumnAlign(align)),
+        }
+    }
+
+    /// The height of the columns the image is scanned in, none for the modes without alignment
+    pub fn column_align(self) -> Option<u8> {
+        match self {
+            Self::Row | Self::Column => None,
+            Self::ByteColumn => Some(imagedecode::BYTE_COLUMN_ALIGN),
+            Self::AlignedColumn(align) => Some(align.pixels()),
+        }
+    }
+}
+
+/// The height of the columns of [`FontMode::AlignedColumn`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColumnAlign {
+    /// Columns of 16 pixels
+    Sixteen,
+    /// Columns of 32 pixels
+    ThirtyTwo,
+}
+
+impl ColumnAlign {
+    /// The height of a column in pixels
+    pub fn pixels(self) -> u8 {
+        match self {
+            Self::Sixteen => 16,
+            Self::ThirtyTwo => 32,
+        }
+    }
 }
 
 impl FromStr for FontMode {
@@ -70,20 +496,184 @@ impl FromStr for FontMode {
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s {
             "row" => Ok(Self::Row),
-            // "column" => Ok(Self::Column),
-            "byte-column" | "column-byte" => Ok(Self::ByteColumn),
+            "column" => Ok(Self::Column),
+            "byte-column" | "column-byte" | "column-8" => Ok(Self::ByteColumn),
+            "column-16" => Ok(Self::AlignedColumn(ColumnAlign::Sixteen)),
+            "column-32" => Ok(Self::AlignedColumn(ColumnAlign::ThirtyTwo)),
             _ => Err(FontModeParseError(s.to_owned())),
         }
     }
 }
 
+impl fmt::Display for FontMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Self::Row => f.write_str("row"),
+            Self::Column => f.write_str("column"),
+            Self::ByteColumn => f.write_str("byte-column"),
+            Self::AlignedColumn(align) => write!(f, "column-{}", align.pixels()),
+        }
+    }
+}
+
 /// A font mode was tried to be parsed that doesn't exist
-#[derive(Debug, thiserror::Error)]
-#[error("Unsuported font mode: {0}")]
+#[derive(Debug)]
+#[cfg_attr(
+    feature = "std",
+    derive(thiserror::Error),
+    error("Unsuported font mode: {0}")
+)]
 pub struct FontModeParseError(String);
 
+/// How the padding added for the alignment of a font mode is filled
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PaddingMode {
+    /// All padding pixels are off
+    #[default]
+    Zero,
+    /// All padding pixels are on, with an inverted generator they are written as 0 bits like any
+    /// other on pixel
+    One,
+    /// The padding repeats the last real row of the image to avoid hard cutoffs
+    RepeatEdge,
+}
+
+impl FromStr for PaddingMode {
+    type Err = PaddingModeParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "zero" => Ok(Self::Zero),
+            "one" => Ok(Self::One),
+            "repeat-edge" | "edge" => Ok(Self::RepeatEdge),
+            s => Err(PaddingModeParseError(s.to_owned())),
+        }
+    }
+}
+
+/// A padding mode was tried to be parsed that doesn't exist
+#[derive(Clone, Debug)]
+#[cfg_attr(
+    feature = "std",
+    derive(thiserror::Error),
+    error("Unsupported padding mode: {0}")
+)]
+pub struct PaddingModeParseError(String);
+
+/// How the alpha of semi-transparent pixels is treated in grayscale fonts
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AlphaMode {
+    /// Pixels are either full ink or background, like in monochromatic fonts
+    #[default]
+    Threshold,
+    /// The alpha of a pixel is its level, semi-transparent pixels become intermediate gray levels
+    Modulate,
+}
+
+impl FromStr for AlphaMode {
+    type Err = AlphaModeParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "threshold" => Ok(Self::Threshold),
+            "modulate" => Ok(Self::Modulate),
+            s => Err(AlphaModeParseError(s.to_owned())),
+        }
+    }
+}
+
+/// An alpha mode was tried to be parsed that doesn't exist
+#[derive(Clone, Debug)]
+#[cfg_attr(
+    feature = "std",
+    derive(thiserror::Error),
+    error("Unsupported alpha mode: {0}")
+)]
+pub struct AlphaModeParseError(String);
+
+/// How the color of an opaque pixel without alpha channel decides wether it is on
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LumaMode {
+    /// The pixel is on if any of its color components is below the threshold
+    #[default]
+    AnyChannel,
+    /// The luminance with the weights of Rec. 601, `0.299R + 0.587G + 0.114B`, is compared
+    /// against the threshold
+    Rec601,
+    /// The luminance with the weights of Rec. 709, `0.2126R + 0.7152G + 0.0722B`, is compared
+    /// against the threshold
+    Rec709,
+}
+
+impl LumaMode {
+    /// The weights of the red, green and blue components in ten thousandths, the luminance of a
+    /// luminance table is weighted like Rec. 601 with the any channel mode
+    pub(crate) fn weights(self) -> [u32; 3] {
+        match self {
+            Self::AnyChannel | Self::Rec601 => [2990, 5870, 1140],
+            Self::Rec709 => [2126, 7152, 722],
+        }
+    }
+}
+
+impl FromStr for LumaMode {
+    type Err = LumaModeParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "any" | "any-channel" => Ok(Self::AnyChannel),
+            "rec601" | "601" => Ok(Self::Rec601),
+            "rec709" | "709" => Ok(Self::Rec709),
+            s => Err(LumaModeParseError(s.to_owned())),
+        }
+    }
+}
+
+/// A luma mode was tried to be parsed that doesn't exist
+#[derive(Clone, Debug)]
+#[cfg_attr(
+    feature = "std",
+    derive(thiserror::Error),
+    error("Unsupported luma mode: {0}")
+)]
+pub struct LumaModeParseError(String);
+
+/// Which pixels count towards the bounding box used to trim glyphs
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BboxRule {
+    /// The pixels that are on in the packed glyph
+    #[default]
+    Packing,
+    /// Pixels that are more than half opaque, regardless of their color
+    Alpha,
+    /// Pixels that are darker than half luminance, even if they are fully transparent
+    Luminance,
+}
+
+impl FromStr for BboxRule {
+    type Err = BboxRuleParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "packing" => Ok(Self::Packing),
+            "alpha" => Ok(Self::Alpha),
+            "luminance" => Ok(Self::Luminance),
+            s => Err(BboxRuleParseError(s.to_owned())),
+        }
+    }
+}
+
+/// A bounding box rule was tried to be parsed that doesn't exist
+#[derive(Clone, Debug)]
+#[cfg_attr(
+    feature = "std",
+    derive(thiserror::Error),
+    error("Unsupported bounding box rule: {0}")
+)]
+pub struct BboxRuleParseError(String);
+
 /// In what direction the bits inside a byte flow in a monochromatic font.
-#[derive(Clone, Copy, Debug, Default)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
 pub enum BitFlow {
     /// Most significant bit to least significant bit
     #[default]
@@ -104,7 +694,371 @@ impl FromStr for BitFlow {
     }
 }
 
+impl fmt::Display for BitFlow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Self::Mtl => f.write_str("mtl"),
+            Self::Ltm => f.write_str("ltm"),
+        }
+    }
+}
+
 /// A bit flow was tried to be parsed that doesn't exist
-#[derive(Clone, Debug, thiserror::Error)]
-#[error("Unsupported byte flow: {0}")]
+#[derive(Clone, Debug)]
+#[cfg_attr(
+    feature = "std",
+    derive(thiserror::Error),
+    error("Unsupported byte flow: {0}")
+)]
 pub struct BitFlowParseError(String);
+
+/// In what order the bytes of a column are written in [`FontMode::ByteColumn`] for glyphs taller
+/// than 8 pixels. Every byte holds 8 rows of a column, the bytes of one block of 8 rows are
+/// written for all columns before the next block. In [`FontMode::AlignedColumn`] the blocks are
+/// as tall as the columns.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ColumnByteOrder {
+    /// The block of the top 8 rows comes first
+    #[default]
+    TopFirst,
+    /// The block of the bottom rows, including the alignment padding, comes first
+    BottomFirst,
+}
+
+impl FromStr for ColumnByteOrder {
+    type Err = ColumnByteOrderParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "top-first" | "top" => Ok(Self::TopFirst),
+            "bottom-first" | "bottom" => Ok(Self::BottomFirst),
+            s => Err(ColumnByteOrderParseError(s.to_owned())),
+        }
+    }
+}
+
+/// A column byte order was tried to be parsed that doesn't exist
+#[derive(Clone, Debug)]
+#[cfg_attr(
+    feature = "std",
+    derive(thiserror::Error),
+    error("Unsupported column byte order: {0}")
+)]
+pub struct ColumnByteOrderParseError(String);
+
+display_without_std!(
+    FontModeParseError,
+    PaddingModeParseError,
+    AlphaModeParseError,
+    LumaModeParseError,
+    BboxRuleParseError,
+    BitFlowParseError,
+    ColumnByteOrderParseError
+);
+
+#[cfg(all(test, feature = "std"))]
+mod test {
+    use super::*;
+    use crate::testutil;
+
+    /// The number of written bytes is returned, including a padded last byte
+    #[test]
+    fn written_byte_count() {
+        let png = testutil::gray(16, 16, &[0; 16 * 16]);
+        let mut out = Vec::new();
+        let written = generate_monochromatic(&png, FontMode::Row, BitFlow::Mtl, &mut out).unwrap();
+        assert_eq!(written, 32);
+        assert_eq!(out.len(), 32);
+
+        let png = testutil::gray(3, 3, &[0; 3 * 3]);
+        let written =
+            generate_monochromatic(&png, FontMode::Row, BitFlow::Mtl, &mut Vec::new()).unwrap();
+        assert_eq!(written, 2);
+    }
+
+    /// Decoding the generated font reproduces the pixel grid of the source image
+    #[test]
+    fn decode_round_trip() {
+        let rows = [
+            "#..#.", ".##..", "#...#", "....#", "#####", ".#.#.", "..#..", "#....",
+        ];
+        let png = testutil::picture(&rows);
+        let source = Glyph::decode(&png).unwrap();
+
+        for font_mode in [FontMode::Row, FontMode::Column, FontMode::ByteColumn] {
+            for bit_flow in [BitFlow::Mtl, BitFlow::Ltm] {
+                let font = generate_monochromatic_to_vec(&png, font_mode, bit_flow).unwrap();
+                let decoded = decode_monochromatic(&font, 5, 8, font_mode, bit_flow).unwrap();
+                assert_eq!(Glyph::decode(&decoded).unwrap(), source);
+            }
+        }
+
+        assert!(matches!(
+            decode_monochromatic(&[0; 4], 5, 8, FontMode::Row, BitFlow::Mtl),
+            Err(GenerationError::PixelBufferSize {
+                expected: 5,
+                found: 4
+            })
+        ));
+    }
+
+    /// The header has the size of the glyph and the rows reproduce its pixels
+    #[test]
+    fn pbm_round_trip() {
+        let rows = ["#........#", ".##....##.", "...####..."];
+        let mut out = Vec::new();
+        generate_pbm(&testutil::picture(&rows), &FontGenerator::new(), &mut out).unwrap();
+
+        let header = b"P4\n10 3\n";
+        assert_eq!(out[..header.len()], *header);
+        let bitmap = &out[header.len()..];
+        assert_eq!(bitmap.len(), 2 * 3);
+        for (y, row) in rows.iter().enumerate() {
+            for (x, pixel) in row.bytes().enumerate() {
+                let bit = bitmap[y * 2 + x / 8] >> (7 - x % 8) & 1;
+                assert_eq!(bit == 1, pixel == b'#', "pixel {x}, {y}");
+            }
+            assert_eq!(bitmap[y * 2 + 1] & 0x3F, 0);
+        }
+    }
+
+    /// The last two pixels of a 10x13 glyph are written in a padded byte
+    #[test]
+    fn partial_byte_glyph() {
+        let mut rows = vec![".........."; 12];
+        rows.push("........##");
+        let out =
+            generate_monochromatic_to_vec(&testutil::picture(&rows), FontMode::Row, BitFlow::Mtl)
+                .unwrap();
+        assert_eq!(out.len(), 17);
+        assert_eq!(out[16], 0xC0);
+    }
+
+    /// Reading the png from a reader writes the same bytes as the slice based path
+    #[test]
+    fn monochromatic_from_reader() {
+        let png = testutil::picture(&["#.#.#.#.#", ".#.#.#.#."]);
+        let mut expected = Vec::new();
+        generate_monochromatic(&png, FontMode::Column, BitFlow::Ltm, &mut expected).unwrap();
+
+        let mut out = Vec::new();
+        let reader = std::io::Cursor::new(&png);
+        let written =
+            generate_monochromatic_from_reader(reader, FontMode::Column, BitFlow::Ltm, &mut out)
+                .unwrap();
+        assert_eq!(written, expected.len());
+        assert_eq!(out, expected);
+    }
+
+    /// The buffer holds exactly the bytes written by the writer based path
+    #[test]
+    fn monochromatic_to_vec() {
+        let png = testutil::picture(&["#..##..#", ".#....#.", "..####..", "#......#"]);
+        for font_mode in [FontMode::Row, FontMode::Column, FontMode::ByteColumn] {
+            for bit_flow in [BitFlow::Mtl, BitFlow::Ltm] {
+                let mut out = Vec::new();
+                generate_monochromatic(&png, font_mode, bit_flow, &mut out).unwrap();
+                let vec = generate_monochromatic_to_vec(&png, font_mode, bit_flow).unwrap();
+                assert_eq!(vec, out);
+            }
+        }
+        assert!(matches!(
+            generate_monochromatic_to_vec(b"not a png", FontMode::Row, BitFlow::Mtl),
+            Err(GenerationError::PngDecodingError(_) | GenerationError::ImageDecodingError(_))
+        ));
+    }
+
+    /// A lower threshold turns fewer gray pixels on than a higher one
+    #[test]
+    fn threshold_cutoff() {
+        let png = testutil::gray(8, 1, &[0, 32, 63, 64, 100, 150, 191, 255]);
+        let generate = |threshold| {
+            let mut out = Vec::new();
+            generate_monochromatic_with_threshold(
+                &png,
+                FontMode::Row,
+                BitFlow::Mtl,
+                threshold,
+                &mut out,
+            )
+            .unwrap();
+            out
+        };
+
+        assert_eq!(generate(64), [0b1110_0000]);
+        assert_eq!(generate(192), [0b1111_1110]);
+    }
+
+    /// A blank row below a multiple of 8 rows only appends a zero padded block
+    #[test]
+    fn byte_column_boundary_output() {
+        for boundary in [8, 16, 24] {
+            let rows: Vec<String> = (0..boundary)
+                .map(|y| if y % 3 == 0 { "#.#" } else { ".#." }.to_owned())
+                .collect();
+            let mut extended = rows.clone();
+            extended.push("...".to_owned());
+            let generate = |rows: &[String]| {
+                let rows: Vec<_> = rows.iter().map(String::as_str).collect();
+                let mut out = Vec::new();
+                generate_monochromatic(
+                    &testutil::picture(&rows),
+                    FontMode::ByteColumn,
+                    BitFlow::Mtl,
+                    &mut out,
+                )
+                .unwrap();
+                out
+            };
+
+            let unpadded = generate(&rows);
+            let padded = generate(&extended);
+            assert_eq!(unpadded.len(), 3 * boundary / 8);
+            assert_eq!(padded[..unpadded.len()], unpadded);
+            assert_eq!(padded[unpadded.len()..], [0, 0, 0]);
+        }
+    }
+
+    /// Pixels are emitted in GRB order, off pixels are black
+    #[test]
+    fn ws2812_grb_order() {
+        let png = testutil::rgba(2, 1, &[[0, 0xFF, 0, 0xFF], [0xFF, 0xFF, 0xFF, 0]]);
+        let mut out = Vec::new();
+        generate_ws2812(&png, FontMode::Row, &mut out).unwrap();
+        assert_eq!(out, [0xFF, 0x00, 0x00, 0x00, 0x00, 0x00]);
+    }
+
+    /// A gradient is reduced to the size of the palette
+    #[test]
+    fn ws2812_palette() {
+        let gradient: Vec<_> = (0..16u8)
+            .map(|n| [n * 16, 0xFF - n * 16, 0x40, 0xFF])
+            .collect();
+        let png = testutil::rgba(16, 1, &gradient);
+
+        let distinct = |palette_size| {
+            let mut out = Vec::new();
+            generate_ws2812_with_palette(&png, FontMode::Row, palette_size, &mut out).unwrap();
+            assert_eq!(out.len(), 16 * 3);
+            let mut colors: Vec<_> = out.chunks(3).map(<[u8]>::to_vec).collect();
+            colors.sort_unstable();
+            colors.dedup();
+            colors.len()
+        };
+
+        assert_eq!(distinct(4), 4);
+        assert_eq!(distinct(1), 1);
+        assert_eq!(distinct(32), 16);
+    }
+
+    /// A 2x2 gradient is quantized to 4 bits, two pixels per byte in the order of the bit flow
+    #[test]
+    fn grayscale_gradient() {
+        let png = testutil::gray(2, 2, &[0, 85, 170, 255]);
+        let generate = |bits_per_pixel, bit_flow| {
+            let mut out = Vec::new();
+            generate_grayscale(&png, FontMode::Row, bits_per_pixel, bit_flow, &mut out).unwrap();
+            out
+        };
+
+        assert_eq!(generate(4, BitFlow::Mtl), [0xFA, 0x50]);
+        assert_eq!(generate(4, BitFlow::Ltm), [0xAF, 0x05]);
+        // The 8 bits of the image fit a single byte at 2 bits per pixel
+        assert_eq!(generate(2, BitFlow::Mtl), [0b1110_0100]);
+
+        let png = testutil::gray(3, 1, &[0, 85, 170]);
+        let mut out = Vec::new();
+        generate_grayscale(&png, FontMode::Row, 2, BitFlow::Mtl, &mut out).unwrap();
+        assert_eq!(out, [0b1110_0100]);
+    }
+
+    /// A half transparent white pixel is a mid level gray when alpha modulates the level
+    #[test]
+    fn grayscale_alpha_modulate() {
+        let png = testutil::rgba(2, 1, &[[0xFF, 0xFF, 0xFF, 0x80], [0, 0, 0, 0]]);
+        let generate = |alpha_mode| {
+            let mut out = Vec::new();
+            generate_grayscale_with_alpha(
+                &png,
+                FontMode::Row,
+                4,
+                alpha_mode,
+                BitFlow::Mtl,
+                &mut out,
+            )
+            .unwrap();
+            out
+        };
+
+        assert_eq!(generate(AlphaMode::Modulate), [0x80]);
+        assert_eq!(generate(AlphaMode::Threshold), [0xF0]);
+    }
+}
+
+/// Exercises the packing and decoding with only `core` and `alloc`, this module is only compiled
+/// without the `std` feature
+#[cfg(all(test, not(feature = "std")))]
+mod no_std_test {
+    use alloc::vec::Vec;
+
+    use super::*;
+
+    /// A sink of fixed capacity, like a buffer on a device without allocator
+    struct FixedSink<const N: usize> {
+        /// The storage of the sink
+        bytes: [u8; N],
+        /// The number of bytes put into the sink
+        len: usize,
+    }
+
+    /// The error of a full [`FixedSink`]
+    #[derive(Debug, PartialEq, Eq)]
+    struct Full;
+
+    impl<const N: usize> ByteSink for FixedSink<N> {
+        type Error = Full;
+
+        fn put_bytes(&mut self, bytes: &[u8]) -> Result<(), Self::Error> {
+            let end = self.len.checked_add(bytes.len()).ok_or(Full)?;
+            self.bytes
+                .get_mut(self.len..end)
+                .ok_or(Full)?
+                .copy_from_slice(bytes);
+            self.len = end;
+            Ok(())
+        }
+    }
+
+    /// Pixels packed into a custom sink decode back into the same glyph
+    #[test]
+    fn pack_and_decode() {
+        let pixels = [
+            true, false, false, true, //
+            false, true, true, false, //
+            true, true, false, false,
+        ];
+        let mut sink = FixedSink {
+            bytes: [0; 2],
+            len: 0,
+        };
+        assert_eq!(pack_monochromatic(pixels, BitFlow::Mtl, &mut sink), Ok(2));
+        assert_eq!(sink.bytes, [0x96, 0xC0]);
+
+        let png = decode_monochromatic(&sink.bytes, 4, 3, FontMode::Row, BitFlow::Mtl).unwrap();
+        let decoded: Vec<bool> = MonochromaticColorIter::new(&png, FontMode::Row)
+            .unwrap()
+            .collect();
+        assert_eq!(decoded, pixels);
+    }
+
+    /// A sink that doesn't accept the bytes returns its own error
+    #[test]
+    fn full_sink() {
+        let mut sink = FixedSink {
+            bytes: [0; 1],
+            len: 0,
+        };
+        let res = pack_monochromatic([true; 9], BitFlow::Mtl, &mut sink);
+        assert_eq!(res, Err(Full));
+    }
+}
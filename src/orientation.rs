@@ -0,0 +1,124 @@
+//! Orientation metadata of png images, stored as EXIF data in the `eXIf` chunk.
+
+use zune_png::PngDecoder;
+
+/// The EXIF tag holding the orientation
+const ORIENTATION_TAG: u16 = 0x0112;
+
+/// How an image has to be transformed to be displayed upright, the values of the EXIF
+/// orientation tag
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Orientation {
+    /// The image is stored upright
+    #[default]
+    Normal,
+    /// The image has to be mirrored horizontally
+    MirrorHorizontal,
+    /// The image has to be rotated by 180 degrees
+    Rotate180,
+    /// The image has to be mirrored vertically
+    MirrorVertical,
+    /// The image has to be mirrored along its top left to bottom right diagonal
+    Transpose,
+    /// The image has to be rotated by 90 degrees clockwise
+    Rotate90,
+    /// The image has to be mirrored along its top right to bottom left diagonal
+    Transverse,
+    /// The image has to be rotated by 90 degrees counter clockwise
+    Rotate270,
+}
+
+impl Orientation {
+    /// The orientation of the value of the EXIF orientation tag
+    pub fn from_exif(value: u16) -> Option<Self> {
+        match value {
+            1 => Some(Self::Normal),
+            2 => Some(Self::MirrorHorizontal),
+            3 => Some(Self::Rotate180),
+            4 => Some(Self::MirrorVertical),
+            5 => Some(Self::Transpose),
+            6 => Some(Self::Rotate90),
+            7 => Some(Self::Transverse),
+            8 => Some(Self::Rotate270),
+            _ => None,
+        }
+    }
+
+    /// Wether width and height of the image are swapped by this orientation
+    pub fn swaps_axes(self) -> bool {
+        matches!(
+            self,
+            Self::Transpose | Self::Rotate90 | Self::Transverse | Self::Rotate270
+        )
+    }
+
+    /// The position in the stored image of size `width` x `height` of the pixel at `x`, `y` in
+    /// the upright image
+    #[cfg(feature = "std")]
+    pub(crate) fn source(self, x: usize, y: usize, width: usize, height: usize) -> (usize, usize) {
+        let last_x = |v: usize| width.saturating_sub(1).saturating_sub(v);
+        let last_y = |v: usize| height.saturating_sub(1).saturating_sub(v);
+        match self {
+            Self::Normal => (x, y),
+            Self::MirrorHorizontal => (last_x(x), y),
+            Self::Rotate180 => (last_x(x), last_y(y)),
+            Self::MirrorVertical => (x, last_y(y)),
+            Self::Transpose => (y, x),
+            Self::Rotate90 => (y, last_y(x)),
+            Self::Transverse => (last_x(y), last_y(x)),
+            Self::Rotate270 => (last_x(y), x),
+        }
+    }
+
+    /// Read the orientation of png data, images without orientation metadata and images in other
+    /// formats are upright.
+    ///
+    /// # Errors
+    /// An error is returned when the headers of the png data can not be decoded.
+    pub fn of_png(data: &[u8]) -> crate::Result<Self> {
+        if !crate::imagedecode::is_png(data) {
+            return Ok(Self::Normal);
+        }
+        let mut decoder = PngDecoder::new(data);
+        decoder.decode_headers()?;
+        Ok(decoder
+            .get_info()
+            .and_then(|info| info.exif)
+            .and_then(exif_orientation)
+            .unwrap_or_default())
+    }
+}
+
+/// Find the orientation tag in the first image file directory of EXIF data
+fn exif_orientation(exif: &[u8]) -> Option<Orientation> {
+    let little_endian = match exif.get(..4)? {
+        [0x49, 0x49, 0x2A, 0x00] => true,
+        [0x4D, 0x4D, 0x00, 0x2A] => false,
+        _ => return None,
+    };
+    let u16_at = |pos: usize| -> Option<u16> {
+        let bytes = [*exif.get(pos)?, *exif.get(pos.checked_add(1)?)?];
+        Some(if little_endian {
+            u16::from_le_bytes(bytes)
+        } else {
+            u16::from_be_bytes(bytes)
+        })
+    };
+    let u32_at = |pos: usize| -> Option<u32> {
+        let high = u32::from(u16_at(pos)?);
+        let low = u32::from(u16_at(pos.checked_add(2)?)?);
+        Some(if little_endian {
+            low << 16 | high
+        } else {
+            high << 16 | low
+        })
+    };
+
+    let ifd = usize::try_from(u32_at(4)?).ok()?;
+    let count = u16_at(ifd)?;
+    (0..usize::from(count))
+        .map(|n| ifd.saturating_add(2).saturating_add(n.saturating_mul(12)))
+        .find(|&entry| u16_at(entry) == Some(ORIENTATION_TAG))
+        .and_then(|entry| u16_at(entry.saturating_add(8)))
+        .and_then(Orientation::from_exif)
+}
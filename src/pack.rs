@@ -0,0 +1,417 @@
+//! Packing of monochromatic pixels into bytes.
+
+use alloc::{borrow::ToOwned, string::String, vec, vec::Vec};
+use core::str::FromStr;
+#[cfg(feature = "std")]
+use std::io::Write;
+
+#[cfg(feature = "std")]
+use crate::WriteSink;
+use crate::{
+    imagedecode::{calc_char_size, checked_char_size, scan_index},
+    BitFlow, ByteSink, FontMode,
+};
+
+/// The number of bytes a packed monochromatic glyph of the given size takes
+///
+/// # Errors
+/// An error is returned when the size of the glyph overflows.
+pub(crate) fn packed_len(font_mode: FontMode, width: usize, height: usize) -> crate::Result<usize> {
+    Ok(checked_char_size(font_mode, width, height)?.div_ceil(8))
+}
+
+/// Reverse the packing of a single monochromatic glyph, returns its pixels left to right, top to
+/// bottom. Pixels whose byte is missing are off, the alignment padding of the font mode is
+/// skipped.
+pub(crate) fn unpack_monochromatic(
+    bytes: &[u8],
+    width: usize,
+    height: usize,
+    font_mode: FontMode,
+    bit_flow: BitFlow,
+) -> Vec<bool> {
+    let mut pixels = vec![false; width.saturating_mul(height)];
+    for idx in 0..calc_char_size(font_mode, width, height).unwrap_or(0) {
+        let Some(&byte) = bytes.get(idx / 8) else {
+            break;
+        };
+        let i = 7u8.saturating_sub(u8::try_from(idx % 8).unwrap_or(0));
+        let on = byte & (1 << bit_position(bit_flow, i)) != 0;
+        let pixel = scan_index(font_mode, width, height, idx).and_then(|pos| pixels.get_mut(pos));
+        if let Some(pixel) = pixel {
+            *pixel = on;
+        }
+    }
+    pixels
+}
+
+/// Pack monochromatic pixels into bytes and write them to `sink`, returns the number of written
+/// bytes. The last byte is padded with off pixels like in [`generate_monochromatic`].
+///
+/// This is the packing of the generator without decoding an image, it only needs `core` and
+/// `alloc`, so a `Vec<u8>` sink can't fail:
+///
+/// ```
+/// use embedded_font_generator::{pack_monochromatic, BitFlow};
+///
+/// let mut out = Vec::new();
+/// let Ok(written) = pack_monochromatic([true, false, true], BitFlow::Mtl, &mut out);
+/// assert_eq!((written, out), (1, vec![0xA0]));
+/// ```
+///
+/// # Errors
+/// An error is returned when the sink doesn't accept the bytes.
+///
+/// [`generate_monochromatic`]: crate::generate_monochromatic
+pub fn pack_monochromatic<S: ByteSink>(
+    pixels: impl IntoIterator<Item = bool>,
+    bit_flow: BitFlow,
+    sink: S,
+) -> Result<usize, S::Error> {
+    let mut packer = BitPacker::with_sink(sink, bit_flow);
+    for pix in pixels {
+        packer.push(pix)?;
+    }
+    packer.finish()
+}
+
+/// Pack the levels of grayscale pixels into bytes and write them to `out`, returns the number of
+/// written bytes.
+///
+/// Only the `bits_per_pixel` most significant bits of each level are kept. The pixels are placed
+/// in the byte in the order of the bit flow, the bits of each pixel keep their significance.
+#[cfg(feature = "std")]
+pub(crate) fn pack_grayscale(
+    levels: impl Iterator<Item = u8>,
+    bits_per_pixel: u8,
+    bit_flow: BitFlow,
+    out: &mut impl Write,
+) -> crate::Result<usize> {
+    let mut packer = BitPacker::new(out, bit_flow);
+    for level in levels {
+        let value = level.checked_shr(u32::from(8u8.saturating_sub(bits_per_pixel)));
+        let value = value.unwrap_or(0);
+        for n in 0..bits_per_pixel {
+            let bit = match bit_flow {
+                BitFlow::Mtl => bits_per_pixel.saturating_sub(n).saturating_sub(1),
+                BitFlow::Ltm => n,
+            };
+            packer.push(value & (1 << bit) != 0)?;
+        }
+    }
+    packer.finish()
+}
+
+/// Packs single pixels into bytes according to a bit flow
+pub(crate) struct BitPacker<S> {
+    /// The sink the packed bytes are written to
+    out: S,
+    /// The flow of the bits inside a byte
+    bit_flow: BitFlow,
+    /// Number of pixels in a bit run and the order its bytes are written in
+    bit_run: Option<(usize, BitRunEndianness)>,
+    /// Wether every bit, including the padding, is inverted
+    invert: bool,
+    /// The byte currently being filled
+    cur_byte: u8,
+    /// Index of the next bit to fill, counting down from 7
+    i: u8,
+    /// Bytes of the current bit run
+    run_bytes: Vec<u8>,
+    /// Number of pixels pushed in the current bit run
+    run_pixels: usize,
+    /// Number of bytes written to `out`
+    written: usize,
+}
+
+#[cfg(feature = "std")]
+impl<W: Write> BitPacker<WriteSink<W>> {
+    /// Create a new packer writing to `out`, every byte is written as soon as it is filled.
+    pub(crate) fn new(out: W, bit_flow: BitFlow) -> Self {
+        Self::with_sink(WriteSink(out), bit_flow)
+    }
+}
+
+impl<S: ByteSink> BitPacker<S> {
+    /// Create a new packer writing to `out`, every byte is written as soon as it is filled.
+    pub(crate) fn with_sink(out: S, bit_flow: BitFlow) -> Self {
+        Self {
+            out,
+            bit_flow,
+            bit_run: None,
+            invert: false,
+            cur_byte: 0,
+            i: 7,
+            run_bytes: Vec::new(),
+            run_pixels: 0,
+            written: 0,
+        }
+    }
+
+    /// Group the pixels into runs of `run_len` pixels, each run is padded to full bytes and its
+    /// bytes are written in the given order.
+    #[cfg(feature = "std")]
+    pub(crate) fn with_bit_run(mut self, bit_run: Option<(usize, BitRunEndianness)>) -> Self {
+        self.bit_run = bit_run.filter(|&(run_len, _)| run_len != 0);
+        self
+    }
+
+    /// Invert every written bit, so on pixels are 0 and the padding is 1
+    #[cfg(feature = "std")]
+    pub(crate) fn with_invert(mut self, invert: bool) -> Self {
+        self.invert = invert;
+        self
+    }
+
+    /// Push the next pixel
+    pub(crate) fn push(&mut self, pix: bool) -> Result<(), S::Error> {
+        self.push_bit(pix)?;
+
+        if let Some((run_len, endianness)) = self.bit_run {
+            self.run_pixels = self.run_pixels.saturating_add(1);
+            if self.run_pixels >= run_len {
+                while self.i != 7 {
+                    self.push_bit(false)?;
+                }
+                self.run_pixels = 0;
+                self.write_run(endianness)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Finish packing, returns the number of bytes written.
+    ///
+    /// A trailing byte that is not completely filled is padded with off pixels and written, so
+    /// the padding takes the least significant bits with [`BitFlow::Mtl`] and the most
+    /// significant bits with [`BitFlow::Ltm`].
+    pub(crate) fn finish(mut self) -> Result<usize, S::Error> {
+        self.align()?;
+        Ok(self.written)
+    }
+
+    /// Pad a byte that is not completely filled with off pixels and write it together with the
+    /// current bit run, the next pixel starts a fresh byte.
+    pub(crate) fn align(&mut self) -> Result<(), S::Error> {
+        while self.i != 7 {
+            self.push_bit(false)?;
+        }
+        if let Some((_, endianness)) = self.bit_run {
+            self.run_pixels = 0;
+            self.write_run(endianness)?;
+        }
+        Ok(())
+    }
+
+    /// Put a single bit into the current byte and emit the byte once it is full
+    fn push_bit(&mut self, pix: bool) -> Result<(), S::Error> {
+        self.cur_byte |= u8::from(pix != self.invert) << bit_position(self.bit_flow, self.i);
+        if let Some(v) = self.i.checked_sub(1) {
+            self.i = v;
+        } else {
+            self.emit_byte()?;
+        }
+
+        Ok(())
+    }
+
+    /// The current byte is filled, emit it either into the current run or directly to `out`
+    fn emit_byte(&mut self) -> Result<(), S::Error> {
+        if self.bit_run.is_some() {
+            self.run_bytes.push(self.cur_byte);
+        } else {
+            self.write(&[self.cur_byte])?;
+        }
+        self.i = 7;
+        self.cur_byte = 0;
+        Ok(())
+    }
+
+    /// Write the bytes of the current run in the given endianness
+    fn write_run(&mut self, endianness: BitRunEndianness) -> Result<(), S::Error> {
+        let mut run_bytes = core::mem::take(&mut self.run_bytes);
+        if endianness.reverses(self.bit_flow) {
+            run_bytes.reverse();
+        }
+        self.write(&run_bytes)?;
+        run_bytes.clear();
+        self.run_bytes = run_bytes;
+        Ok(())
+    }
+
+    /// Write all bytes to the sink
+    fn write(&mut self, bytes: &[u8]) -> Result<(), S::Error> {
+        self.out.put_bytes(bytes)?;
+        self.written = self.written.saturating_add(bytes.len());
+        Ok(())
+    }
+}
+
+/// The position of the bit inside a byte for the `i`th pixel, `i` counting down from 7 for the
+/// first pixel of a byte to 0 for the last.
+///
+/// The position only depends on the index, so the bits of a byte can be filled independent of
+/// its previous state.
+fn bit_position(bit_flow: BitFlow, i: u8) -> u8 {
+    match bit_flow {
+        BitFlow::Mtl => i,
+        BitFlow::Ltm => 7u8.saturating_sub(i),
+    }
+}
+
+/// The byte order of bit runs that span more than one byte.
+///
+/// With [`BitFlow::Mtl`] the first pixel of a run is its most significant bit, with
+/// [`BitFlow::Ltm`] it is the least significant bit. A 12 pixel run is for example stored as
+/// 16 bit field and this decides in which order the bytes of this field are written.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BitRunEndianness {
+    /// The byte holding the most significant bits of the run is written first
+    #[default]
+    Big,
+    /// The byte holding the least significant bits of the run is written first
+    Little,
+}
+
+impl BitRunEndianness {
+    /// Wether the bytes of a run have to be written in reverse fill order
+    fn reverses(self, bit_flow: BitFlow) -> bool {
+        matches!(
+            (self, bit_flow),
+            (Self::Little, BitFlow::Mtl) | (Self::Big, BitFlow::Ltm)
+        )
+    }
+}
+
+impl FromStr for BitRunEndianness {
+    type Err = BitRunEndiannessParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "big" | "Big" | "be" | "BE" => Ok(Self::Big),
+            "little" | "Little" | "le" | "LE" => Ok(Self::Little),
+            s => Err(BitRunEndiannessParseError(s.to_owned())),
+        }
+    }
+}
+
+/// A bit run endianness was tried to be parsed that doesn't exist
+#[derive(Clone, Debug)]
+#[cfg_attr(
+    feature = "std",
+    derive(thiserror::Error),
+    error("Unsupported bit run endianness: {0}")
+)]
+pub struct BitRunEndiannessParseError(String);
+
+display_without_std!(BitRunEndiannessParseError);
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[cfg(feature = "std")]
+    use crate::GenerationError;
+
+    /// Pack the pixels given as string of `#` and `.`, a `Vec` sink can't fail
+    fn pack(pixels: &str, bit_flow: BitFlow) -> Vec<u8> {
+        let mut out = Vec::new();
+        let Ok(_) = pack_monochromatic(pixels.chars().map(|c| c == '#'), bit_flow, &mut out);
+        out
+    }
+
+    /// Every pixel of a byte ends up at its exact bit position
+    #[test]
+    fn bit_positions() {
+        assert_eq!(pack("#.......", BitFlow::Ltm), [0x01]);
+        assert_eq!(pack(".......#", BitFlow::Ltm), [0x80]);
+        assert_eq!(pack("##.#....", BitFlow::Ltm), [0x0B]);
+        assert_eq!(pack("#.......", BitFlow::Mtl), [0x80]);
+        assert_eq!(pack(".......#", BitFlow::Mtl), [0x01]);
+        assert_eq!(pack("##.#....", BitFlow::Mtl), [0xD0]);
+        assert_eq!(pack("#..............#", BitFlow::Ltm), [0x01, 0x80]);
+    }
+
+    /// The last partial byte is padded with off pixels in the order of the bit flow
+    #[test]
+    fn partial_byte_flush() {
+        assert_eq!(pack("##", BitFlow::Mtl), [0xC0]);
+        assert_eq!(pack("##", BitFlow::Ltm), [0x03]);
+        assert!(pack("", BitFlow::Mtl).is_empty());
+    }
+
+    /// Inverted pixels and their padding are the complement of the normal output
+    #[cfg(feature = "std")]
+    #[test]
+    fn inverted_padding() {
+        let pack_inverted = |pixels: &str| {
+            let mut out = Vec::new();
+            let mut packer = BitPacker::new(&mut out, BitFlow::Mtl).with_invert(true);
+            for c in pixels.chars() {
+                packer.push(c == '#').unwrap();
+            }
+            packer.finish().unwrap();
+            out
+        };
+        assert_eq!(pack_inverted("#.##...."), [!0xB0]);
+        assert_eq!(pack_inverted("#..."), [0x7F]);
+    }
+
+    /// A writer that never accepts any byte without reporting an error
+    #[cfg(feature = "std")]
+    struct ZeroWriter;
+
+    #[cfg(feature = "std")]
+    impl Write for ZeroWriter {
+        fn write(&mut self, _buf: &[u8]) -> std::io::Result<usize> {
+            Ok(0)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    /// A short write of zero bytes is reported as error instead of silently losing the byte
+    #[cfg(feature = "std")]
+    #[test]
+    fn zero_write_is_error() {
+        assert_eq!(ZeroWriter.write(&[0xFF]).unwrap(), 0);
+
+        let pixels = "#.......".chars().map(|c| c == '#');
+        let res = pack_monochromatic(pixels, BitFlow::Mtl, WriteSink(ZeroWriter));
+        assert!(matches!(
+            res,
+            Err(GenerationError::OutputWriterError(e)) if e.kind() == std::io::ErrorKind::WriteZero
+        ));
+    }
+
+    /// Pack a 12 pixel run
+    #[cfg(feature = "std")]
+    fn pack_run(bit_flow: BitFlow, endianness: BitRunEndianness) -> Vec<u8> {
+        let pixels = "#.......##.#".chars().map(|c| c == '#');
+        let mut out = Vec::new();
+        let mut packer = BitPacker::new(&mut out, bit_flow).with_bit_run(Some((12, endianness)));
+        for pix in pixels {
+            packer.push(pix).unwrap();
+        }
+        assert_eq!(packer.finish().unwrap(), 2);
+        out
+    }
+
+    /// A 12 pixel run is distributed over two bytes in the order of the endianness
+    #[cfg(feature = "std")]
+    #[test]
+    fn bit_run_endianness() {
+        assert_eq!(pack_run(BitFlow::Mtl, BitRunEndianness::Big), [0x80, 0xD0]);
+        assert_eq!(
+            pack_run(BitFlow::Mtl, BitRunEndianness::Little),
+            [0xD0, 0x80]
+        );
+        assert_eq!(
+            pack_run(BitFlow::Ltm, BitRunEndianness::Little),
+            [0x01, 0x0B]
+        );
+        assert_eq!(pack_run(BitFlow::Ltm, BitRunEndianness::Big), [0x0B, 0x01]);
+    }
+}
@@ -0,0 +1,70 @@
+//! Reduction of colors to a small palette.
+
+/// Reduce the colors to a palette of at most `size` colors, but at least one, using median cut.
+///
+/// The colors are split into boxes, the box with the largest range in any channel is split at
+/// the median of this channel until there are `size` boxes. Every box contributes its average
+/// color to the palette.
+pub(crate) fn median_cut(colors: &[[u8; 3]], size: usize) -> Vec<[u8; 3]> {
+    let mut boxes = vec![colors.to_vec()];
+    while boxes.len() < size {
+        let widest = boxes
+            .iter()
+            .enumerate()
+            .map(|(i, colors)| (i, widest_channel(colors)))
+            .max_by_key(|&(_, (_, range))| range);
+        let Some((i, (channel, range))) = widest else {
+            break;
+        };
+        if range == 0 {
+            break;
+        }
+
+        let mut colors = boxes.swap_remove(i);
+        colors.sort_unstable_by_key(|c| c[channel]);
+        let upper = colors.split_off(colors.len() / 2);
+        boxes.push(colors);
+        boxes.push(upper);
+    }
+
+    boxes.iter().filter_map(|colors| average(colors)).collect()
+}
+
+/// The palette color closest to `color`, `color` itself if the palette is empty
+pub(crate) fn nearest(palette: &[[u8; 3]], color: [u8; 3]) -> [u8; 3] {
+    palette
+        .iter()
+        .copied()
+        .min_by_key(|p| {
+            p.iter()
+                .zip(color)
+                .map(|(&a, b)| u32::from(a.abs_diff(b)).pow(2))
+                .sum::<u32>()
+        })
+        .unwrap_or(color)
+}
+
+/// The channel with the largest range of values and this range
+fn widest_channel(colors: &[[u8; 3]]) -> (usize, u8) {
+    (0..3)
+        .map(|channel| {
+            let values = colors.iter().map(|c| c[channel]);
+            let min = values.clone().min().unwrap_or(0);
+            let max = values.max().unwrap_or(0);
+            (channel, max.saturating_sub(min))
+        })
+        .max_by_key(|&(_, range)| range)
+        .unwrap_or((0, 0))
+}
+
+/// The average of all colors, `None` if there are no colors
+fn average(colors: &[[u8; 3]]) -> Option<[u8; 3]> {
+    let len = u32::try_from(colors.len()).ok().filter(|&len| len != 0)?;
+    let mut sum = [0u32; 3];
+    for color in colors {
+        for (sum, &c) in sum.iter_mut().zip(color) {
+            *sum = sum.saturating_add(u32::from(c));
+        }
+    }
+    Some(sum.map(|sum| u8::try_from(sum / len).unwrap_or(u8::MAX)))
+}
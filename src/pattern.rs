@@ -0,0 +1,80 @@
+//! Test pattern glyphs to bring up new displays without any source image.
+
+use std::str::FromStr;
+
+use crate::{FontGenerator, Glyph};
+
+/// A known pattern of pixels
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TestPattern {
+    /// Every pixel is on
+    AllOn,
+    /// Every pixel is off
+    AllOff,
+    /// Pixels alternate between on and off in both directions, the top left pixel is on
+    Checkerboard,
+    /// Only the pixel in every corner is on
+    Corners,
+}
+
+impl TestPattern {
+    /// The pattern as glyph of the given size
+    pub fn glyph(self, width: usize, height: usize) -> Glyph {
+        let last_x = width.saturating_sub(1);
+        let last_y = height.saturating_sub(1);
+        Glyph::from_fn(width, height, |x, y| match self {
+            Self::AllOn => true,
+            Self::AllOff => false,
+            Self::Checkerboard => (x ^ y) & 1 == 0,
+            Self::Corners => (x == 0 || x == last_x) && (y == 0 || y == last_y),
+        })
+    }
+}
+
+/// Generate a glyph of `width` x `height` pixels showing the pattern, packed row by row with the
+/// first pixel as most significant bit like the default [`FontGenerator`].
+pub fn generate_test_pattern(kind: TestPattern, width: usize, height: usize) -> Vec<u8> {
+    let mut out = Vec::new();
+    // Writing to a vec can't fail
+    let _ = FontGenerator::new().write_glyph(&kind.glyph(width, height), &mut out);
+    out
+}
+
+impl FromStr for TestPattern {
+    type Err = TestPatternParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "all-on" | "on" => Ok(Self::AllOn),
+            "all-off" | "off" => Ok(Self::AllOff),
+            "checkerboard" | "checker" => Ok(Self::Checkerboard),
+            "corners" => Ok(Self::Corners),
+            s => Err(TestPatternParseError(s.to_owned())),
+        }
+    }
+}
+
+/// A test pattern was tried to be parsed that doesn't exist
+#[derive(Clone, Debug, thiserror::Error)]
+#[error("Unsupported test pattern: {0}")]
+pub struct TestPatternParseError(String);
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// The rows of an 8x8 checkerboard alternate between 0xAA and 0x55
+    #[test]
+    fn test_patterns() {
+        assert_eq!(
+            generate_test_pattern(TestPattern::Checkerboard, 8, 8),
+            [0xAA, 0x55, 0xAA, 0x55, 0xAA, 0x55, 0xAA, 0x55]
+        );
+        assert_eq!(generate_test_pattern(TestPattern::AllOn, 8, 2), [0xFF; 2]);
+        assert_eq!(generate_test_pattern(TestPattern::AllOff, 8, 2), [0x00; 2]);
+        assert_eq!(
+            generate_test_pattern(TestPattern::Corners, 8, 3),
+            [0x81, 0x00, 0x81]
+        );
+    }
+}
@@ -0,0 +1,321 @@
+//! Summaries of generated fonts.
+
+use std::{
+    collections::BTreeMap,
+    io::{self, Write},
+    path::PathBuf,
+};
+
+use crate::{
+    BitFlow, BoundingBox, Compression, FontGenerator, FontMode, GenerationError, GlyphMetrics,
+};
+
+/// Information about a single glyph written as part of a font
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GlyphRecord {
+    /// The file the glyph was generated from
+    pub path: PathBuf,
+    /// The codepoint derived from the file name
+    pub codepoint: Option<u32>,
+    /// The width of the glyph in pixels
+    pub width: usize,
+    /// The height of the glyph in pixels
+    pub height: usize,
+    /// The offset of the first byte of this glyph inside the font
+    pub offset: usize,
+    /// The number of bytes written for this glyph
+    pub byte_len: usize,
+    /// The CRC-32 of the bytes written for this glyph
+    pub crc: u32,
+    /// How the bytes of this glyph are stored
+    pub compression: Compression,
+    /// The metrics of the glyph
+    pub metrics: GlyphMetrics,
+    /// The number of fully blank rows dropped from the glyph before it was packed
+    pub dropped_rows: usize,
+    /// The number of pixels that are on
+    pub on_count: usize,
+    /// The region of the image the glyph was cropped to, only set if the generator crops glyphs
+    pub crop: Option<BoundingBox>,
+}
+
+impl GlyphRecord {
+    /// The percentage of pixels that are on, including dropped blank rows. An empty glyph has a
+    /// coverage of 0.
+    pub fn coverage(&self) -> f64 {
+        let total = self
+            .width
+            .saturating_mul(self.height.saturating_add(self.dropped_rows));
+        match (u32::try_from(self.on_count), u32::try_from(total)) {
+            (Ok(on), Ok(total)) if total != 0 => f64::from(on) * 100.0 / f64::from(total),
+            _ => 0.0,
+        }
+    }
+}
+
+/// Summary of a complete generation run
+#[derive(Debug)]
+pub struct FontReport {
+    /// The mode the font was generated in
+    pub font_mode: FontMode,
+    /// The flow of the bits inside each byte
+    pub bit_flow: BitFlow,
+    /// All generated glyphs in the order they were written
+    pub glyphs: Vec<GlyphRecord>,
+    /// Warnings that occured during generation
+    pub warnings: Vec<String>,
+    /// Files that were skipped because generating their glyph failed, only collected if the
+    /// generation continues on errors
+    pub errors: Vec<(PathBuf, GenerationError)>,
+    /// Factor the advance widths are multiplied with in the metrics table
+    pub advance_scale: f64,
+    /// Wether the metrics table stores the advance widths as 8.8 fixed point numbers
+    pub advance_fixed_point: bool,
+    /// The number of bytes of the end sentinel written after the last glyph
+    pub sentinel_len: usize,
+    /// The number of bytes written between glyphs to align them to flash pages
+    pub padding_len: usize,
+    /// Wether the metrics table stores the number of dropped blank rows
+    pub collapse_blank_rows: bool,
+}
+
+impl FontReport {
+    /// Create an empty report for a font with the given mode and flow
+    pub fn new(font_mode: FontMode, bit_flow: BitFlow) -> Self {
+        Self {
+            font_mode,
+            bit_flow,
+            glyphs: Vec::new(),
+            warnings: Vec::new(),
+            errors: Vec::new(),
+            advance_scale: 1.0,
+            advance_fixed_point: false,
+            sentinel_len: 0,
+            padding_len: 0,
+            collapse_blank_rows: false,
+        }
+    }
+
+    /// Create an empty report for a font generated by `generator`
+    pub fn for_generator(generator: &FontGenerator) -> Self {
+        Self {
+            advance_scale: generator.advance_scale(),
+            advance_fixed_point: generator.advance_fixed_point(),
+            collapse_blank_rows: generator.collapses_blank_rows(),
+            ..Self::new(generator.font_mode(), generator.bit_flow())
+        }
+    }
+
+    /// The total number of bytes written for all glyphs, the page alignment and the end sentinel
+    pub fn total_bytes(&self) -> usize {
+        self.glyphs.iter().fold(
+            self.sentinel_len.saturating_add(self.padding_len),
+            |acc, g| acc.saturating_add(g.byte_len),
+        )
+    }
+
+    /// A single line summarizing the run
+    pub fn summary(&self) -> String {
+        format!(
+            "Generated {} glyphs with {} bytes in total",
+            self.glyphs.len(),
+            self.total_bytes()
+        )
+    }
+
+    /// Write this report as JSON object.
+    ///
+    /// # Errors
+    /// An error is returned when writing to `out` fails.
+    pub fn write_json(&self, out: &mut impl Write) -> io::Result<()> {
+        write!(out, "{{\"font_mode\":")?;
+        write_json_str(out, &self.font_mode.to_string())?;
+        write!(out, ",\"bit_flow\":")?;
+        write_json_str(out, &self.bit_flow.to_string())?;
+        write!(
+            out,
+            ",\"total_glyphs\":{},\"total_bytes\":{},\"glyphs\":[",
+            self.glyphs.len(),
+            self.total_bytes()
+        )?;
+        for (i, glyph) in self.glyphs.iter().enumerate() {
+            if i != 0 {
+                write!(out, ",")?;
+            }
+            write!(out, "{{\"file\":")?;
+            write_json_str(out, &glyph.path.to_string_lossy())?;
+            match glyph.codepoint {
+                Some(codepoint) => write!(out, ",\"codepoint\":{}", codepoint)?,
+                None => write!(out, ",\"codepoint\":null")?,
+            }
+            write!(
+                out,
+                ",\"width\":{},\"height\":{},\"offset\":{},\"bytes\":{},\"advance\":{},\"y_offset\":{},\"dropped_rows\":{}",
+                glyph.width,
+                glyph.height,
+                glyph.offset,
+                glyph.byte_len,
+                glyph.metrics.advance,
+                glyph.metrics.y_offset,
+                glyph.dropped_rows
+            )?;
+            match glyph.crop {
+                Some(crop) => write!(
+                    out,
+                    ",\"crop\":{{\"x\":{},\"y\":{},\"width\":{},\"height\":{}}}}}",
+                    crop.x, crop.y, crop.width, crop.height
+                )?,
+                None => write!(out, ",\"crop\":null}}")?,
+            }
+        }
+        write!(out, "],\"warnings\":[")?;
+        for (i, warning) in self.warnings.iter().enumerate() {
+            if i != 0 {
+                write!(out, ",")?;
+            }
+            write_json_str(out, warning)?;
+        }
+        write!(out, "],\"errors\":[")?;
+        for (i, (path, error)) in self.errors.iter().enumerate() {
+            if i != 0 {
+                write!(out, ",")?;
+            }
+            write!(out, "{{\"file\":")?;
+            write_json_str(out, &path.to_string_lossy())?;
+            write!(out, ",\"error\":")?;
+            write_json_str(out, &error.to_string())?;
+            write!(out, "}}")?;
+        }
+        writeln!(out, "]}}")
+    }
+
+    /// Write the metrics table, for every glyph the advance width scaled by the advance scale
+    /// followed by one byte y offset and, if blank rows are collapsed, one byte holding the number
+    /// of dropped rows. The advance is either one byte or, for fixed point advances, an unsigned
+    /// little endian 8.8 fixed point number of two bytes. Values too large for their field are
+    /// saturated.
+    ///
+    /// # Errors
+    /// An error is returned when writing to `out` fails.
+    pub fn write_metrics(&self, out: &mut impl Write) -> io::Result<()> {
+        for glyph in &self.glyphs {
+            if self.advance_fixed_point {
+                out.write_all(
+                    &glyph
+                        .metrics
+                        .fixed_point_advance(self.advance_scale)
+                        .to_le_bytes(),
+                )?;
+            } else {
+                out.write_all(&[glyph.metrics.scaled_advance(self.advance_scale)])?;
+            }
+            out.write_all(&[saturate_u8(glyph.metrics.y_offset)])?;
+            if self.collapse_blank_rows {
+                out.write_all(&[saturate_u8(glyph.dropped_rows)])?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Write a human readable manifest with one line per glyph holding its file name, codepoint,
+    /// offset, byte length, width, height and coverage separated by tabs. The first line is a
+    /// header starting with `#`, glyphs without codepoint have a `-` instead.
+    ///
+    /// # Errors
+    /// An error is returned when writing to `out` fails.
+    pub fn write_manifest(&self, out: &mut impl Write) -> io::Result<()> {
+        writeln!(
+            out,
+            "# name\tcodepoint\toffset\tbytes\twidth\theight\tcoverage"
+        )?;
+        for glyph in &self.glyphs {
+            let name = glyph.path.file_name().map_or_else(
+                || glyph.path.to_string_lossy(),
+                |name| name.to_string_lossy(),
+            );
+            let codepoint = glyph
+                .codepoint
+                .map_or_else(|| "-".to_owned(), |cp| format!("U+{:04X}", cp));
+            writeln!(
+                out,
+                "{}\t{}\t{}\t{}\t{}\t{}\t{:.2}%",
+                name,
+                codepoint,
+                glyph.offset,
+                glyph.byte_len,
+                glyph.width,
+                glyph.height,
+                glyph.coverage()
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Write the checksum table, for every glyph the CRC-32 of exactly the bytes written for it,
+    /// including its glyph header and the padding of its last byte, as little endian `u32`. The
+    /// entries are in the order of the glyphs of the report.
+    ///
+    /// # Errors
+    /// An error is returned when writing to `out` fails.
+    pub fn write_checksums(&self, out: &mut impl Write) -> io::Result<()> {
+        for glyph in &self.glyphs {
+            out.write_all(&glyph.crc.to_le_bytes())?;
+        }
+        Ok(())
+    }
+
+    /// Write the codepoint map, for every glyph with a codepoint the codepoint and the number of
+    /// the glyph in the font, both as little endian `u32`. The entries are sorted by codepoint, a
+    /// codepoint shared by several glyphs, like the mirrored copy of a glyph, maps to the first.
+    ///
+    /// # Errors
+    /// An error is returned when writing to `out` fails or a glyph number doesn't fit into `u32`.
+    pub fn write_codepoint_map(&self, out: &mut impl Write) -> io::Result<()> {
+        let mut map = BTreeMap::new();
+        for (n, glyph) in self.glyphs.iter().enumerate() {
+            if let Some(codepoint) = glyph.codepoint {
+                map.entry(codepoint).or_insert(n);
+            }
+        }
+        for (codepoint, n) in map {
+            let n = u32::try_from(n).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+            out.write_all(&codepoint.to_le_bytes())?;
+            out.write_all(&n.to_le_bytes())?;
+        }
+        Ok(())
+    }
+
+    /// Write the advance width table, one byte per glyph holding the advance width scaled by
+    /// the advance scale, saturated at 255.
+    ///
+    /// # Errors
+    /// An error is returned when writing to `out` fails.
+    pub fn write_widths(&self, out: &mut impl Write) -> io::Result<()> {
+        for glyph in &self.glyphs {
+            out.write_all(&[glyph.metrics.scaled_advance(self.advance_scale)])?;
+        }
+        Ok(())
+    }
+}
+
+/// Convert to u8, saturating at `u8::MAX`
+fn saturate_u8(val: usize) -> u8 {
+    u8::try_from(val).unwrap_or(u8::MAX)
+}
+
+/// Write a quoted and escaped JSON string
+fn write_json_str(out: &mut impl Write, s: &str) -> io::Result<()> {
+    write!(out, "\"")?;
+    for c in s.chars() {
+        match c {
+            '"' => write!(out, "\\\"")?,
+            '\\' => write!(out, "\\\\")?,
+            '\n' => write!(out, "\\n")?,
+            '\r' => write!(out, "\\r")?,
+            '\t' => write!(out, "\\t")?,
+            c if c.is_control() => write!(out, "\\u{:04x}", u32::from(c))?,
+            c => write!(out, "{}", c)?,
+        }
+    }
+    write!(out, "\"")
+}
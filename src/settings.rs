@@ -0,0 +1,107 @@
+//! Per directory generation settings read from `.efg` files.
+
+use std::str::FromStr;
+
+use crate::{BitFlow, FontGenerator, FontMode, PaddingMode};
+
+/// Name of the settings file inside a glyph directory
+pub const SETTINGS_FILE_NAME: &str = ".efg";
+
+/// Generation settings of a single glyph directory, overriding the options of the generator.
+///
+/// The settings are given as `key = value` lines, empty lines and lines starting with `#` are
+/// ignored. Supported keys are `mode`, `flow`, `padding` and `threshold` taking the same values as
+/// the command line options.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DirSettings {
+    /// The mode in which the font is generated
+    pub font_mode: Option<FontMode>,
+    /// The flow of the bits inside each byte
+    pub bit_flow: Option<BitFlow>,
+    /// How the alignment padding of the font mode is filled
+    pub padding_mode: Option<PaddingMode>,
+    /// The cutoff deciding wether a pixel is on
+    pub threshold: Option<u8>,
+}
+
+impl DirSettings {
+    /// Apply these settings to a generator, every set value overrides the option of the
+    /// generator.
+    pub fn apply(&self, generator: &FontGenerator) -> FontGenerator {
+        let mut generator = generator.clone();
+        if let Some(font_mode) = self.font_mode {
+            generator = generator.with_mode(font_mode);
+        }
+        if let Some(bit_flow) = self.bit_flow {
+            generator = generator.with_bit_flow(bit_flow);
+        }
+        if let Some(padding_mode) = self.padding_mode {
+            generator = generator.with_padding_mode(padding_mode);
+        }
+        if let Some(threshold) = self.threshold {
+            generator = generator.with_threshold(threshold);
+        }
+        generator
+    }
+}
+
+impl FromStr for DirSettings {
+    type Err = DirSettingsParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut settings = Self::default();
+        for (n, line) in s.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let err = || DirSettingsParseError {
+                line: n.saturating_add(1),
+                content: line.to_owned(),
+            };
+            let (key, value) = line.split_once('=').ok_or_else(err)?;
+            let value = value.trim();
+            match key.trim() {
+                "mode" => settings.font_mode = Some(value.parse().map_err(|_| err())?),
+                "flow" => settings.bit_flow = Some(value.parse().map_err(|_| err())?),
+                "padding" => settings.padding_mode = Some(value.parse().map_err(|_| err())?),
+                "threshold" => settings.threshold = Some(value.parse().map_err(|_| err())?),
+                _ => return Err(err()),
+            }
+        }
+
+        Ok(settings)
+    }
+}
+
+/// A line of a settings file could not be parsed
+#[derive(Debug, Clone, thiserror::Error)]
+#[error("Invalid setting in line {line}: {content}")]
+pub struct DirSettingsParseError {
+    /// The number of the line starting at 1
+    pub line: usize,
+    /// The content of the line
+    pub content: String,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Comments are skipped and unknown keys rejected
+    #[test]
+    fn parse_settings() {
+        let settings: DirSettings =
+            "# glyphs of the status bar\nmode = byte-column\n\nflow=ltm\nthreshold = 64\n"
+                .parse()
+                .unwrap();
+        assert!(matches!(settings.font_mode, Some(FontMode::ByteColumn)));
+        assert!(matches!(settings.bit_flow, Some(BitFlow::Ltm)));
+        assert_eq!(settings.padding_mode, None);
+        assert_eq!(settings.threshold, Some(64));
+
+        let err = "mode = row\nsize = 8".parse::<DirSettings>().unwrap_err();
+        assert_eq!(err.line, 2);
+    }
+}
@@ -0,0 +1,197 @@
+//! Splitting of sprite sheets holding many glyphs in a grid.
+
+use std::io::Write;
+
+use crate::{BitFlow, FontGenerator, FontMode, Glyph};
+
+/// Generate every cell of an atlas holding glyphs in a grid as its own glyph, left to right, top
+/// to bottom, returns the number of bytes written. The atlas is decoded only once, see
+/// [`FontGenerator::generate_atlas`].
+///
+/// # Errors
+/// An error is returned when the image data can not be decoded as png, its width or height is
+/// not a multiple of the cell size or writing to the `out` writer fails.
+pub fn generate_atlas(
+    data: &[u8],
+    cell_width: usize,
+    cell_height: usize,
+    font_mode: FontMode,
+    bit_flow: BitFlow,
+    out: &mut impl Write,
+) -> crate::Result<usize> {
+    FontGenerator::new()
+        .with_mode(font_mode)
+        .with_bit_flow(bit_flow)
+        .generate_atlas(data, cell_width, cell_height, out)
+}
+
+/// The layout of the cells of a sprite sheet.
+///
+/// The first cell starts after the margin in the top left corner, the cells are separated by the
+/// spacing. A sheet with cells of 8x8 pixels and gutters of 1 pixel between them has a spacing
+/// of 1 in both directions.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SheetLayout {
+    /// The width of a single cell in pixels
+    pub cell_width: usize,
+    /// The height of a single cell in pixels
+    pub cell_height: usize,
+    /// Number of pixels left of the first column of cells
+    pub margin_x: usize,
+    /// Number of pixels above the first row of cells
+    pub margin_y: usize,
+    /// Number of pixels between two columns of cells
+    pub spacing_x: usize,
+    /// Number of pixels between two rows of cells
+    pub spacing_y: usize,
+}
+
+impl SheetLayout {
+    /// Create a layout of directly adjacent cells without margin
+    pub fn new(cell_width: usize, cell_height: usize) -> Self {
+        Self {
+            cell_width,
+            cell_height,
+            ..Self::default()
+        }
+    }
+
+    /// Set the margin left and above the cells
+    #[must_use]
+    pub fn with_margin(mut self, margin_x: usize, margin_y: usize) -> Self {
+        self.margin_x = margin_x;
+        self.margin_y = margin_y;
+        self
+    }
+
+    /// Set the spacing between the cells
+    #[must_use]
+    pub fn with_spacing(mut self, spacing_x: usize, spacing_y: usize) -> Self {
+        self.spacing_x = spacing_x;
+        self.spacing_y = spacing_y;
+        self
+    }
+
+    /// The number of complete columns and rows of cells in a sheet of the given size
+    pub fn grid_size(&self, sheet_width: usize, sheet_height: usize) -> (usize, usize) {
+        (
+            cell_count(sheet_width, self.margin_x, self.cell_width, self.spacing_x),
+            cell_count(
+                sheet_height,
+                self.margin_y,
+                self.cell_height,
+                self.spacing_y,
+            ),
+        )
+    }
+
+    /// The position of the top left pixel of the cell in the given column and row
+    pub fn cell_origin(&self, column: usize, row: usize) -> (usize, usize) {
+        (
+            cell_start(column, self.margin_x, self.cell_width, self.spacing_x),
+            cell_start(row, self.margin_y, self.cell_height, self.spacing_y),
+        )
+    }
+
+    /// Split a sheet into the glyphs of all complete cells, left to right, top to bottom
+    pub fn split(&self, sheet: &Glyph) -> Vec<Glyph> {
+        let (columns, rows) = self.grid_size(sheet.width(), sheet.height());
+        (0..rows)
+            .flat_map(|row| (0..columns).map(move |column| (column, row)))
+            .map(|(column, row)| {
+                let (x, y) = self.cell_origin(column, row);
+                sheet.crop(x, y, self.cell_width, self.cell_height)
+            })
+            .collect()
+    }
+}
+
+/// The number of complete cells along one axis of a sheet
+fn cell_count(len: usize, margin: usize, cell: usize, spacing: usize) -> usize {
+    let Some(available) = len.checked_sub(margin) else {
+        return 0;
+    };
+    // The last cell needs no spacing after it
+    available
+        .saturating_add(spacing)
+        .checked_div(cell.saturating_add(spacing))
+        .unwrap_or(0)
+}
+
+/// The first pixel of the `n`th cell along one axis of a sheet
+fn cell_start(n: usize, margin: usize, cell: usize, spacing: usize) -> usize {
+    n.saturating_mul(cell.saturating_add(spacing))
+        .saturating_add(margin)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{testutil, GenerationError};
+
+    /// A 16x8 atlas holds two 8x8 glyphs, other cell sizes are rejected
+    #[test]
+    fn atlas_cells() {
+        let atlas = testutil::picture(&[
+            "#.......########",
+            ".#......#......#",
+            "..#.....#......#",
+            "...#....#......#",
+            "....#...#......#",
+            ".....#..#......#",
+            "......#.#......#",
+            ".......#########",
+        ]);
+        let mut out = Vec::new();
+        let written = generate_atlas(&atlas, 8, 8, FontMode::Row, BitFlow::Mtl, &mut out).unwrap();
+        assert_eq!(written, 16);
+        assert_eq!(
+            out,
+            [
+                0x80, 0x40, 0x20, 0x10, 0x08, 0x04, 0x02, 0x01, 0xFF, 0x81, 0x81, 0x81, 0x81, 0x81,
+                0x81, 0xFF
+            ]
+        );
+
+        let res = generate_atlas(&atlas, 6, 8, FontMode::Row, BitFlow::Mtl, &mut Vec::new());
+        assert!(matches!(
+            res,
+            Err(GenerationError::AtlasDimensionMismatch {
+                width: 16,
+                height: 8,
+                cell_width: 6,
+                cell_height: 8
+            })
+        ));
+    }
+
+    /// The gutters between the cells are not part of any glyph
+    #[test]
+    fn split_with_gutters() {
+        let sheet = testutil::picture(&[
+            "#########",
+            "#####.#.#",
+            "##.#.###.",
+            "#########",
+            "#..####.#",
+            "##.#..###",
+        ]);
+        let sheet = Glyph::decode(&sheet).unwrap();
+        let layout = SheetLayout::new(2, 2).with_margin(1, 1).with_spacing(1, 1);
+        assert_eq!(layout.grid_size(sheet.width(), sheet.height()), (3, 2));
+
+        let expected = [
+            ["##", "#."],
+            ["#.", ".#"],
+            [".#", "#."],
+            ["..", "#."],
+            ["##", ".."],
+            [".#", "##"],
+        ];
+        let glyphs = layout.split(&sheet);
+        assert_eq!(glyphs.len(), expected.len());
+        for (glyph, rows) in glyphs.iter().zip(expected) {
+            assert_eq!(*glyph, Glyph::decode(&testutil::picture(&rows)).unwrap());
+        }
+    }
+}
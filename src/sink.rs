@@ -0,0 +1,58 @@
+//! Sinks the packed bytes of a font are written to.
+//!
+//! The packing of pixels only relies on `core` and `alloc`, it writes to a [`ByteSink`]. Writers
+//! of `std` are adapted with [`WriteSink`].
+
+use alloc::vec::Vec;
+use core::convert::Infallible;
+#[cfg(feature = "std")]
+use std::io::Write;
+
+#[cfg(feature = "std")]
+use crate::GenerationError;
+
+/// A sink accepting the packed bytes of a font
+pub trait ByteSink {
+    /// The error returned when the sink doesn't accept the bytes
+    type Error;
+
+    /// Append all bytes to the sink.
+    ///
+    /// # Errors
+    /// An error is returned when the sink doesn't accept all bytes.
+    fn put_bytes(&mut self, bytes: &[u8]) -> Result<(), Self::Error>;
+}
+
+impl ByteSink for Vec<u8> {
+    type Error = Infallible;
+
+    fn put_bytes(&mut self, bytes: &[u8]) -> Result<(), Self::Error> {
+        self.extend_from_slice(bytes);
+        Ok(())
+    }
+}
+
+impl<S: ByteSink + ?Sized> ByteSink for &mut S {
+    type Error = S::Error;
+
+    fn put_bytes(&mut self, bytes: &[u8]) -> Result<(), Self::Error> {
+        (**self).put_bytes(bytes)
+    }
+}
+
+/// Adapter writing the bytes to a `std` writer, a writer that doesn't accept all bytes is a
+/// [`GenerationError::OutputWriterError`]
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub struct WriteSink<W>(pub W);
+
+#[cfg(feature = "std")]
+impl<W: Write> ByteSink for WriteSink<W> {
+    type Error = GenerationError;
+
+    fn put_bytes(&mut self, bytes: &[u8]) -> Result<(), Self::Error> {
+        self.0
+            .write_all(bytes)
+            .map_err(GenerationError::OutputWriterError)
+    }
+}
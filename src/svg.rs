@@ -0,0 +1,69 @@
+//! Rasterization of SVG glyph sources.
+
+use std::io::Write;
+
+use resvg::{tiny_skia, usvg};
+
+use crate::{generate_from_pixels, FontGenerator, GenerationError};
+
+/// Rasterize an SVG image scaled to `width` x `height` pixels and generate a glyph from it with
+/// the options of the generator, see [`generate_from_pixels`].
+///
+/// Returns the number of bytes written.
+///
+/// # Errors
+/// An error is returned when the SVG can not be parsed, the size is zero or writing to the `out`
+/// writer fails.
+pub fn generate_from_svg(
+    svg: &[u8],
+    width: u32,
+    height: u32,
+    generator: &FontGenerator,
+    out: &mut impl Write,
+) -> crate::Result<usize> {
+    let tree = usvg::Tree::from_data(svg, &usvg::Options::default())
+        .map_err(|e| GenerationError::SvgError(e.to_string()))?;
+    let mut pixmap = tiny_skia::Pixmap::new(width, height)
+        .ok_or_else(|| GenerationError::SvgError(format!("Invalid size {width}x{height}")))?;
+
+    let size = tree.size();
+    let transform = tiny_skia::Transform::from_scale(
+        to_f32(width) / size.width(),
+        to_f32(height) / size.height(),
+    );
+    resvg::render(&tree, transform, &mut pixmap.as_mut());
+
+    generate_from_pixels(
+        pixmap.data(),
+        usize::try_from(width).unwrap_or(usize::MAX),
+        usize::try_from(height).unwrap_or(usize::MAX),
+        generator,
+        out,
+    )
+}
+
+/// Convert a pixel size to a float for scaling
+fn to_f32(val: u32) -> f32 {
+    // Sizes of glyphs are far below the precision limit of f32
+    #![allow(clippy::as_conversions, clippy::cast_precision_loss)]
+    val as f32
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// The rectangle is filled exactly in the scaled output
+    #[test]
+    fn svg_rectangle() {
+        let svg =
+            br#"<svg xmlns="http://www.w3.org/2000/svg" width="16" height="8" viewBox="0 0 16 8">
+            <rect x="4" y="2" width="8" height="4" fill="black"/>
+        </svg>"#;
+
+        let mut out = Vec::new();
+        let written = generate_from_svg(svg, 8, 4, &FontGenerator::new(), &mut out).unwrap();
+        assert_eq!(written, 4);
+        assert_eq!(out, [0x00, 0x3C, 0x3C, 0x00]);
+    }
+}
@@ -0,0 +1,185 @@
+//! Helpers to build small png images in memory for tests.
+
+use alloc::{vec, vec::Vec};
+
+/// Png color type for 8 bit grayscale images
+pub const GRAY: u8 = 0;
+/// Png color type for 8 bit rgb images
+pub const RGB: u8 = 2;
+/// Png color type for 8 bit palette images
+pub const INDEXED: u8 = 3;
+/// Png color type for 8 bit rgba images
+pub const RGBA: u8 = 6;
+
+/// Encode raw 8 bit samples as png without any filtering or compression.
+pub fn png(width: u32, height: u32, color_type: u8, samples: &[u8]) -> Vec<u8> {
+    png_with_palette(width, height, color_type, 8, &[], samples)
+}
+
+/// Encode packed samples as png, a `PLTE` chunk is written if the palette is not empty.
+fn png_with_palette(
+    width: u32,
+    height: u32,
+    color_type: u8,
+    bit_depth: u8,
+    palette: &[[u8; 3]],
+    samples: &[u8],
+) -> Vec<u8> {
+    let row_len = samples.len() / height.max(1) as usize;
+    let mut raw = Vec::new();
+    for row in samples.chunks(row_len.max(1)) {
+        // Filter type none
+        raw.push(0);
+        raw.extend_from_slice(row);
+    }
+
+    let mut ihdr = Vec::new();
+    ihdr.extend_from_slice(&width.to_be_bytes());
+    ihdr.extend_from_slice(&height.to_be_bytes());
+    ihdr.extend_from_slice(&[bit_depth, color_type, 0, 0, 0]);
+
+    let mut out = b"\x89PNG\r\n\x1a\n".to_vec();
+    write_chunk(&mut out, b"IHDR", &ihdr);
+    if !palette.is_empty() {
+        write_chunk(&mut out, b"PLTE", &palette.concat());
+    }
+    write_chunk(&mut out, b"IDAT", &zlib_stored(&raw));
+    write_chunk(&mut out, b"IEND", &[]);
+    out
+}
+
+/// Encode a grayscale image, every value is one pixel.
+pub fn gray(width: u32, height: u32, pixels: &[u8]) -> Vec<u8> {
+    png(width, height, GRAY, pixels)
+}
+
+/// Encode a 16 bit grayscale image, every value is one pixel.
+pub fn gray16(width: u32, height: u32, pixels: &[u16]) -> Vec<u8> {
+    let samples: Vec<u8> = pixels.iter().flat_map(|val| val.to_be_bytes()).collect();
+    png_with_palette(width, height, GRAY, 16, &[], &samples)
+}
+
+/// Encode a palette image with `bit_depth` bits per pixel, every value is the palette index of
+/// one pixel. The indices of every row are packed most significant bits first.
+pub fn indexed(
+    width: u32,
+    height: u32,
+    bit_depth: u8,
+    palette: &[[u8; 3]],
+    indices: &[u8],
+) -> Vec<u8> {
+    let packed = pack_samples(width, bit_depth, indices);
+    png_with_palette(width, height, INDEXED, bit_depth, palette, &packed)
+}
+
+/// Encode a grayscale image with `bit_depth` bits per pixel below 8, every value is the level of
+/// one pixel from 0 to `2^bit_depth - 1`.
+pub fn gray_bits(width: u32, height: u32, bit_depth: u8, levels: &[u8]) -> Vec<u8> {
+    let packed = pack_samples(width, bit_depth, levels);
+    png_with_palette(width, height, GRAY, bit_depth, &[], &packed)
+}
+
+/// Pack samples of `bit_depth` bits, most significant bits first, every row starts on a byte.
+fn pack_samples(width: u32, bit_depth: u8, samples: &[u8]) -> Vec<u8> {
+    let per_byte = (8 / bit_depth) as usize;
+    samples
+        .chunks(width.max(1) as usize)
+        .flat_map(|row| {
+            row.chunks(per_byte).map(|pixels| {
+                pixels.iter().enumerate().fold(0u8, |byte, (i, &sample)| {
+                    byte | sample << (8 - bit_depth as usize * (i + 1))
+                })
+            })
+        })
+        .collect()
+}
+
+/// Encode an rgba image.
+pub fn rgba(width: u32, height: u32, pixels: &[[u8; 4]]) -> Vec<u8> {
+    png(width, height, RGBA, &pixels.concat())
+}
+
+/// Encode an rgb image without alpha channel.
+pub fn rgb(width: u32, height: u32, pixels: &[[u8; 3]]) -> Vec<u8> {
+    png(width, height, RGB, &pixels.concat())
+}
+
+/// Encode a black and white image from a textual picture, `#` is black and every other character
+/// is white.
+pub fn picture(rows: &[&str]) -> Vec<u8> {
+    let width = rows.first().map_or(0, |r| r.len());
+    let pixels: Vec<u8> = rows
+        .iter()
+        .flat_map(|r| r.bytes().map(|b| if b == b'#' { 0 } else { 255 }))
+        .collect();
+    gray(width as u32, rows.len() as u32, &pixels)
+}
+
+/// Insert an `eXIf` chunk holding the EXIF orientation tag after the header of a png.
+pub fn with_exif_orientation(png: &[u8], orientation: u16) -> Vec<u8> {
+    let mut exif = b"II*\0".to_vec();
+    exif.extend_from_slice(&8u32.to_le_bytes());
+    exif.extend_from_slice(&1u16.to_le_bytes());
+    exif.extend_from_slice(&0x0112u16.to_le_bytes());
+    exif.extend_from_slice(&3u16.to_le_bytes());
+    exif.extend_from_slice(&1u32.to_le_bytes());
+    exif.extend_from_slice(&orientation.to_le_bytes());
+    exif.extend_from_slice(&[0, 0, 0, 0, 0, 0]);
+
+    // Signature and IHDR chunk with 13 bytes of data
+    let header_len = 8 + 12 + 13;
+    let mut out = png[..header_len].to_vec();
+    write_chunk(&mut out, b"eXIf", &exif);
+    out.extend_from_slice(&png[header_len..]);
+    out
+}
+
+/// Append a png chunk with its length and crc.
+fn write_chunk(out: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let start = out.len();
+    out.extend_from_slice(kind);
+    out.extend_from_slice(data);
+    let crc = crc32(&out[start..]);
+    out.extend_from_slice(&crc.to_be_bytes());
+}
+
+/// Wrap data in a zlib stream made of stored deflate blocks.
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    let mut out = vec![0x78, 0x01];
+    let mut chunks = data.chunks(u16::MAX as usize).peekable();
+    if chunks.peek().is_none() {
+        out.extend_from_slice(&[1, 0, 0, 0xFF, 0xFF]);
+    }
+    while let Some(chunk) = chunks.next() {
+        out.push(u8::from(chunks.peek().is_none()));
+        let len = chunk.len() as u16;
+        out.extend_from_slice(&len.to_le_bytes());
+        out.extend_from_slice(&(!len).to_le_bytes());
+        out.extend_from_slice(chunk);
+    }
+
+    let (mut a, mut b) = (1u32, 0u32);
+    for &byte in data {
+        a = (a + u32::from(byte)) % 65521;
+        b = (b + a) % 65521;
+    }
+    out.extend_from_slice(&((b << 16) | a).to_be_bytes());
+    out
+}
+
+/// Crc32 as used by png.
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in data {
+        crc ^= u32::from(byte);
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
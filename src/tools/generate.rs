@@ -2,12 +2,20 @@
 
 use std::{
     fs::{self, File},
-    io::{self, BufWriter, Write},
+    io::{self, BufWriter, Read, Write},
     path::{Path, PathBuf},
     str::FromStr,
 };
 
-use embedded_font_generator::{BitFlow, FontMode, GenerationError};
+use embedded_font_generator::{
+    dump_with_options, generate_dir_split, generate_dir_with_progress, generate_eg_mono,
+    generate_eg_mono_tiled, generate_pbm, generate_reader, generate_ws2812,
+    generate_ws2812_with_palette, inspect_png, make_patch, pack_bins, verify_dump, write_patch,
+    BboxRule, BitFlow, BitRunEndianness, CodepointRanges, ColumnByteOrder, Dither, DumpFormat,
+    DumpOptions, EndSentinel, FlushPolicy, FontGenerator, FontHeader, FontMode, FontReport,
+    GenerationError, GlyphIndex, IndexPosition, LumaMode, OnColor, PaddingMode, Progress, Rgba,
+    TestPattern,
+};
 
 xflags::xflags! {
     /// Tool to convert png images to a simple bitmap font format readable in embedded software.
@@ -17,30 +25,267 @@ xflags::xflags! {
         /// The mode in which the font should be generated
         ///
         /// row: Each row is read and written directly to the font file, there is no alignment
+        /// column: Each column is read top to bottom, left to right, there is no alignment
         /// column-byte: 8 Pixel Columns are read from left to right and then top to bottom,
         ///              the data is byte aligned in multiples of 8.
+        ///              column-16 and column-32 align to 16 and 32 pixels instead
         optional -m, --mode mode: FontMode
+        /// Align the columns to 8, 16 or 32 pixels, selects the column mode with this alignment
+        optional --column-align column_align: u8
         /// The flow in which the bits inside a byte flow
         ///
         /// big: The first read pixel is the most significant bit
         /// small: The first read pixel is the least significant bit
         optional -f, --flow flow: BitFlow
+        /// The order of the bytes of a column in the column-byte mode for glyphs taller than 8
+        /// pixels
+        ///
+        /// top-first: The byte of the top 8 rows comes first
+        /// bottom-first: The byte of the bottom rows comes first
+        optional --column-byte-order column_byte_order: ColumnByteOrder
+        /// How the padding of the column-byte mode is filled
+        ///
+        /// zero: All padding pixels are off
+        /// one: All padding pixels are on
+        /// repeat-edge: The padding repeats the last row of the image
+        optional --padding padding: PaddingMode
+        /// Group this many scanned pixels into one bit run which is padded to full bytes
+        optional --bit-run bit_run: usize
+        /// Start a fresh byte after this many scanned pixels, for images holding several glyphs
+        optional --glyph-stride glyph_stride: usize
+        /// A pixel is on if it has this color, written as RRGGBB, instead of by its luminance
+        optional --on-color on_color: Rgba
+        /// The largest difference of a color component to the on color that is still on
+        optional --tolerance tolerance: u8
+        /// The order of the bytes of a bit run
+        ///
+        /// big: The byte holding the most significant bits is written first
+        /// little: The byte holding the least significant bits is written first
+        optional --run-endianness run_endianness: BitRunEndianness
+        /// Preset for ST7920 LCDs: row mode, big flow and every glyph row padded to full bytes,
+        /// overrides the mode and flow
+        optional --st7920
+        /// Emit glyphs without any on pixel as blank glyph of this size, given as WIDTHxHEIGHT
+        optional --blank-cell blank_cell: CellSize
+        /// Reorder the glyphs so similar glyphs are adjacent, the index keeps the original order
+        optional --optimize-order
+        /// Only generate the glyphs of a directory with these codepoints, e.g. 0x30-0x39,0x41-0x5A
+        ///
+        /// The codepoint is derived from the file name: a single character, a hex number like
+        /// 0041 or U+0041.
+        optional --include-codepoints include_codepoints: CodepointRanges
+        /// Skip the files of a directory whose name is no codepoint, like A.png, 0041.png or
+        /// U+0041.png
+        optional --codepoint-from-filename
+        /// Write the codepoint and number of every glyph with a codepoint, sorted by codepoint,
+        /// as pairs of little endian u32 to this path
+        optional --codepoint-map codepoint_map: PathBuf
+        /// Pixels at least this opaque with a color component below it are on, 128 by default
+        optional --threshold threshold: u8
+        /// How the color of opaque pixels is compared against the threshold
+        ///
+        /// any: The pixel is on if any color component is below the threshold, in images with
+        /// alpha every opaque pixel is on
+        /// rec601: The luminance weighted as in Rec. 601 is below the threshold
+        /// rec709: The luminance weighted as in Rec. 709 is below the threshold
+        optional --luma luma: LumaMode
+        /// Reject images with more pixels than this, including the alignment padding, 2^24 by
+        /// default
+        optional --max-image-pixels max_image_pixels: usize
+        /// Map the luminance of every pixel through the 256 byte table in this file before it is
+        /// thresholded, to match the gamma curve of a display
+        optional --luminance-lut luminance_lut: PathBuf
+        /// Fail if a glyph has less than this percentage of on pixels
+        optional --min-coverage min_coverage: f64
+        /// Skip files of a directory that fail to generate and report all errors at the end
+        optional --continue-on-error
+        /// Generate the files in the subdirectories of a directory too
+        optional --recursive
+        /// Fail on files of a directory that are no images instead of skipping them
+        optional --strict
+        /// Fail if the images of a directory differ in size, for fonts with a fixed cell size
+        optional --uniform
+        /// Follow every glyph by a mask plane, the glyph dilated by one pixel
+        optional --mask-plane
+        /// Rotate and mirror images as described by their EXIF orientation before packing
+        optional --normalize-orientation
+        /// Drop the fully blank rows of every glyph in row mode, the metrics table stores the
+        /// number of dropped rows after the y offset
+        optional --collapse-blank-rows
+        /// Write on pixels as 0 and off pixels as 1, including the padding
+        optional --invert
+        /// Distribute midtones into a pattern, none, ordered with an optional Bayer matrix size,
+        /// e.g. ordered:8, or floyd-steinberg error diffusion. Ordered dithering replaces the
+        /// threshold
+        optional --dither dither: Dither
+        /// Preset for self describing variable width fonts, every glyph is trimmed and prefixed
+        /// by a width, height and advance byte
+        optional --self-describing
+        /// Remove the blank columns left and right of every glyph, for proportional fonts
+        optional --trim
+        /// Crop every glyph to the bounding box of its on pixels, the crop offsets are part of
+        /// the report
+        optional --auto-crop
+        /// Which pixels count towards the bounding box the glyphs are trimmed to
+        ///
+        /// packing: The pixels that are on in the generated font
+        /// alpha: Pixels that are more than half opaque
+        /// luminance: Pixels darker than half luminance, even if transparent
+        optional --bbox-rule bbox_rule: BboxRule
+        /// Append a sentinel after the last glyph, given as BYTE or BYTE:LEN like 0xff:8. Without
+        /// a length the sentinel is as long as the last glyph.
+        optional --end-sentinel end_sentinel: EndSentinel
+        /// Fail if a glyph is packed to more than this number of bytes
+        optional --max-glyph-bytes max_glyph_bytes: usize
+        /// Start every glyph at a multiple of this many bytes, e.g. the 256 byte pages of NOR
+        /// flash, the gaps are filled with 0xff
+        optional --glyph-page-align glyph_page_align: usize
+        /// Append a horizontally mirrored copy of the font after the normal glyphs
+        optional --with-mirror
+        /// Write a header with the magic EFNT, the mode, the bit flow and the glyph size before
+        /// the font
+        optional --with-header
+        /// Don't print the file names while generating a directory, the summary is still printed
+        optional -q, --quiet
+        /// Write the glyph index table to this path
+        optional --index index: PathBuf
+        /// Store the CRC-32 of every glyph in the glyph index
+        optional --index-crc
+        /// Run length encode every glyph that gets smaller, the glyph index stores a compression
+        /// flag for every glyph: 0 for plain and 1 for run length encoded bytes
+        optional --compress
+        /// Embed the glyph index in the generated font of a directory
+        ///
+        /// start: The table is written before the glyphs, the offsets include its size
+        /// end: The table is appended after the glyphs, followed by its offset as u32
+        optional --index-position index_position: IndexPosition
+        /// Write a JSON summary of the generation run to this path
+        optional --report report: PathBuf
+        /// Write the advance width and y offset of every glyph to this path
+        optional --metrics metrics: PathBuf
+        /// Write a text manifest with the name, codepoint, offset, byte length, size and
+        /// coverage of every glyph to this path
+        optional --manifest-txt manifest_txt: PathBuf
+        /// When the generated font of a directory is flushed: end, glyph or after a number of
+        /// bytes
+        optional --flush flush: FlushPolicy
+        /// Force the advance width of the glyphs with these codepoints, given as RANGES:WIDTH like
+        /// 0x30-0x39:6 for tabular digits
+        optional --force-width force_width: ForcedWidth
+        /// Write the advance width of every glyph as one byte to this path
+        optional --widths widths: PathBuf
+        /// Write the CRC-32 of the bytes of every glyph as little endian u32 to this path
+        optional --checksums checksums: PathBuf
+        /// Multiply the advance widths of the metrics and width table by this factor
+        optional --advance-scale advance_scale: f64
+        /// Store the advance widths of the metrics as two byte 8.8 fixed point numbers
+        optional --advance-fixed-point
         /// Generate a complete directory
+        ///
+        /// A .efg file in the directory with lines like `mode = byte-column` overrides the mode,
+        /// flow, padding and threshold for this directory.
         cmd generate-dir {
+            /// Write every glyph to its own .bin file named after its image in this directory
+            /// instead of concatenating them
+            optional --split-output split_output: PathBuf
+            /// Path to the directory
+            required dir_path: PathBuf
+        }
+        /// Concatenate the already packed .bin glyphs of a directory, use --index to write the
+        /// glyph index with the codepoints derived from the file names
+        cmd pack-bins {
+            /// The size of every glyph as WIDTHxHEIGHT
+            required cell_size: CellSize
             /// Path to the directory
             required dir_path: PathBuf
         }
-        /// Generate a single file as font
+        /// Generate a test pattern glyph without any source image to calibrate a display
+        cmd generate-test-pattern {
+            /// The pattern: all-on, all-off, checkerboard or corners
+            required pattern: TestPattern
+            /// The size of the glyph as WIDTHxHEIGHT
+            required cell_size: CellSize
+        }
+        /// Generate the files as font, concatenated in the given order
         cmd generate-file {
+            /// Paths to the files, a single png is read from stdin if no path is given
+            repeated file_paths: PathBuf
+        }
+        /// Generate every cell of an atlas holding the glyphs in a grid as its own glyph, left to
+        /// right, top to bottom
+        cmd generate-atlas {
+            /// The number of columns of cells
+            required --cols cols: usize
+            /// The number of rows of cells
+            required --rows rows: usize
+            /// Path to the atlas, the png is read from stdin if no path is given
+            optional file_path: PathBuf
+        }
+        /// Generate an atlas of glyphs as image data of an embedded-graphics MonoFont, every row
+        /// of the strip image padded to a byte
+        cmd generate-eg-mono {
+            /// The width of a glyph in pixels
+            required --glyph-width glyph_width: usize
+            /// The height of a glyph in pixels
+            required --glyph-height glyph_height: usize
+            /// The number of glyphs per row of the strip, defaults to the columns of the atlas
+            optional --columns columns: usize
+            /// Path to the atlas, the png is read from stdin if no path is given
+            optional file_path: PathBuf
+        }
+        /// Generate the files as binary portable bitmaps (P4), concatenated in the given order
+        cmd generate-pbm {
+            /// Paths to the files
+            repeated file_paths: PathBuf
+        }
+        /// Generate the GRB pixel data of a single file for WS2812 led matrices
+        cmd generate-ws2812 {
+            /// Reduce the colors of the file to a palette of this size
+            optional --palette-size palette_size: usize
             /// Path to the file
             required file_path: PathBuf
         }
+        /// Check that dumping a file in a format reproduces its bytes exactly, the bytes parsed
+        /// back from the dump are written to the output if given
+        cmd verify {
+            /// Remove a font header from the checked bytes like dump does
+            optional --strip-header
+            /// The format to check
+            required format: DumpFormat
+            /// The file to check
+            required file_path: PathBuf
+        }
+        /// Write the binary patch turning the old font into the new font. The patch starts with
+        /// the length of the new font as little endian u32, every record after it is a u32
+        /// offset and u32 length followed by the new bytes
+        cmd make-patch {
+            /// The font to patch
+            required old_path: PathBuf
+            /// The font after applying the patch
+            required new_path: PathBuf
+        }
         /// Dump a file
         cmd dump {
+            /// Copy the dump to the clipboard instead of printing it, requires the clipboard
+            /// feature
+            optional --clipboard
+            /// Place the array of the c format in the flash of AVR microcontrollers
+            optional --progmem
+            /// The name of the array of the c format, font by default
+            optional --identifier identifier: String
+            /// Define the length of the array of the c format, e.g. FONT_LEN
+            optional --define-len
+            /// The width of every glyph, required by the ascii format
+            optional --width width: usize
+            /// The height of every glyph, required by the ascii format
+            optional --height height: usize
+            /// Remove a font header from the dumped bytes, its mode, bit flow and glyph size are
+            /// used unless given as flags
+            optional --strip-header
             /// The format to dump to
             required format: DumpFormat
-            /// The file to dump
-            required file_path: PathBuf
+            /// The file to dump, it is read from stdin if no path is given
+            optional file_path: PathBuf
         }
     }
 }
@@ -48,109 +293,671 @@ xflags::xflags! {
 fn main() {
     let args = App::from_env_or_exit();
 
-    if let Err(e) = run(args) {
+    if let Err(e) = run(args, &mut io::stdin().lock()) {
         eprintln!("Error while generating font: {}", e);
     }
 }
 
-/// Run the command with the given arguments
-fn run(args: App) -> embedded_font_generator::Result<()> {
-    let font_mode = args.mode.unwrap_or_default();
-    let bit_flow = args.flow.unwrap_or_default();
-    match args.subcommand {
-        AppCmd::GenerateFile(GenerateFile { file_path }) => match args.output {
-            Some(out_path) => {
-                let f = File::create(&out_path).map_err(GenerationError::IoError)?;
-                generate_file(&file_path, font_mode, bit_flow, &mut BufWriter::new(f))
+/// The name of the input shown in messages when it is read from stdin
+const STDIN_NAME: &str = "<stdin>";
+
+/// The name of the input at `path` shown in messages, stdin if there is no path
+fn input_name(path: Option<&Path>) -> &Path {
+    path.unwrap_or(Path::new(STDIN_NAME))
+}
+
+/// Read the whole file at `path`, or all of `stdin` if there is no path. The bytes of stdin are
+/// read raw, without any newline translation.
+fn read_input(path: Option<&Path>, stdin: &mut dyn Read) -> io::Result<Vec<u8>> {
+    match path {
+        Some(path) => fs::read(path),
+        None => {
+            let mut data = Vec::new();
+            stdin.read_to_end(&mut data)?;
+            Ok(data)
+        }
+    }
+}
+
+/// Open the file at `path` for reading, or `stdin` if there is no path
+fn open_input<'a>(path: Option<&Path>, stdin: &'a mut dyn Read) -> io::Result<Box<dyn Read + 'a>> {
+    match path {
+        Some(path) => Ok(Box::new(File::open(path)?)),
+        None => Ok(Box::new(stdin)),
+    }
+}
+
+/// Run the command with the given arguments, `stdin` is read by commands given no input path
+fn run(args: App, stdin: &mut dyn Read) -> embedded_font_generator::Result<()> {
+    let mode = match args.column_align {
+        Some(align) => FontMode::aligned_column(align)?,
+        None => args.mode.unwrap_or_default(),
+    };
+    let mut generator = FontGenerator::new()
+        .with_mode(mode)
+        .with_bit_flow(args.flow.unwrap_or_default())
+        .with_column_byte_order(args.column_byte_order.unwrap_or_default())
+        .with_glyph_stride(args.glyph_stride)
+        .with_on_color(args.on_color.map(|target| OnColor {
+            target,
+            tolerance: args.tolerance.unwrap_or(0),
+        }))
+        .with_padding_mode(args.padding.unwrap_or_default())
+        .with_optimize_order(args.optimize_order)
+        .with_mirror(args.with_mirror)
+        .with_trim(args.trim)
+        .with_auto_crop(args.auto_crop)
+        .with_compress(args.compress)
+        .with_normalize_orientation(args.normalize_orientation)
+        .with_collapse_blank_rows(args.collapse_blank_rows)
+        .with_invert(args.invert)
+        .with_bbox_rule(args.bbox_rule.unwrap_or_default())
+        .with_dither(args.dither.unwrap_or_default())
+        .with_luma_mode(args.luma.unwrap_or_default())
+        .with_flush_policy(args.flush.unwrap_or_default())
+        .with_mask_plane(args.mask_plane)
+        .with_continue_on_error(args.continue_on_error)
+        .with_recursive(args.recursive)
+        .with_strict(args.strict)
+        .with_uniform(args.uniform)
+        .with_advance_fixed_point(args.advance_fixed_point);
+    if args.st7920 {
+        generator = generator.with_st7920();
+    }
+    if args.self_describing {
+        generator = generator.with_self_describing();
+    }
+    if let Some(run_len) = args.bit_run {
+        generator = generator.with_bit_run(run_len, args.run_endianness.unwrap_or_default());
+    }
+    generator = generator.with_codepoint_from_filename(args.codepoint_from_filename);
+    if let Some(include) = args.include_codepoints {
+        generator = generator.with_include_codepoints(include);
+    }
+    if let Some(sentinel) = args.end_sentinel {
+        generator = generator.with_end_sentinel(sentinel);
+    }
+    if let Some(scale) = args.advance_scale {
+        generator = generator.with_advance_scale(scale);
+    }
+    if let Some(page_size) = args.glyph_page_align {
+        generator = generator.with_glyph_page_align(page_size);
+    }
+    if let Some(max) = args.max_glyph_bytes {
+        generator = generator.with_max_glyph_bytes(max);
+    }
+    if let Some(threshold) = args.threshold {
+        generator = generator.with_threshold(threshold);
+    }
+    if let Some(max) = args.max_image_pixels {
+        generator = generator.with_max_image_pixels(max);
+    }
+    if let Some(lut_path) = args.luminance_lut {
+        let lut = fs::read(lut_path)?;
+        let lut = lut
+            .as_slice()
+            .try_into()
+            .map_err(|_| GenerationError::InvalidLuminanceLut(lut.len()))?;
+        generator = generator.with_luminance_lut(lut);
+    }
+    if let Some(ForcedWidth { codepoints, width }) = args.force_width {
+        generator =
+            generator.with_force_width(codepoints.codepoints().map(|cp| (cp, width)).collect());
+    }
+    if let Some(min_coverage) = args.min_coverage {
+        generator = generator.with_min_coverage(min_coverage);
+    }
+    if let Some(CellSize { width, height }) = args.blank_cell {
+        generator = generator.with_blank_cell(width, height);
+    }
+
+    let report = match args.subcommand {
+        AppCmd::GenerateFile(GenerateFile { file_paths }) => {
+            let mut report = FontReport::for_generator(&generator);
+            let mut out: Box<dyn Write> = match args.output {
+                Some(out_path) => {
+                    let f = File::create(&out_path).map_err(GenerationError::IoError)?;
+                    Box::new(BufWriter::new(f))
+                }
+                None => Box::new(io::stdout().lock()),
+            };
+            // The header can only be built once all glyphs are generated
+            let mut font = Vec::new();
+            let mut offset = 0usize;
+            let inputs: Vec<Option<&Path>> = if file_paths.is_empty() {
+                vec![None]
+            } else {
+                file_paths.iter().map(|path| Some(path.as_path())).collect()
+            };
+            for file_path in inputs {
+                let input = open_input(file_path, stdin)?;
+                let name = input_name(file_path);
+                let mut record = if args.with_header {
+                    generate_reader(name, input, &generator, &mut font)?
+                } else {
+                    generate_reader(name, input, &generator, &mut out)?
+                };
+                record.offset = offset;
+                offset = offset.saturating_add(record.byte_len);
+                report.glyphs.push(record);
             }
-            None => generate_file(&file_path, font_mode, bit_flow, &mut io::stdout().lock()),
-        },
-        AppCmd::GenerateDir(GenerateDir { dir_path }) => match args.output {
-            Some(out_path) => {
-                let f = File::create(&out_path).map_err(GenerationError::IoError)?;
-                generate_dir(&dir_path, font_mode, bit_flow, &mut BufWriter::new(f))
+            if args.with_header {
+                FontHeader::for_report(&report).write(&mut out)?;
+                out.write_all(&font)
+                    .map_err(GenerationError::OutputWriterError)?;
             }
-            None => generate_dir(&dir_path, font_mode, bit_flow, &mut io::stdout().lock()),
-        },
-        AppCmd::Dump(Dump { format, file_path }) => {
-            let bytes = fs::read(file_path)?;
-            match format {
-                DumpFormat::Binary => {
-                    for (byte, i) in bytes.into_iter().zip(1..) {
-                        print!("{:08b} ", byte);
-                        if i % 8 == 0 {
-                            println!("");
-                        }
-                    }
-                    println!("");
+            out.flush().map_err(GenerationError::OutputWriterError)?;
+            report
+        }
+        AppCmd::GenerateDir(GenerateDir {
+            split_output: Some(out_dir),
+            dir_path,
+        }) => {
+            let report = generate_dir_split(&dir_path, &generator, &out_dir)?;
+            eprintln!("{}", report.summary());
+            report
+        }
+        AppCmd::GenerateDir(GenerateDir {
+            split_output: None,
+            dir_path,
+        }) => {
+            let to_stdout = args.output.is_none();
+            let progress = |p: Progress<'_>| match p {
+                Progress::Glyph(path) if !args.quiet => {
+                    eprintln!("Generating for {}", path.display());
+                }
+                Progress::Glyph(_) => {}
+                // The font on stdout doesn't end with a line break, start a new line so the
+                // summary is not appended to the binary data in a terminal
+                Progress::Summary(summary) if to_stdout => eprintln!("\n{}", summary),
+                Progress::Summary(summary) => eprintln!("{}", summary),
+            };
+            let mut out: Box<dyn Write> = match args.output {
+                Some(out_path) => {
+                    let f = File::create(&out_path).map_err(GenerationError::IoError)?;
+                    Box::new(BufWriter::new(f))
                 }
-                DumpFormat::Hex => {
-                    for (byte, i) in bytes.into_iter().zip(1..) {
-                        print!("{:#04x} ", byte);
-                        if i % 8 == 0 {
-                            println!("");
-                        }
-                    }
-                    println!("");
+                None => Box::new(io::stdout().lock()),
+            };
+            let report = if args.index_position.is_some() || args.with_header {
+                // The index and the header can only be built once all glyphs are generated
+                let mut font = Vec::new();
+                let report =
+                    generate_dir_with_progress(&dir_path, &generator, &mut font, progress)?;
+                if args.with_header {
+                    FontHeader::for_report(&report).write(&mut out)?;
                 }
+                match args.index_position {
+                    Some(position) => GlyphIndex::from_report(&report)?
+                        .with_crc(args.index_crc)
+                        .with_compression(args.compress)
+                        .write_embedded(&font, position, &mut out)?,
+                    None => out
+                        .write_all(&font)
+                        .map_err(GenerationError::OutputWriterError)?,
+                }
+                report
+            } else {
+                generate_dir_with_progress(&dir_path, &generator, &mut out, progress)?
+            };
+            out.flush()?;
+            report
+        }
+        AppCmd::PackBins(PackBins {
+            cell_size: CellSize { width, height },
+            dir_path,
+        }) => {
+            let mut out: Box<dyn Write> = match args.output {
+                Some(out_path) => Box::new(BufWriter::new(File::create(out_path)?)),
+                None => Box::new(io::stdout().lock()),
+            };
+            let report = pack_bins(&dir_path, (width, height), &mut out)?;
+            out.flush()?;
+            report
+        }
+        AppCmd::GenerateTestPattern(GenerateTestPattern {
+            pattern,
+            cell_size: CellSize { width, height },
+        }) => {
+            let glyph = pattern.glyph(width, height);
+            let mut out: Box<dyn Write> = match args.output {
+                Some(out_path) => Box::new(BufWriter::new(File::create(out_path)?)),
+                None => Box::new(io::stdout().lock()),
+            };
+            generator.write_glyph(&glyph, &mut out)?;
+            return out.flush().map_err(GenerationError::OutputWriterError);
+        }
+        AppCmd::GenerateAtlas(GenerateAtlas {
+            cols,
+            rows,
+            file_path,
+        }) => {
+            let data = read_input(file_path.as_deref(), stdin)?;
+            let info = inspect_png(&data, generator.font_mode())?;
+            let cell_width = info.width.checked_div(cols).unwrap_or(0);
+            let cell_height = info.height.checked_div(rows).unwrap_or(0);
+            if cell_width.saturating_mul(cols) != info.width
+                || cell_height.saturating_mul(rows) != info.height
+            {
+                return Err(GenerationError::AtlasDimensionMismatch {
+                    width: info.width,
+                    height: info.height,
+                    cell_width,
+                    cell_height,
+                });
             }
-            Ok(())
+            let mut out: Box<dyn Write> = match args.output {
+                Some(out_path) => Box::new(BufWriter::new(File::create(out_path)?)),
+                None => Box::new(io::stdout().lock()),
+            };
+            let written = generator.generate_atlas(&data, cell_width, cell_height, &mut out)?;
+            if !args.quiet {
+                let name = input_name(file_path.as_deref());
+                eprintln!("{}: {} bytes", name.display(), written);
+            }
+            return out.flush().map_err(GenerationError::OutputWriterError);
         }
+        AppCmd::GenerateEgMono(GenerateEgMono {
+            glyph_width,
+            glyph_height,
+            columns,
+            file_path,
+        }) => {
+            let data = read_input(file_path.as_deref(), stdin)?;
+            let mut out: Box<dyn Write> = match args.output {
+                Some(out_path) => Box::new(BufWriter::new(File::create(out_path)?)),
+                None => Box::new(io::stdout().lock()),
+            };
+            let written = match columns {
+                Some(columns) => {
+                    generate_eg_mono_tiled(&data, glyph_width, glyph_height, columns, &mut out)?
+                }
+                None => generate_eg_mono(&data, glyph_width, glyph_height, &mut out)?,
+            };
+            if !args.quiet {
+                let name = input_name(file_path.as_deref());
+                eprintln!("{}: {} bytes", name.display(), written);
+            }
+            return out.flush().map_err(GenerationError::OutputWriterError);
+        }
+        AppCmd::GeneratePbm(GeneratePbm { file_paths }) => {
+            let mut out: Box<dyn Write> = match args.output {
+                Some(out_path) => Box::new(BufWriter::new(File::create(out_path)?)),
+                None => Box::new(io::stdout().lock()),
+            };
+            for file_path in file_paths {
+                generate_pbm(&fs::read(file_path)?, &generator, &mut out)?;
+            }
+            return out.flush().map_err(GenerationError::OutputWriterError);
+        }
+        AppCmd::GenerateWs2812(GenerateWs2812 {
+            palette_size,
+            file_path,
+        }) => {
+            let data = fs::read(file_path)?;
+            let font_mode = generator.font_mode();
+            return match args.output {
+                Some(out_path) => {
+                    let mut f = BufWriter::new(File::create(out_path)?);
+                    write_ws2812(&data, font_mode, palette_size, &mut f)?;
+                    f.flush().map_err(GenerationError::OutputWriterError)
+                }
+                None => write_ws2812(&data, font_mode, palette_size, &mut io::stdout().lock()),
+            };
+        }
+        AppCmd::Verify(Verify {
+            strip_header,
+            format,
+            file_path,
+        }) => {
+            let data = fs::read(&file_path)?;
+            let (bytes, options) = dump_input(data, strip_header, args.mode, args.flow, None)?;
+            let parsed = verify_dump(&bytes, format, &options)?;
+            if let Some(out_path) = args.output {
+                fs::write(out_path, &parsed)?;
+            }
+            eprintln!("Verified {} bytes of {}", bytes.len(), file_path.display());
+            return Ok(());
+        }
+        AppCmd::MakePatch(MakePatch { old_path, new_path }) => {
+            let new = fs::read(new_path)?;
+            let patch = make_patch(&fs::read(old_path)?, &new);
+            let mut out: Box<dyn Write> = match args.output {
+                Some(out_path) => Box::new(BufWriter::new(File::create(out_path)?)),
+                None => Box::new(io::stdout().lock()),
+            };
+            write_patch(new.len(), &patch, &mut out)?;
+            out.flush()?;
+            eprintln!("Wrote {} patch records", patch.len());
+            return Ok(());
+        }
+        AppCmd::Dump(Dump {
+            format,
+            file_path,
+            clipboard,
+            progmem,
+            identifier,
+            define_len,
+            width,
+            height,
+            strip_header,
+        }) => {
+            let data = read_input(file_path.as_deref(), stdin)?;
+            let glyph_size = width.zip(height);
+            let (bytes, options) =
+                dump_input(data, strip_header, args.mode, args.flow, glyph_size)?;
+            let options = DumpOptions {
+                progmem,
+                identifier,
+                define_len,
+                ..options
+            };
+            if clipboard {
+                return copy_to_clipboard(&bytes, format, &options);
+            }
+            return dump_with_options(&bytes, format, &options, &mut io::stdout().lock());
+        }
+    };
+
+    if !args.quiet {
+        for glyph in &report.glyphs {
+            eprintln!("{}: {} bytes", glyph.path.display(), glyph.byte_len);
+        }
+    }
+    for warning in &report.warnings {
+        eprintln!("Warning: {}", warning);
+    }
+    for (path, e) in &report.errors {
+        eprintln!("Error while generating {}: {}", path.display(), e);
+    }
+    if let Some(report_path) = args.report {
+        let mut f = BufWriter::new(File::create(report_path)?);
+        report.write_json(&mut f)?;
+        f.flush()?;
+    }
+    if let Some(index_path) = args.index {
+        let mut f = BufWriter::new(File::create(index_path)?);
+        GlyphIndex::from_report(&report)?
+            .with_crc(args.index_crc)
+            .with_compression(args.compress)
+            .write(&mut f)?;
+        f.flush()?;
+    }
+    if let Some(widths_path) = args.widths {
+        let mut f = BufWriter::new(File::create(widths_path)?);
+        report.write_widths(&mut f)?;
+        f.flush()?;
     }
+    if let Some(map_path) = args.codepoint_map {
+        let mut f = BufWriter::new(File::create(map_path)?);
+        report.write_codepoint_map(&mut f)?;
+        f.flush()?;
+    }
+    if let Some(checksums_path) = args.checksums {
+        let mut f = BufWriter::new(File::create(checksums_path)?);
+        report.write_checksums(&mut f)?;
+        f.flush()?;
+    }
+    if let Some(manifest_path) = args.manifest_txt {
+        let mut f = BufWriter::new(File::create(manifest_path)?);
+        report.write_manifest(&mut f)?;
+        f.flush()?;
+    }
+    if let Some(metrics_path) = args.metrics {
+        let mut f = BufWriter::new(File::create(metrics_path)?);
+        report.write_metrics(&mut f)?;
+        f.flush()?;
+    }
+
+    Ok(())
 }
 
-/// generate single letter file
-fn generate_file(
-    file_path: &Path,
+/// The bytes of a dump and the options rendering them, shared by dump and verify so both see the
+/// same bytes. A font header at the start of the data is
+/// printed to stderr and only removed with `strip_header`, its mode, bit flow and glyph size then
+/// fill in the ones not given.
+fn dump_input(
+    data: Vec<u8>,
+    strip_header: bool,
+    font_mode: Option<FontMode>,
+    bit_flow: Option<BitFlow>,
+    glyph_size: Option<(usize, usize)>,
+) -> embedded_font_generator::Result<(Vec<u8>, DumpOptions)> {
+    let parsed = FontHeader::parse(&data)?;
+    if let Some((header, _)) = parsed {
+        eprintln!("{}", header);
+    }
+    // Without stripping the dump holds every byte of the input, the header included
+    let (header, bytes) = match parsed {
+        Some((header, bitmap)) if strip_header => (Some(header), bitmap.to_vec()),
+        _ => (None, data),
+    };
+    let header_size = header
+        .and_then(|h| h.glyph_size)
+        .map(|(w, h)| (usize::from(w), usize::from(h)));
+    let options = DumpOptions {
+        glyph_size: glyph_size.or(header_size),
+        font_mode: font_mode
+            .or(header.map(|h| h.font_mode))
+            .unwrap_or_default(),
+        bit_flow: bit_flow.or(header.map(|h| h.bit_flow)).unwrap_or_default(),
+        ..DumpOptions::default()
+    };
+    Ok((bytes, options))
+}
+
+/// Generate the WS2812 pixel data, with the colors reduced to a palette if a size is given
+fn write_ws2812(
+    data: &[u8],
     font_mode: FontMode,
-    bit_flow: BitFlow,
-    mut out: &mut dyn Write,
+    palette_size: Option<usize>,
+    out: &mut impl Write,
 ) -> embedded_font_generator::Result<()> {
-    let data = fs::read(file_path).map_err(GenerationError::IoError)?;
+    match palette_size {
+        Some(palette_size) => generate_ws2812_with_palette(data, font_mode, palette_size, out),
+        None => generate_ws2812(data, font_mode, out),
+    }
+}
 
-    embedded_font_generator::generate_monochromatic(&data, font_mode, bit_flow, &mut out)
+/// Copy the rendered dump to the system clipboard
+#[cfg(feature = "clipboard")]
+fn copy_to_clipboard(
+    bytes: &[u8],
+    format: DumpFormat,
+    options: &DumpOptions,
+) -> embedded_font_generator::Result<()> {
+    let mut clipboard = embedded_font_generator::SystemClipboard::new()?;
+    embedded_font_generator::dump_to_clipboard(bytes, format, options, &mut clipboard)
 }
 
-/// Generate all images in a directory as font
-fn generate_dir(
-    dir_path: &Path,
-    font_mode: FontMode,
-    bit_flow: BitFlow,
-    out: &mut dyn Write,
+/// Copy the rendered dump to the system clipboard
+#[cfg(not(feature = "clipboard"))]
+fn copy_to_clipboard(
+    _bytes: &[u8],
+    _format: DumpFormat,
+    _options: &DumpOptions,
 ) -> embedded_font_generator::Result<()> {
-    let dir_ents = fs::read_dir(dir_path)?;
-    let mut entries: Vec<_> = dir_ents.filter_map(|res| res.ok()).collect();
-    entries.sort_unstable_by_key(|ent| ent.file_name());
-    for ent in entries {
-        eprintln!("Generating for {}", ent.path().display());
-        generate_file(&ent.path(), font_mode, bit_flow, out)?;
-    }
+    Err(GenerationError::ClipboardError(
+        "built without the clipboard feature".to_owned(),
+    ))
+}
 
-    Ok(())
+/// An advance width forced for a set of codepoints
+#[derive(Debug, Clone)]
+pub struct ForcedWidth {
+    /// The codepoints of the glyphs
+    codepoints: CodepointRanges,
+    /// The advance width in pixels
+    width: usize,
+}
+
+impl FromStr for ForcedWidth {
+    type Err = ForcedWidthParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (codepoints, width) = s
+            .rsplit_once(':')
+            .ok_or_else(|| ForcedWidthParseError(s.to_owned()))?;
+        match (codepoints.parse(), width.parse()) {
+            (Ok(codepoints), Ok(width)) => Ok(Self { codepoints, width }),
+            _ => Err(ForcedWidthParseError(s.to_owned())),
+        }
+    }
 }
 
-/// The format to show the dump in
+/// An error that occurs when a forced width is not given as RANGES:WIDTH
+#[derive(Debug, Clone, thiserror::Error)]
+#[error("Invalid forced width, expected RANGES:WIDTH: {0}")]
+pub struct ForcedWidthParseError(String);
+
+/// The size of a glyph cell
 #[derive(Debug, Clone, Copy)]
-pub enum DumpFormat {
-    /// Binary number
-    Binary,
-    /// Hexadecimal numbers
-    Hex,
+pub struct CellSize {
+    /// Width of the cell in pixels
+    width: usize,
+    /// Height of the cell in pixels
+    height: usize,
 }
 
-impl FromStr for DumpFormat {
-    type Err = DumpFormatParseError;
+impl FromStr for CellSize {
+    type Err = CellSizeParseError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match s {
-            "binary" => Ok(Self::Binary),
-            "hex" => Ok(Self::Hex),
-            s => Err(DumpFormatParseError(s.to_owned())),
+        let (width, height) = s
+            .split_once('x')
+            .ok_or_else(|| CellSizeParseError(s.to_owned()))?;
+        match (width.parse(), height.parse()) {
+            (Ok(width), Ok(height)) => Ok(Self { width, height }),
+            _ => Err(CellSizeParseError(s.to_owned())),
         }
     }
 }
 
-/// An error that occurs when trying to parse a dump format that doesn't exist
+/// An error that occurs when a cell size is not given as WIDTHxHEIGHT
 #[derive(Debug, Clone, thiserror::Error)]
-#[error("Unsupported dump format: {0}")]
-pub struct DumpFormatParseError(String);
+#[error("Invalid cell size, expected WIDTHxHEIGHT: {0}")]
+pub struct CellSizeParseError(String);
+
+#[cfg(test)]
+mod test {
+    use std::ffi::OsString;
+
+    use super::*;
+    use embedded_font_generator::decode_monochromatic;
+
+    /// A glyph piped to `generate-file` without a path is read from stdin and generated unchanged
+    #[test]
+    fn generate_from_stdin() {
+        let packed = [0xF0, 0x0F, 0xAA, 0x55, 0x00, 0xFF, 0x81, 0x18];
+        let png = decode_monochromatic(&packed, 8, 8, FontMode::Row, BitFlow::Mtl).unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let out_path = dir.path().join("font.bin");
+        let args = App::from_vec(vec![
+            "--quiet".into(),
+            "-o".into(),
+            out_path.clone().into(),
+            "generate-file".into(),
+        ])
+        .unwrap();
+
+        run(args, &mut io::Cursor::new(png)).unwrap();
+        assert_eq!(fs::read(&out_path).unwrap(), packed);
+    }
+
+    /// An atlas piped to `generate-atlas` without a path is read from stdin, flags after the
+    /// command are still parsed
+    #[test]
+    fn generate_atlas_from_stdin() {
+        let atlas = decode_monochromatic(&[0xF0, 0x0F], 8, 2, FontMode::Row, BitFlow::Mtl).unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let out_path = dir.path().join("font.bin");
+        let args = App::from_vec(vec![
+            "--quiet".into(),
+            "generate-atlas".into(),
+            "--cols".into(),
+            "2".into(),
+            "--rows".into(),
+            "1".into(),
+            "-o".into(),
+            out_path.clone().into(),
+        ])
+        .unwrap();
+
+        run(args, &mut io::Cursor::new(atlas)).unwrap();
+        assert_eq!(fs::read(&out_path).unwrap(), [0xF0, 0x0F]);
+    }
+
+    /// Several files are concatenated in the order of the arguments, not sorted by name
+    #[test]
+    fn generate_files_in_order() {
+        let dir = tempfile::tempdir().unwrap();
+        let glyphs = [("c.png", 0xC3), ("a.png", 0x18), ("b.png", 0x7E)];
+        let mut args: Vec<OsString> = vec![
+            "-o".into(),
+            dir.path().join("font.bin").into(),
+            "--quiet".into(),
+            "generate-file".into(),
+        ];
+        for (name, byte) in glyphs {
+            let png = decode_monochromatic(&[byte], 8, 1, FontMode::Row, BitFlow::Mtl).unwrap();
+            fs::write(dir.path().join(name), png).unwrap();
+            args.push(dir.path().join(name).into());
+        }
+
+        run(App::from_vec(args).unwrap(), &mut io::empty()).unwrap();
+        assert_eq!(
+            fs::read(dir.path().join("font.bin")).unwrap(),
+            [0xC3, 0x18, 0x7E]
+        );
+    }
+
+    /// A font header is only removed from the dumped bytes with `--strip-header`, its glyph size
+    /// and mode are used only then
+    #[test]
+    fn dump_keeps_header() {
+        let bitmap = [0xF0, 0x0F, 0xAA];
+        let mut data = Vec::new();
+        FontHeader::new(FontMode::ByteColumn, BitFlow::Ltm)
+            .with_glyph_size(3, 8)
+            .write(&mut data)
+            .unwrap();
+        data.extend_from_slice(&bitmap);
+
+        let (bytes, options) = dump_input(data.clone(), false, None, None, None).unwrap();
+        assert_eq!(bytes, data);
+        assert_eq!(options.glyph_size, None);
+        assert_eq!(options.font_mode, FontMode::Row);
+
+        let (bytes, options) = dump_input(data, true, None, None, None).unwrap();
+        assert_eq!(bytes, bitmap);
+        assert_eq!(options.glyph_size, Some((3, 8)));
+        assert_eq!(options.font_mode, FontMode::ByteColumn);
+        assert_eq!(options.bit_flow, BitFlow::Ltm);
+    }
+
+    /// Verify checks the bytes dump would print, with the font header unless it is stripped
+    #[test]
+    fn verify_like_dump() {
+        let dir = tempfile::tempdir().unwrap();
+        let font_path = dir.path().join("font.bin");
+        let out_path = dir.path().join("parsed.bin");
+        let mut font = Vec::new();
+        FontHeader::new(FontMode::Row, BitFlow::Mtl)
+            .with_glyph_size(8, 2)
+            .write(&mut font)
+            .unwrap();
+        font.extend_from_slice(&[0x81, 0x7E]);
+        fs::write(&font_path, &font).unwrap();
+
+        let verify = |flags: &[&str]| {
+            let mut args: Vec<OsString> = vec!["-o".into(), out_path.clone().into()];
+            args.extend(["verify", "hex"].iter().chain(flags).map(Into::into));
+            args.push(font_path.clone().into());
+            run(App::from_vec(args).unwrap(), &mut io::empty())
+        };
+        verify(&[]).unwrap();
+        assert_eq!(fs::read(&out_path).unwrap(), font);
+        verify(&["--strip-header"]).unwrap();
+        assert_eq!(fs::read(&out_path).unwrap(), [0x81, 0x7E]);
+    }
+}